@@ -0,0 +1,157 @@
+use crate::color::Color;
+
+/// How a source color is combined with the pixel already in the framebuffer.
+///
+/// The Porter-Duff operators follow "Compositing Digital Images" (1984); the
+/// separable modes (`Multiply`, `Screen`) follow the W3C Compositing spec and
+/// are composited with source-over.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    Clear,
+    /// Replaces the destination, alpha included.
+    Source,
+    Destination,
+    #[default]
+    SourceOver,
+    DestinationOver,
+    SourceIn,
+    DestinationIn,
+    SourceOut,
+    DestinationOut,
+    SourceAtop,
+    DestinationAtop,
+    Xor,
+    Multiply,
+    Screen,
+    /// Adds source and destination, saturating at white.
+    Additive,
+}
+
+impl BlendMode {
+    // Porter-Duff fractions (Fa, Fb) applied to the source and destination
+    fn factors(self, src_alpha: f32, dst_alpha: f32) -> (f32, f32) {
+        match self {
+            BlendMode::Clear => (0.0, 0.0),
+            BlendMode::Source => (1.0, 0.0),
+            BlendMode::Destination => (0.0, 1.0),
+            BlendMode::SourceOver | BlendMode::Multiply | BlendMode::Screen => (1.0, 1.0 - src_alpha),
+            BlendMode::DestinationOver => (1.0 - dst_alpha, 1.0),
+            BlendMode::SourceIn => (dst_alpha, 0.0),
+            BlendMode::DestinationIn => (0.0, src_alpha),
+            BlendMode::SourceOut => (1.0 - dst_alpha, 0.0),
+            BlendMode::DestinationOut => (0.0, 1.0 - src_alpha),
+            BlendMode::SourceAtop => (dst_alpha, 1.0 - src_alpha),
+            BlendMode::DestinationAtop => (1.0 - dst_alpha, src_alpha),
+            BlendMode::Xor => (1.0 - dst_alpha, 1.0 - src_alpha),
+            BlendMode::Additive => (1.0, 1.0),
+        }
+    }
+
+    // Separable blend function B(Cb, Cs) on straight (non-premultiplied) channels
+    fn mix(self, dst: f32, src: f32) -> f32 {
+        match self {
+            BlendMode::Multiply => dst * src,
+            BlendMode::Screen => dst + src - dst * src,
+            _ => src,
+        }
+    }
+}
+
+/// Composites `src` over `dst` with the given mode and returns the new destination.
+pub fn composite(src: Color, dst: Color, mode: BlendMode) -> Color {
+    // opaque source-over is by far the most common case
+    if mode == BlendMode::SourceOver && src.a == 255 {
+        return src;
+    }
+
+    let src_alpha = src.a as f32 / 255.0;
+    let dst_alpha = dst.a as f32 / 255.0;
+    let (fa, fb) = mode.factors(src_alpha, dst_alpha);
+
+    let out_alpha = (src_alpha * fa + dst_alpha * fb).min(1.0);
+    if out_alpha <= 0.0 {
+        return Color::new_rgba(0, 0, 0, 0);
+    }
+
+    let channel = |s: u8, d: u8| {
+        let (s, d) = (s as f32 / 255.0, d as f32 / 255.0);
+        // blend the source with the backdrop where the backdrop is present
+        let blended = (1.0 - dst_alpha) * s + dst_alpha * mode.mix(d, s);
+        let premultiplied = (src_alpha * fa * blended + dst_alpha * fb * d).min(out_alpha);
+        (premultiplied / out_alpha * 255.0).round() as i32
+    };
+
+    Color::new_rgba(
+        channel(src.r, dst.r),
+        channel(src.g, dst.g),
+        channel(src.b, dst.b),
+        (out_alpha * 255.0).round() as i32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color { r: 255, g: 0, b: 0, a: 255 };
+    const BLUE: Color = Color { r: 0, g: 0, b: 255, a: 255 };
+    const TRANSPARENT: Color = Color { r: 0, g: 0, b: 0, a: 0 };
+
+    #[test]
+    fn test_source_over() {
+        assert_eq!(composite(RED, BLUE, BlendMode::SourceOver), RED);
+        assert_eq!(composite(TRANSPARENT, BLUE, BlendMode::SourceOver), BLUE);
+
+        let half_red = RED.with_alpha(128);
+        let white = Color::from_hex(0xFFFFFF);
+        assert_eq!(composite(half_red, white, BlendMode::SourceOver), Color::new(255, 127, 127));
+
+        // two half-transparent layers over nothing
+        let out = composite(half_red, BLUE.with_alpha(128), BlendMode::SourceOver);
+        assert_eq!(out.a, 192);
+        assert_eq!((out.r, out.g, out.b), (170, 0, 85));
+    }
+
+    #[test]
+    fn test_porter_duff_alpha() {
+        let src = RED.with_alpha(204); // 0.8
+        let dst = BLUE.with_alpha(102); // 0.4
+        let alpha = |mode| composite(src, dst, mode).a;
+        assert_eq!(alpha(BlendMode::Clear), 0);
+        assert_eq!(alpha(BlendMode::Source), 204);
+        assert_eq!(alpha(BlendMode::Destination), 102);
+        assert_eq!(alpha(BlendMode::SourceOver), 224); // 0.8 + 0.4 * 0.2
+        assert_eq!(alpha(BlendMode::DestinationOver), 224);
+        assert_eq!(alpha(BlendMode::SourceIn), 82); // 0.8 * 0.4
+        assert_eq!(alpha(BlendMode::DestinationIn), 82);
+        assert_eq!(alpha(BlendMode::SourceOut), 122); // 0.8 * 0.6
+        assert_eq!(alpha(BlendMode::DestinationOut), 20); // 0.4 * 0.2
+        assert_eq!(alpha(BlendMode::SourceAtop), 102);
+        assert_eq!(alpha(BlendMode::DestinationAtop), 204);
+        assert_eq!(alpha(BlendMode::Xor), 143); // 0.8 * 0.6 + 0.4 * 0.2
+        assert_eq!(alpha(BlendMode::Additive), 255);
+    }
+
+    #[test]
+    fn test_porter_duff_colors() {
+        assert_eq!(composite(RED, BLUE, BlendMode::Destination), BLUE);
+        assert_eq!(composite(RED, BLUE, BlendMode::Source), RED);
+        assert_eq!(composite(RED, BLUE, BlendMode::SourceAtop), RED);
+        assert_eq!(composite(RED, BLUE, BlendMode::DestinationOver), BLUE);
+        assert_eq!(composite(RED, BLUE, BlendMode::Xor).a, 0);
+        assert_eq!(composite(RED, TRANSPARENT, BlendMode::SourceIn).a, 0);
+        assert_eq!(composite(RED.with_alpha(10), BLUE, BlendMode::Source), RED.with_alpha(10));
+    }
+
+    #[test]
+    fn test_separable_modes() {
+        let gray = Color::from_hex(0x808080);
+        let orange = Color::from_hex(0xFF8000);
+        assert_eq!(composite(gray, orange, BlendMode::Multiply), Color::from_hex(0x804000));
+        assert_eq!(composite(gray, orange, BlendMode::Screen), Color::from_hex(0xFFC080));
+        assert_eq!(composite(gray, orange, BlendMode::Additive), Color::from_hex(0xFFFF80));
+
+        // multiply over an empty backdrop leaves the source untouched
+        assert_eq!(composite(gray, TRANSPARENT, BlendMode::Multiply), gray);
+    }
+}
@@ -1,94 +1,508 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{Write, BufWriter};
 
+use crate::error::{Error, Result};
+use crate::framebuffer::Framebuffer;
+
 const BMP_HEADER_SIZE: usize = 54;
-const BMP_PIXEL_OFFSET: usize = 54;
 const BMP_BITS_PER_PIXEL: usize = 24;
+const BMP_FILE_HEADER_SIZE: usize = 14;
+const BMP_INFO_HEADER_SIZE: usize = 40;
+const BMP_V4_HEADER_SIZE: usize = 108;
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+// channel masks for 32-bit BGRA pixels
+const BMP_RED_MASK: u32 = 0x00FF_0000;
+const BMP_GREEN_MASK: u32 = 0x0000_FF00;
+const BMP_BLUE_MASK: u32 = 0x0000_00FF;
+const BMP_ALPHA_MASK: u32 = 0xFF00_0000;
+const LCS_SRGB: u32 = 0x7352_4742; // 'sRGB'
+
+/// Pixel layout used when writing a BMP file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum BmpFormat {
+    /// 24-bit BGR with a BITMAPINFOHEADER; alpha is dropped.
+    #[default]
+    Rgb24,
+    /// 32-bit BGRA with a BITMAPV4HEADER and an alpha mask, so alpha is preserved.
+    Bgra32,
+}
+
+impl BmpFormat {
+    fn bits_per_pixel(self) -> usize {
+        match self {
+            BmpFormat::Rgb24 => BMP_BITS_PER_PIXEL,
+            BmpFormat::Bgra32 => 32,
+        }
+    }
+
+    fn dib_header_size(self) -> usize {
+        match self {
+            BmpFormat::Rgb24 => BMP_INFO_HEADER_SIZE,
+            BmpFormat::Bgra32 => BMP_V4_HEADER_SIZE,
+        }
+    }
+
+    fn pixel_offset(self) -> usize {
+        BMP_FILE_HEADER_SIZE + self.dib_header_size()
+    }
+}
 
 pub fn write_bmp_file(
     file_path: &str,
     buffer: &[u32],
     width: usize,
     height: usize,
-) {
-    //TODO: create a buffered writer for the file
-    let file = File::create(file_path).unwrap();
+) -> Result<()> {
+    write_bmp_file_with_format(file_path, buffer, width, height, BmpFormat::Rgb24)
+}
+
+pub fn write_bmp_file_with_format(
+    file_path: &str,
+    buffer: &[u32],
+    width: usize,
+    height: usize,
+    format: BmpFormat,
+) -> Result<()> {
+    check_dimensions(buffer, width, height, format)?;
+
+    // create a buffered writer for the file
+    let file = File::create(file_path)?;
     let mut writer = BufWriter::new(file);
 
-    //wrute the BMP header
-    write_bmp_header(&mut writer, width, height);
+    write_bmp_with_format(&mut writer, buffer, width, height, format)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_bmp<W: Write>(
+    writer: &mut W,
+    buffer: &[u32],
+    width: usize,
+    height: usize,
+) -> Result<()> {
+    write_bmp_with_format(writer, buffer, width, height, BmpFormat::Rgb24)
+}
+
+pub fn write_bmp_with_format<W: Write>(
+    writer: &mut W,
+    buffer: &[u32],
+    width: usize,
+    height: usize,
+    format: BmpFormat,
+) -> Result<()> {
+    check_dimensions(buffer, width, height, format)?;
+
+    //write the BMP header
+    write_bmp_header(writer, width, height, format)?;
 
     // write the pixel data from the framebuffer
-    write_pixel_data(&mut writer, buffer, width, height);
+    write_pixel_data(writer, buffer, width, height, format)
+}
 
+// BMP stores dimensions as signed 32-bit values and sizes as unsigned 32-bit values
+fn check_dimensions(buffer: &[u32], width: usize, height: usize, format: BmpFormat) -> Result<()> {
+    let too_large = row_stride(width, format.bits_per_pixel())
+        .checked_mul(height)
+        .and_then(|size| size.checked_add(format.pixel_offset()))
+        .is_none_or(|size| size > u32::MAX as usize);
+    if width == 0 || height == 0 || width > i32::MAX as usize || too_large || buffer.len() != width * height {
+        return Err(Error::InvalidDimensions { width, height });
+    }
+    Ok(())
 }
 
-fn write_bmp_header(
-    file: &mut BufWriter<File>,
+fn write_bmp_header<W: Write>(
+    file: &mut W,
     width: usize,
     height: usize,
-)  {
-    //TODO: calculate file size and pixel data size
-    let file_size = (BMP_HEADER_SIZE + width * height * 3) as u32;
+    format: BmpFormat,
+) -> Result<()> {
+    // each row is padded to a multiple of 4 bytes
+    let image_size = (row_stride(width, format.bits_per_pixel()) * height) as u32;
+    let offset = format.pixel_offset() as u32;
+    let file_size = offset + image_size;
     let reserved: u32 = 0;
-    let offset = BMP_PIXEL_OFFSET as u32;
-    let dib_header_size: u32 = 40;
+    let dib_header_size = format.dib_header_size() as u32;
     let planes: u16 = 1;
-    let bits_per_pixel: u16 = BMP_BITS_PER_PIXEL as u16;
-    let compression: u32 = 0;
-    let image_size: u32 = (width * height * 3) as u32;
+    let bits_per_pixel = format.bits_per_pixel() as u16;
+    let compression = match format {
+        BmpFormat::Rgb24 => BI_RGB,
+        BmpFormat::Bgra32 => BI_BITFIELDS,
+    };
     let x_ppm: u32 = 0; // 72 DPI
     let y_ppm: u32 = 0; // 72 DPI
     let total_colors: u32 = 0;
     let important_colors: u32 = 0;
 
     //write bmp signature
-    file.write_all(b"BM").unwrap();
+    file.write_all(b"BM")?;
 
     //write file size, reserved bytes, and pixel offset
-    file.write_all(&file_size.to_le_bytes()).unwrap();
-    file.write_all(&reserved.to_le_bytes()).unwrap();
-    file.write_all(&offset.to_le_bytes()).unwrap();
+    file.write_all(&file_size.to_le_bytes())?;
+    file.write_all(&reserved.to_le_bytes())?;
+    file.write_all(&offset.to_le_bytes())?;
 
     //write header size, image width, and image height
-    file.write_all(&dib_header_size.to_le_bytes()).unwrap();
-    file.write_all(&(width as u32).to_le_bytes()).unwrap();
-    file.write_all(&(height as u32).to_le_bytes()).unwrap();
+    file.write_all(&dib_header_size.to_le_bytes())?;
+    file.write_all(&(width as u32).to_le_bytes())?;
+    file.write_all(&(height as u32).to_le_bytes())?;
 
 
     //write color planes and bits per pixel
-    file.write_all(&planes.to_le_bytes()).unwrap();
-    file.write_all(&bits_per_pixel.to_le_bytes()).unwrap();
+    file.write_all(&planes.to_le_bytes())?;
+    file.write_all(&bits_per_pixel.to_le_bytes())?;
 
-    //wrtie compression method, pixel data size, and resolution
-    file.write_all(&compression.to_le_bytes()).unwrap();
-    file.write_all(&image_size.to_le_bytes()).unwrap();
-    file.write_all(&x_ppm.to_le_bytes()).unwrap();
-    file.write_all(&y_ppm.to_le_bytes()).unwrap();
+    //write compression method, pixel data size, and resolution
+    file.write_all(&compression.to_le_bytes())?;
+    file.write_all(&image_size.to_le_bytes())?;
+    file.write_all(&x_ppm.to_le_bytes())?;
+    file.write_all(&y_ppm.to_le_bytes())?;
 
     //write number of colors and important colors
-    file.write_all(&total_colors.to_le_bytes()).unwrap();
-    file.write_all(&important_colors.to_le_bytes()).unwrap();
+    file.write_all(&total_colors.to_le_bytes())?;
+    file.write_all(&important_colors.to_le_bytes())?;
+
+    if format == BmpFormat::Bgra32 {
+        //write the V4 channel masks and color space
+        for mask in [BMP_RED_MASK, BMP_GREEN_MASK, BMP_BLUE_MASK, BMP_ALPHA_MASK, LCS_SRGB] {
+            file.write_all(&mask.to_le_bytes())?;
+        }
+        // CIE endpoints and gamma are unused for sRGB
+        file.write_all(&[0u8; 48])?;
+    }
 
+    Ok(())
 }
 
-fn write_pixel_data(
-    file: &mut BufWriter<File>,
+fn write_pixel_data<W: Write>(
+    file: &mut W,
     buffer: &[u32],
     width: usize,
     height: usize,
-) {
+    format: BmpFormat,
+) -> Result<()> {
     // Calcular el tamaño del padding para cada fila
+    let bytes_per_pixel = format.bits_per_pixel() / 8;
+    let padding = vec![0u8; row_stride(width, format.bits_per_pixel()) - width * bytes_per_pixel];
 
     for y in (0..height).rev() {
         for x in 0..width {
             let index = y * width + x;
             let color = buffer[index];
+            let a = ((color >> 24) & 0xFF) as u8;
             let r = ((color >> 16) & 0xFF) as u8;
             let g = ((color >> 8) & 0xFF) as u8;
             let b = (color & 0xFF) as u8;
-            file.write_all(&[b, g, r]).unwrap();
+            match format {
+                BmpFormat::Rgb24 => file.write_all(&[b, g, r])?,
+                BmpFormat::Bgra32 => file.write_all(&[b, g, r, a])?,
+            }
+        }
+        file.write_all(&padding)?;
+    }
+
+    Ok(())
+}
+
+pub fn read_bmp_file(file_path: &str) -> Result<Framebuffer> {
+    // read the whole file and decode it from memory
+    let data = fs::read(file_path)?;
+    decode_bmp(&data)
+}
+
+pub fn decode_bmp(data: &[u8]) -> Result<Framebuffer> {
+    if data.len() < BMP_HEADER_SIZE || &data[0..2] != b"BM" {
+        return Err(Error::UnsupportedFormat("not a BMP file".to_string()));
+    }
+
+    //read pixel offset and DIB header fields
+    let offset = read_u32(data, 10) as usize;
+    let dib_header_size = read_u32(data, 14);
+    if dib_header_size < 40 {
+        return Err(Error::UnsupportedFormat("unsupported BMP header version".to_string()));
+    }
+    let raw_width = read_u32(data, 18) as i32;
+    let raw_height = read_u32(data, 22) as i32;
+    let bits_per_pixel = read_u16(data, 28);
+    let compression = read_u32(data, 30);
+
+    if raw_width <= 0 || raw_height == 0 {
+        return Err(Error::InvalidDimensions { width: raw_width.unsigned_abs() as usize, height: raw_height.unsigned_abs() as usize });
+    }
+    if bits_per_pixel != 24 && bits_per_pixel != 32 {
+        return Err(Error::UnsupportedFormat(format!("{} bit BMP files are not supported", bits_per_pixel)));
+    }
+
+    // bitfields are only accepted for the standard BGRA layout; the masks directly follow
+    // the BITMAPINFOHEADER fields, and the alpha mask only exists in V3+ headers
+    let has_alpha = match compression {
+        BI_RGB => bits_per_pixel == 32,
+        BI_BITFIELDS if bits_per_pixel == 32 => {
+            let mask_size = if dib_header_size >= 56 { 16 } else { 12 };
+            if data.len() < BMP_HEADER_SIZE + mask_size {
+                return Err(Error::InvalidData("BMP channel masks are truncated".to_string()));
+            }
+            let masks = [read_u32(data, 54), read_u32(data, 58), read_u32(data, 62)];
+            if masks != [BMP_RED_MASK, BMP_GREEN_MASK, BMP_BLUE_MASK] {
+                return Err(Error::UnsupportedFormat("unsupported BMP channel masks".to_string()));
+            }
+            dib_header_size >= 56 && read_u32(data, 66) == BMP_ALPHA_MASK
+        }
+        _ => return Err(Error::UnsupportedFormat("compressed BMP files are not supported".to_string())),
+    };
+
+    // a negative height means the rows are stored top-down
+    let width = raw_width as usize;
+    let height = raw_height.unsigned_abs() as usize;
+    let top_down = raw_height < 0;
+    let bytes_per_pixel = bits_per_pixel as usize / 8;
+    let row_size = row_stride(width, bits_per_pixel as usize);
+
+    if offset + row_size * height > data.len() {
+        return Err(Error::InvalidData("BMP pixel data is truncated".to_string()));
+    }
+
+    let mut buffer = vec![0u32; width * height];
+    for row in 0..height {
+        let y = if top_down { row } else { height - 1 - row };
+        let start = offset + row * row_size;
+        for x in 0..width {
+            let p = start + x * bytes_per_pixel;
+            let b = data[p] as u32;
+            let g = data[p + 1] as u32;
+            let r = data[p + 2] as u32;
+            let a = if has_alpha { data[p + 3] as u32 } else { 0xFF };
+            buffer[y * width + x] = (a << 24) | (r << 16) | (g << 8) | b;
+        }
+    }
+
+    // many writers leave the fourth byte of BI_RGB pixels zeroed, meaning opaque
+    if compression == BI_RGB && buffer.iter().all(|&pixel| pixel >> 24 == 0) {
+        buffer.iter_mut().for_each(|pixel| *pixel |= 0xFF00_0000);
+    }
+
+    Ok(Framebuffer::from_buffer(width, height, buffer))
+}
+
+// Bytes per row, rounded up to a multiple of 4 as required by the format
+fn row_stride(width: usize, bits_per_pixel: usize) -> usize {
+    (width * bits_per_pixel).div_ceil(32) * 4
+}
+
+fn read_u16(data: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([data[at], data[at + 1]])
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    // pixel data offset of a 24-bit file
+    const BMP_PIXEL_OFFSET: usize = 54;
+
+    // Builds a BMP in memory with the given height sign, bit depth and pixels (0xRRGGBB, top row first)
+    fn build_bmp(width: usize, height: usize, bits_per_pixel: usize, top_down: bool, pixels: &[u32]) -> Vec<u8> {
+        let row_size = row_stride(width, bits_per_pixel);
+        let mut data = Vec::new();
+        data.extend_from_slice(b"BM");
+        data.extend_from_slice(&((BMP_HEADER_SIZE + row_size * height) as u32).to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&(BMP_PIXEL_OFFSET as u32).to_le_bytes());
+        data.extend_from_slice(&40u32.to_le_bytes());
+        data.extend_from_slice(&(width as i32).to_le_bytes());
+        let signed_height = if top_down { -(height as i32) } else { height as i32 };
+        data.extend_from_slice(&signed_height.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&(bits_per_pixel as u16).to_le_bytes());
+        data.extend_from_slice(&[0u8; 24]);
+
+        for row in 0..height {
+            let y = if top_down { row } else { height - 1 - row };
+            let mut written = 0;
+            for x in 0..width {
+                let color = pixels[y * width + x];
+                data.extend_from_slice(&[color as u8, (color >> 8) as u8, (color >> 16) as u8]);
+                written += 3;
+                if bits_per_pixel == 32 {
+                    data.push(0xFF);
+                    written += 1;
+                }
+            }
+            data.resize(data.len() + row_size - written, 0);
+        }
+        data
+    }
+
+    const PIXELS: [u32; 6] = [0xFF0000, 0x00FF00, 0x0000FF, 0x123456, 0xFFFFFF, 0x000000];
+
+    #[test]
+    fn test_decode_bottom_up_24() {
+        let data = build_bmp(3, 2, 24, false, &PIXELS);
+        let fb = decode_bmp(&data).unwrap();
+        assert_eq!((fb.width, fb.height), (3, 2));
+        assert_eq!(fb.get_point(0, 0), Some(Color::from_hex(0xFF0000)));
+        assert_eq!(fb.get_point(2, 0), Some(Color::from_hex(0x0000FF)));
+        assert_eq!(fb.get_point(0, 1), Some(Color::from_hex(0x123456)));
+        assert_eq!(fb.get_point(2, 1), Some(Color::from_hex(0x000000)));
+    }
+
+    #[test]
+    fn test_decode_top_down_24() {
+        let data = build_bmp(3, 2, 24, true, &PIXELS);
+        let fb = decode_bmp(&data).unwrap();
+        assert_eq!(fb.get_point(0, 0), Some(Color::from_hex(0xFF0000)));
+        assert_eq!(fb.get_point(1, 1), Some(Color::from_hex(0xFFFFFF)));
+    }
+
+    #[test]
+    fn test_decode_32_bit() {
+        let data = build_bmp(3, 2, 32, false, &PIXELS);
+        let fb = decode_bmp(&data).unwrap();
+        assert_eq!(fb.get_point(1, 0), Some(Color::from_hex(0x00FF00)));
+        assert_eq!(fb.get_point(0, 1), Some(Color::from_hex(0x123456)));
+    }
+
+    fn write_to_bytes(buffer: &[u32], width: usize, height: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        write_bmp(&mut data, buffer, width, height).unwrap();
+        data
+    }
+
+    fn pattern(width: usize, height: usize) -> Vec<u32> {
+        (0..width * height).map(|i| (i as u32).wrapping_mul(0x9E3779B1) & 0xFFFFFF).collect()
+    }
+
+    #[test]
+    fn test_row_stride() {
+        assert_eq!(row_stride(1, 24), 4);
+        assert_eq!(row_stride(3, 24), 12);
+        assert_eq!(row_stride(4, 24), 12);
+        assert_eq!(row_stride(5, 24), 16);
+        assert_eq!(row_stride(799, 24), 2400);
+        assert_eq!(row_stride(3, 32), 12);
+    }
+
+    #[test]
+    fn test_header_fields() {
+        for &(width, height) in &[(1, 1), (2, 3), (3, 2), (5, 7), (799, 2), (800, 600)] {
+            let data = write_to_bytes(&pattern(width, height), width, height);
+            let stride = row_stride(width, 24);
+
+            assert_eq!(&data[0..2], b"BM");
+            assert_eq!(read_u32(&data, 2) as usize, data.len(), "file size for width {}", width);
+            assert_eq!(data.len(), BMP_HEADER_SIZE + stride * height);
+            assert_eq!(read_u32(&data, 6), 0);
+            assert_eq!(read_u32(&data, 10) as usize, BMP_PIXEL_OFFSET);
+            assert_eq!(read_u32(&data, 14), 40);
+            assert_eq!(read_u32(&data, 18) as usize, width);
+            assert_eq!(read_u32(&data, 22) as usize, height);
+            assert_eq!(read_u16(&data, 26), 1);
+            assert_eq!(read_u16(&data, 28), 24);
+            assert_eq!(read_u32(&data, 30), 0);
+            assert_eq!(read_u32(&data, 34) as usize, stride * height, "image size for width {}", width);
         }
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_padding_bytes_are_zero() {
+        let (width, height) = (3, 4);
+        let buffer = vec![0xFFFFFF; width * height];
+        let data = write_to_bytes(&buffer, width, height);
+        let stride = row_stride(width, 24);
+        for row in 0..height {
+            let start = BMP_PIXEL_OFFSET + row * stride;
+            assert!(data[start..start + width * 3].iter().all(|&b| b == 0xFF));
+            assert!(data[start + width * 3..start + stride].iter().all(|&b| b == 0));
+        }
+    }
+
+    #[test]
+    fn test_round_trip_odd_widths() {
+        for &(width, height) in &[(1, 1), (1, 5), (2, 2), (3, 3), (5, 2), (6, 1), (7, 9), (799, 3)] {
+            let buffer = pattern(width, height);
+            let data = write_to_bytes(&buffer, width, height);
+            let fb = decode_bmp(&data).unwrap();
+            assert_eq!((fb.width, fb.height), (width, height));
+            for y in 0..height {
+                for x in 0..width {
+                    assert_eq!(fb.get_point(x as isize, y as isize), Some(Color::from_hex(buffer[y * width + x])));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bgra32_header_and_round_trip() {
+        let (width, height) = (3, 2);
+        let buffer: Vec<u32> = vec![0xFFFF0000, 0x8000FF00, 0x000000FF, 0x40123456, 0xFFFFFFFF, 0x01000000];
+        let mut data = Vec::new();
+        write_bmp_with_format(&mut data, &buffer, width, height, BmpFormat::Bgra32).unwrap();
+
+        assert_eq!(data.len(), 122 + 12 * height);
+        assert_eq!(read_u32(&data, 2) as usize, data.len());
+        assert_eq!(read_u32(&data, 10), 122);
+        assert_eq!(read_u32(&data, 14), 108);
+        assert_eq!(read_u16(&data, 28), 32);
+        assert_eq!(read_u32(&data, 30), BI_BITFIELDS);
+        assert_eq!(read_u32(&data, 66), BMP_ALPHA_MASK);
+        // bottom row first, stored as B, G, R, A
+        assert_eq!(&data[122..126], &[0x56, 0x34, 0x12, 0x40]);
+
+        let fb = decode_bmp(&data).unwrap();
+        for y in 0..height {
+            for x in 0..width {
+                assert_eq!(fb.get_point(x as isize, y as isize), Some(Color::from_argb(buffer[y * width + x])));
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_rejects_bad_input() {
+        assert!(matches!(decode_bmp(b"not a bitmap"), Err(Error::UnsupportedFormat(_))));
+
+        let mut truncated = build_bmp(3, 2, 24, false, &PIXELS);
+        truncated.truncate(truncated.len() - 4);
+        assert!(matches!(decode_bmp(&truncated), Err(Error::InvalidData(_))));
+
+        let mut paletted = build_bmp(3, 2, 24, false, &PIXELS);
+        paletted[28] = 8;
+        assert!(matches!(decode_bmp(&paletted), Err(Error::UnsupportedFormat(_))));
+
+        // a V4 header announcing bitfields, cut off before the alpha mask
+        let mut masks_cut = build_bmp(1, 1, 32, false, &[0]);
+        masks_cut.truncate(BMP_HEADER_SIZE);
+        masks_cut[14..18].copy_from_slice(&108u32.to_le_bytes());
+        masks_cut[30..34].copy_from_slice(&BI_BITFIELDS.to_le_bytes());
+        for mask in [BMP_RED_MASK, BMP_GREEN_MASK, BMP_BLUE_MASK] {
+            masks_cut.extend_from_slice(&mask.to_le_bytes());
+        }
+        assert_eq!(masks_cut.len(), 66);
+        assert!(matches!(decode_bmp(&masks_cut), Err(Error::InvalidData(_))));
+    }
+
+    #[test]
+    fn test_write_errors() {
+        let mut data = Vec::new();
+        assert!(matches!(
+            write_bmp(&mut data, &[], 0, 0),
+            Err(Error::InvalidDimensions { width: 0, height: 0 })
+        ));
+        assert!(matches!(
+            write_bmp(&mut data, &[0; 5], 2, 3),
+            Err(Error::InvalidDimensions { width: 2, height: 3 })
+        ));
+        assert!(data.is_empty());
+
+        let missing_dir = std::env::temp_dir().join("bmp_test_missing_dir").join("out.bmp");
+        assert!(matches!(
+            write_bmp_file(missing_dir.to_str().unwrap(), &[0; 6], 2, 3),
+            Err(Error::Io(_))
+        ));
+    }
+}
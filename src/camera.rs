@@ -0,0 +1,129 @@
+//! Cameras: where the scene is looked at from, and how it is projected.
+//!
+//! World space is right-handed with y up, and the camera looks down its own
+//! negative z axis, as in OpenGL. Projections map the visible volume to clip
+//! space, whose normalized depth runs from -1 at the near plane to 1 at the far one.
+
+use std::f32::consts::PI;
+
+use nalgebra_glm::{self as glm, Mat4, Vec3};
+
+use crate::error::{Error, Result};
+
+/// How the view volume is flattened onto the screen.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Projection {
+    /// A frustum with the given vertical field of view in radians; farther
+    /// things look smaller.
+    Perspective { fov_y: f32, near: f32, far: f32 },
+    /// A box `height` world units tall, as wide as the aspect ratio asks; size
+    /// does not change with distance.
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+impl Projection {
+    /// The projection matrix for a viewport of the given width / height ratio.
+    pub fn matrix(&self, aspect: f32) -> Result<Mat4> {
+        if !(aspect.is_finite() && aspect > 0.0) {
+            return Err(Error::InvalidGeometry(format!("the aspect ratio must be positive, got {}", aspect)));
+        }
+        match *self {
+            Projection::Perspective { fov_y, near, far } => {
+                if !(fov_y > 0.0 && fov_y < PI) {
+                    return Err(Error::InvalidGeometry(format!("the field of view must be between 0 and pi, got {}", fov_y)));
+                }
+                if !(near > 0.0 && far > near) {
+                    return Err(Error::InvalidGeometry(format!(
+                        "a perspective needs 0 < near < far, got near {} and far {}",
+                        near, far
+                    )));
+                }
+                Ok(glm::perspective(aspect, fov_y, near, far))
+            }
+            Projection::Orthographic { height, near, far } => {
+                if !(height > 0.0 && far > near) {
+                    return Err(Error::InvalidGeometry(format!(
+                        "an orthographic box needs a positive height and near < far, got height {}, near {} and far {}",
+                        height, near, far
+                    )));
+                }
+                let (half_width, half_height) = (height * aspect / 2.0, height / 2.0);
+                Ok(glm::ortho(-half_width, half_width, -half_height, half_height, near, far))
+            }
+        }
+    }
+}
+
+/// A camera at `eye` looking at `target`, with `up` pointing to the top of the screen.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Camera {
+    pub eye: Vec3,
+    pub target: Vec3,
+    pub up: Vec3,
+    pub projection: Projection,
+}
+
+impl Camera {
+    pub fn new(eye: Vec3, target: Vec3, up: Vec3, projection: Projection) -> Self {
+        Camera { eye, target, up, projection }
+    }
+
+    /// The matrix taking world space to the camera's view space.
+    pub fn view_matrix(&self) -> Result<Mat4> {
+        let forward = self.target - self.eye;
+        if forward.norm() == 0.0 || forward.cross(&self.up).norm() == 0.0 {
+            return Err(Error::InvalidGeometry(
+                "the camera needs a target away from the eye and an up vector not along the view".to_string(),
+            ));
+        }
+        Ok(glm::look_at(&self.eye, &self.target, &self.up))
+    }
+
+    pub fn projection_matrix(&self, aspect: f32) -> Result<Mat4> {
+        self.projection.matrix(aspect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec4;
+
+    fn ndc(matrix: &Mat4, point: Vec3) -> Vec3 {
+        let clip = matrix * Vec4::new(point.x, point.y, point.z, 1.0);
+        clip.xyz() / clip.w
+    }
+
+    #[test]
+    fn test_view_and_projections() {
+        let perspective = Projection::Perspective { fov_y: PI / 2.0, near: 1.0, far: 10.0 };
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::zeros(), Vec3::y(), perspective);
+        let matrix = camera.projection_matrix(2.0).unwrap() * camera.view_matrix().unwrap();
+
+        // the target is in the middle, the near and far planes at depth -1 and 1
+        assert!(ndc(&matrix, Vec3::zeros()).xy().norm() < 1e-6);
+        assert!((ndc(&matrix, Vec3::new(0.0, 0.0, 4.0)).z + 1.0).abs() < 1e-5);
+        assert!((ndc(&matrix, Vec3::new(0.0, 0.0, -5.0)).z - 1.0).abs() < 1e-5);
+        // a 90 degree view reaches as high as it is far, and twice as wide
+        assert!((ndc(&matrix, Vec3::new(0.0, 5.0, 0.0)).y - 1.0).abs() < 1e-5);
+        assert!((ndc(&matrix, Vec3::new(10.0, 0.0, 0.0)).x - 1.0).abs() < 1e-5);
+
+        let orthographic = Projection::Orthographic { height: 4.0, near: 0.0, far: 10.0 };
+        let matrix = orthographic.matrix(1.0).unwrap();
+        // the same offset at any distance
+        assert!((ndc(&matrix, Vec3::new(1.0, 2.0, -1.0)).xy() - ndc(&matrix, Vec3::new(1.0, 2.0, -9.0)).xy()).norm() < 1e-6);
+        assert!((ndc(&matrix, Vec3::new(1.0, 2.0, -1.0)).y - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_rejects_invalid_cameras() {
+        let perspective = Projection::Perspective { fov_y: 1.0, near: 1.0, far: 10.0 };
+        assert!(perspective.matrix(0.0).is_err());
+        assert!(Projection::Perspective { fov_y: 1.0, near: 0.0, far: 10.0 }.matrix(1.0).is_err());
+        assert!(Projection::Perspective { fov_y: 4.0, near: 1.0, far: 10.0 }.matrix(1.0).is_err());
+        assert!(Projection::Orthographic { height: 2.0, near: 5.0, far: 5.0 }.matrix(1.0).is_err());
+
+        let looking_up = Camera::new(Vec3::zeros(), Vec3::y(), Vec3::y(), perspective);
+        assert!(matches!(looking_up.view_matrix(), Err(Error::InvalidGeometry(_))));
+    }
+}
@@ -1,11 +1,12 @@
 use std::ops::{Add, Mul}; // Importamos los traits Add y Mul del módulo std::ops
 use std::fmt; // Importamos el módulo std::fmt
 
-#[derive(Debug, Copy, Clone)] // Derivamos las traits Debug, Copy y Clone para la estructura Color
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)] // Derivamos las traits Debug, Copy, Clone, PartialEq, Eq y Default para la estructura Color
 pub struct Color { // Definimos la estructura Color
     pub r: u8, // Campo público r de tipo u8 (8 bits sin signo)
     pub g: u8, // Campo público g de tipo u8
     pub b: u8, // Campo público b de tipo u8
+    pub a: u8, // Campo público a (opacidad), 255 es completamente opaco
 }
 
 impl Color { // Implementamos métodos para la estructura Color
@@ -14,19 +15,48 @@ impl Color { // Implementamos métodos para la estructura Color
             r: r.clamp(0, 255) as u8, // Limitamos el valor de r entre 0 y 255 y lo convertimos a u8
             g: g.clamp(0, 255) as u8, // Limitamos el valor de g entre 0 y 255 y lo convertimos a u8
             b: b.clamp(0, 255) as u8, // Limitamos el valor de b entre 0 y 255 y lo convertimos a u8
+            a: 255, // Los colores creados con new son opacos
         }
     }
 
+    pub fn new_rgba(r: i32, g: i32, b: i32, a: i32) -> Self { // Crea un Color con opacidad explícita
+        Color { a: a.clamp(0, 255) as u8, ..Color::new(r, g, b) } // Limitamos la opacidad entre 0 y 255
+    }
+
+    pub fn with_alpha(self, a: u8) -> Self { // Devuelve el mismo color con otra opacidad
+        Color { a, ..self }
+    }
+
     pub fn from_hex(hex: u32) -> Self { // Definimos el método from_hex que crea una instancia de Color a partir de un valor hexadecimal
         let r = ((hex >> 16) & 0xFF) as u8; // Extraemos el componente rojo del valor hexadecimal
         let g = ((hex >> 8) & 0xFF) as u8; // Extraemos el componente verde del valor hexadecimal
         let b = (hex & 0xFF) as u8; // Extraemos el componente azul del valor hexadecimal
-        Color {r, g, b} // Devolvemos una nueva instancia de Color con los componentes extraídos
+        Color {r, g, b, a: 255} // Devolvemos una nueva instancia opaca de Color con los componentes extraídos
     }
 
-    pub fn to_hex(&self) -> u32 { // Definimos el método to_hex que convierte la instancia de Color a un valor hexadecimal
+    pub fn to_hex(self) -> u32 { // Definimos el método to_hex que convierte la instancia de Color a un valor hexadecimal
         ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32) // Combinamos los componentes en un solo valor hexadecimal
     }
+
+    pub fn from_argb(argb: u32) -> Self { // Crea un Color a partir de un valor 0xAARRGGBB
+        Color::from_hex(argb).with_alpha((argb >> 24) as u8) // El byte alto contiene la opacidad
+    }
+
+    pub fn to_argb(self) -> u32 { // Convierte el Color a un valor 0xAARRGGBB
+        ((self.a as u32) << 24) | self.to_hex() // Colocamos la opacidad en el byte alto
+    }
+}
+
+impl From<u32> for Color { // Permite convertir un valor hexadecimal 0xRRGGBB en Color
+    fn from(hex: u32) -> Self {
+        Color::from_hex(hex)
+    }
+}
+
+impl From<Color> for u32 { // Permite convertir un Color en su valor hexadecimal 0xRRGGBB
+    fn from(color: Color) -> Self {
+        color.to_hex()
+    }
 }
 
 impl Add for Color { // Implementamos el trait Add para la estructura Color
@@ -37,6 +67,7 @@ impl Add for Color { // Implementamos el trait Add para la estructura Color
             r: (self.r as u32 + other.r as u32).min(255) as u8, // Sumamos los componentes rojos y los limitamos a 255
             g: (self.g as u32 + other.g as u32).min(255) as u8, // Sumamos los componentes verdes y los limitamos a 255
             b: (self.b as u32 + other.b as u32).min(255) as u8, // Sumamos los componentes azules y los limitamos a 255
+            a: (self.a as u32 + other.a as u32).min(255) as u8, // Sumamos las opacidades y las limitamos a 255
         }
     }
 }
@@ -49,13 +80,32 @@ impl Mul<f32> for Color { // Implementamos el trait Mul para la estructura Color
             r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8, // Multiplicamos el componente rojo por el escalar y lo limitamos a 255
             g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8, // Multiplicamos el componente verde por el escalar y lo limitamos a 255
             b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8, // Multiplicamos el componente azul por el escalar y lo limitamos a 255
+            a: self.a, // La opacidad no cambia al escalar el brillo
+        }
+    }
+}
+
+impl Mul for Color { // Multiplicar dos colores los modula canal por canal, como una luz sobre una superficie
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self::Output {
+        let modulate = |a: u8, b: u8| ((a as u32 * b as u32 + 127) / 255) as u8; // El producto se escala de vuelta a 0..=255 redondeando
+        Color {
+            r: modulate(self.r, other.r),
+            g: modulate(self.g, other.g),
+            b: modulate(self.b, other.b),
+            a: modulate(self.a, other.a), // Las opacidades también se multiplican
         }
     }
 }
 
 impl fmt::Display for Color { // Implementamos el trait Display para la estructura Color
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { // Definimos cómo se formatea la estructura al mostrarla
-        write!(f, "Color (R: {}, G: {}, B: {})", self.r, self.g, self.b) // Escribimos el formato deseado en el formatter proporcionado
+        if self.a == 255 { // Los colores opacos no muestran la opacidad
+            write!(f, "Color (R: {}, G: {}, B: {})", self.r, self.g, self.b) // Escribimos el formato deseado en el formatter proporcionado
+        } else {
+            write!(f, "Color (R: {}, G: {}, B: {}, A: {})", self.r, self.g, self.b, self.a)
+        }
     }
 }
 
@@ -94,6 +144,28 @@ mod tests {
         assert_eq!(color.to_hex(), 0xFF5733);
     }
 
+    #[test]
+    fn test_u32_conversions() {
+        let color: Color = 0x123456.into();
+        assert_eq!(color, Color::new(0x12, 0x34, 0x56));
+        assert_eq!(u32::from(color), 0x123456);
+        assert_eq!(Color::from(0xFF123456), Color::from_hex(0x123456)); // Ignora el byte alto
+    }
+
+    #[test]
+    fn test_alpha() {
+        let color = Color::new_rgba(10, 20, 30, 300);
+        assert_eq!(color.a, 255);
+        assert_eq!(Color::new(1, 2, 3).a, 255);
+        assert_eq!(Color::from_hex(0x123456).a, 255);
+
+        let translucent = Color::from_argb(0x80FF8000);
+        assert_eq!(translucent, Color::new(255, 128, 0).with_alpha(0x80));
+        assert_eq!(translucent.to_argb(), 0x80FF8000);
+        assert_eq!(translucent.to_hex(), 0xFF8000);
+        assert_eq!((translucent * 0.5).a, 0x80);
+    }
+
     #[test]
     fn test_add() {
         let color1 = Color::new(100, 150, 200);
@@ -113,9 +185,18 @@ mod tests {
         assert_eq!(scaled_color.b, 100);
     }
 
+    #[test]
+    fn test_modulate() {
+        let light = Color::new(255, 128, 0);
+        let surface = Color::new_rgba(200, 200, 200, 128);
+        assert_eq!(light * surface, Color::new_rgba(200, 100, 0, 128));
+        assert_eq!(surface * Color::from_hex(0xFFFFFF), surface); // El blanco no cambia nada
+    }
+
     #[test]
     fn test_display() {
         let color = Color::new(100, 150, 200);
         assert_eq!(format!("{}", color), "Color (R: 100, G: 150, B: 200)");
+        assert_eq!(format!("{}", color.with_alpha(64)), "Color (R: 100, G: 150, B: 200, A: 64)");
     }
 }
\ No newline at end of file
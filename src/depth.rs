@@ -0,0 +1,58 @@
+/// The test a fragment's depth must pass against the depth already stored at its
+/// pixel to be drawn. Smaller depths are nearer under the default `Less`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DepthCompare {
+    /// Never draws; useful to disable a pass without touching its geometry.
+    Never,
+    #[default]
+    Less,
+    LessEqual,
+    Equal,
+    NotEqual,
+    GreaterEqual,
+    Greater,
+    /// Always draws, which turns the depth test off while still writing depths.
+    Always,
+}
+
+impl DepthCompare {
+    /// Whether a fragment at depth `incoming` passes over the `stored` depth.
+    pub fn passes(self, incoming: f32, stored: f32) -> bool {
+        match self {
+            DepthCompare::Never => false,
+            DepthCompare::Less => incoming < stored,
+            DepthCompare::LessEqual => incoming <= stored,
+            DepthCompare::Equal => incoming == stored,
+            DepthCompare::NotEqual => incoming != stored,
+            DepthCompare::GreaterEqual => incoming >= stored,
+            DepthCompare::Greater => incoming > stored,
+            DepthCompare::Always => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_functions() {
+        let cases = [
+            (DepthCompare::Never, [false, false, false]),
+            (DepthCompare::Less, [true, false, false]),
+            (DepthCompare::LessEqual, [true, true, false]),
+            (DepthCompare::Equal, [false, true, false]),
+            (DepthCompare::NotEqual, [true, false, true]),
+            (DepthCompare::GreaterEqual, [false, true, true]),
+            (DepthCompare::Greater, [false, false, true]),
+            (DepthCompare::Always, [true, true, true]),
+        ];
+        for (compare, expected) in cases {
+            // nearer, equal and farther than the stored 0.5
+            let results = [0.25, 0.5, 0.75].map(|depth| compare.passes(depth, 0.5));
+            assert_eq!(results, expected, "{:?}", compare);
+        }
+        // every fragment passes a cleared infinitely far buffer under Less
+        assert!(DepthCompare::Less.passes(1e30, f32::INFINITY));
+    }
+}
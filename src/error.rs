@@ -0,0 +1,48 @@
+use std::fmt;
+use std::io;
+
+/// Errors produced while encoding, decoding or rendering images.
+#[derive(Debug)]
+pub enum Error {
+    /// The underlying reader or writer failed.
+    Io(io::Error),
+    /// The image dimensions are zero, too large, or do not match the pixel buffer.
+    InvalidDimensions { width: usize, height: usize },
+    /// The data uses a format or feature this crate cannot handle.
+    UnsupportedFormat(String),
+    /// The data claims to be a supported format but is corrupt or truncated.
+    InvalidData(String),
+    /// The shape cannot be drawn, for example a polygon with too few vertices.
+    InvalidGeometry(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::InvalidDimensions { width, height } => {
+                write!(f, "invalid image dimensions {}x{}", width, height)
+            }
+            Error::UnsupportedFormat(message) => write!(f, "unsupported format: {}", message),
+            Error::InvalidData(message) => write!(f, "invalid data: {}", message),
+            Error::InvalidGeometry(message) => write!(f, "invalid geometry: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
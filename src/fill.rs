@@ -0,0 +1,277 @@
+use nalgebra_glm::Vec3;
+
+use crate::framebuffer::Framebuffer;
+
+/// Rule deciding which regions enclosed by a polygon's contours are inside.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FillRule {
+    /// A point is inside when a ray from it crosses an odd number of edges.
+    EvenOdd,
+    /// A point is inside when the contours wind around it a non-zero number of times.
+    NonZero,
+}
+
+/// Rasterization used for filled shapes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum FillMode {
+    /// Fills the pixels whose centers are inside, following the top-left rule.
+    #[default]
+    Aliased,
+    /// Blends each pixel with the fraction of its area the shape covers.
+    AntiAliased,
+}
+
+// Sub-scanlines per pixel row used for anti-aliased fills; coverage along x is exact
+const SUBSCANLINES: usize = 16;
+
+// A non-horizontal polygon edge, oriented top to bottom
+struct Edge {
+    y_top: f64,
+    y_bottom: f64,
+    x_top: f64,
+    slope: f64,   // change in x per unit of y
+    winding: i32, // +1 if the original edge pointed down, -1 if up
+}
+
+// Active edge table swept over scanlines in increasing y
+struct EdgeTable {
+    edges: Vec<Edge>, // sorted by y_top
+    next_edge: usize,
+    active: Vec<usize>,
+    crossings: Vec<(f64, i32)>,
+}
+
+impl EdgeTable {
+    fn new<C: AsRef<[Vec3]>>(contours: &[C]) -> Self {
+        let mut edges: Vec<Edge> = Vec::new();
+        for contour in contours {
+            let vertices = contour.as_ref();
+            for i in 0..vertices.len() {
+                let start = vertices[i];
+                let end = vertices[(i + 1) % vertices.len()];
+                if start.y == end.y {
+                    continue; // horizontal edges never cross a scanline
+                }
+                let (top, bottom, winding) = if start.y < end.y { (start, end, 1) } else { (end, start, -1) };
+                edges.push(Edge {
+                    y_top: top.y as f64,
+                    y_bottom: bottom.y as f64,
+                    x_top: top.x as f64,
+                    slope: (bottom.x as f64 - top.x as f64) / (bottom.y as f64 - top.y as f64),
+                    winding,
+                });
+            }
+        }
+        edges.sort_by(|a, b| a.y_top.total_cmp(&b.y_top));
+        EdgeTable { edges, next_edge: 0, active: Vec::new(), crossings: Vec::new() }
+    }
+
+    // Vertical extent [top, bottom) covered by the edges
+    fn bounds(&self) -> Option<(f64, f64)> {
+        let top = self.edges.first()?.y_top;
+        let bottom = self.edges.iter().map(|e| e.y_bottom).fold(f64::MIN, f64::max);
+        Some((top, bottom))
+    }
+
+    // Calls `span` with the [left, right) extent of every inside run on the scanline.
+    // Scanlines must be visited in increasing y.
+    fn scanline(&mut self, y: f64, fill_rule: FillRule, mut span: impl FnMut(f64, f64)) {
+        // add edges starting at or above this scanline, drop the ones that ended
+        while self.next_edge < self.edges.len() && self.edges[self.next_edge].y_top <= y {
+            self.active.push(self.next_edge);
+            self.next_edge += 1;
+        }
+        let edges = &self.edges;
+        self.active.retain(|&e| edges[e].y_bottom > y);
+
+        self.crossings.clear();
+        for &e in &self.active {
+            let edge = &edges[e];
+            self.crossings.push((edge.x_top + (y - edge.y_top) * edge.slope, edge.winding));
+        }
+        // the order changes little between scanlines, which insertion sort handles in linear time
+        for i in 1..self.crossings.len() {
+            let mut j = i;
+            while j > 0 && self.crossings[j - 1].0 > self.crossings[j].0 {
+                self.crossings.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+
+        let mut winding = 0;
+        for pair in self.crossings.windows(2) {
+            winding += match fill_rule {
+                FillRule::EvenOdd => 1,
+                FillRule::NonZero => pair[0].1,
+            };
+            let inside = match fill_rule {
+                FillRule::EvenOdd => winding % 2 != 0,
+                FillRule::NonZero => winding != 0,
+            };
+            if inside && pair[1].0 > pair[0].0 {
+                span(pair[0].0, pair[1].0);
+            }
+        }
+    }
+}
+
+/// Fills the contours with the framebuffer's current color and fill mode.
+/// Pixel centers sit on integer coordinates.
+pub(crate) fn fill_contours<C: AsRef<[Vec3]>>(framebuffer: &mut Framebuffer, contours: &[C], fill_rule: FillRule) {
+    let mut table = EdgeTable::new(contours);
+    match framebuffer.fill_mode() {
+        FillMode::Aliased => fill_aliased(framebuffer, &mut table, fill_rule),
+        FillMode::AntiAliased => fill_anti_aliased(framebuffer, &mut table, fill_rule),
+    }
+}
+
+// Samples each pixel at its center. Centers exactly on an edge follow the top-left
+// rule, so polygons sharing an edge never leave gaps or draw a pixel twice.
+fn fill_aliased(framebuffer: &mut Framebuffer, table: &mut EdgeTable, fill_rule: FillRule) {
+    let Some((top, bottom)) = table.bounds() else {
+        return;
+    };
+
+    // scanlines whose centers fall inside [top, bottom), clipped to the framebuffer
+    let first_row = (top.ceil() as isize).max(0);
+    let last_row = (bottom.ceil() as isize - 1).min(framebuffer.height as isize - 1);
+    let last_column = framebuffer.width as isize - 1;
+
+    let mut spans: Vec<(isize, isize)> = Vec::new();
+    for y in first_row..=last_row {
+        // pixels whose centers satisfy left <= x < right
+        spans.clear();
+        table.scanline(y as f64, fill_rule, |left, right| {
+            spans.push(((left.ceil() as isize).max(0), (right.ceil() as isize - 1).min(last_column)));
+        });
+        for &(left, right) in &spans {
+            for x in left..=right {
+                framebuffer.point(x, y);
+            }
+        }
+    }
+}
+
+// Accumulates the covered area of every pixel from sub-scanlines whose spans are
+// measured exactly along x, then blends each touched pixel once.
+fn fill_anti_aliased(framebuffer: &mut Framebuffer, table: &mut EdgeTable, fill_rule: FillRule) {
+    let Some((top, bottom)) = table.bounds() else {
+        return;
+    };
+    if framebuffer.width == 0 {
+        return;
+    }
+
+    // pixel (x, y) covers [x - 0.5, x + 0.5) x [y - 0.5, y + 0.5)
+    let first_row = ((top + 0.5).floor() as isize).max(0);
+    let last_row = ((bottom + 0.5).floor() as isize).min(framebuffer.height as isize - 1);
+    let right_limit = framebuffer.width as f64 - 0.5;
+    let weight = 1.0 / SUBSCANLINES as f64;
+
+    let mut coverage = vec![0.0f64; framebuffer.width];
+    let color = framebuffer.current_color();
+
+    for y in first_row..=last_row {
+        let (mut touched_min, mut touched_max) = (usize::MAX, 0);
+
+        for k in 0..SUBSCANLINES {
+            let sample_y = y as f64 - 0.5 + (k as f64 + 0.5) * weight;
+            table.scanline(sample_y, fill_rule, |left, right| {
+                let (left, right) = (left.max(-0.5), right.min(right_limit));
+                if right <= left {
+                    return;
+                }
+                let first = (left + 0.5).floor() as usize;
+                let last = (right + 0.5).ceil() as usize - 1;
+                if first == last {
+                    coverage[first] += (right - left) * weight;
+                } else {
+                    coverage[first] += (first as f64 + 0.5 - left) * weight;
+                    for cell in &mut coverage[first + 1..last] {
+                        *cell += weight;
+                    }
+                    coverage[last] += (right - (last as f64 - 0.5)) * weight;
+                }
+                touched_min = touched_min.min(first);
+                touched_max = touched_max.max(last);
+            });
+        }
+
+        if touched_min > touched_max {
+            continue;
+        }
+        for (x, cell) in coverage.iter_mut().enumerate().take(touched_max + 1).skip(touched_min) {
+            let alpha = (color.a as f64 * cell.min(1.0)).round() as u8;
+            *cell = 0.0;
+            if alpha > 0 {
+                framebuffer.blend_point(x as isize, y, color.with_alpha(alpha));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    fn rectangle(x0: f32, y0: f32, x1: f32, y1: f32) -> Vec<Vec3> {
+        vec![Vec3::new(x0, y0, 0.0), Vec3::new(x1, y0, 0.0), Vec3::new(x1, y1, 0.0), Vec3::new(x0, y1, 0.0)]
+    }
+
+    fn filled(mode: FillMode, rule: FillRule, contours: &[Vec<Vec3>]) -> Framebuffer {
+        let mut fb = Framebuffer::new(20, 20);
+        fb.set_fill_mode(mode);
+        fb.set_current_color(Color::from_hex(0xFFFFFF));
+        fill_contours(&mut fb, contours, rule);
+        fb
+    }
+
+    fn level(fb: &Framebuffer, x: isize, y: isize) -> u8 {
+        fb.get_point(x, y).unwrap().r
+    }
+
+    #[test]
+    fn test_partial_coverage() {
+        let fb = filled(FillMode::AntiAliased, FillRule::NonZero, &[rectangle(2.0, 2.0, 6.0, 6.0)]);
+        assert_eq!(level(&fb, 4, 4), 255);
+        assert_eq!(level(&fb, 2, 4), 128); // half the pixel
+        assert_eq!(level(&fb, 4, 6), 128);
+        assert_eq!(level(&fb, 2, 2), 64); // a quarter at the corner
+        assert_eq!(level(&fb, 1, 4), 0);
+        assert_eq!(level(&fb, 7, 4), 0);
+
+        // the aliased mode only fills whole pixels
+        let fb = filled(FillMode::Aliased, FillRule::NonZero, &[rectangle(2.0, 2.0, 6.0, 6.0)]);
+        assert!((0..20).all(|x| matches!(level(&fb, x, 4), 0 | 255)));
+    }
+
+    #[test]
+    fn test_coverage_matches_area() {
+        let triangle = vec![Vec3::new(1.3, 2.1, 0.0), Vec3::new(17.6, 4.8, 0.0), Vec3::new(6.2, 16.9, 0.0)];
+        let area = 0.5 * ((17.6 - 1.3) * (16.9 - 2.1) - (6.2 - 1.3) * (4.8 - 2.1));
+        let fb = filled(FillMode::AntiAliased, FillRule::NonZero, &[triangle]);
+        let total: f64 = (0..20).flat_map(|y| (0..20).map(move |x| (x, y))).map(|(x, y)| level(&fb, x, y) as f64 / 255.0).sum();
+        assert!((total - area).abs() < area * 0.01, "covered {} of {}", total, area);
+    }
+
+    #[test]
+    fn test_anti_aliased_fill_rules() {
+        // the same orientation for both contours: a hole only under even-odd
+        let contours = [rectangle(2.0, 2.0, 18.0, 18.0), rectangle(7.0, 7.0, 13.0, 13.0)];
+        let even_odd = filled(FillMode::AntiAliased, FillRule::EvenOdd, &contours);
+        let non_zero = filled(FillMode::AntiAliased, FillRule::NonZero, &contours);
+        assert_eq!(level(&even_odd, 10, 10), 0);
+        assert_eq!(level(&even_odd, 7, 10), 128);
+        assert_eq!(level(&non_zero, 10, 10), 255);
+        assert_eq!(level(&non_zero, 7, 10), 255);
+    }
+
+    #[test]
+    fn test_anti_aliased_clipping() {
+        let fb = filled(FillMode::AntiAliased, FillRule::NonZero, &[rectangle(-30.0, -30.0, 10.0, 40.0)]);
+        assert_eq!(level(&fb, 0, 0), 255);
+        assert_eq!(level(&fb, 10, 19), 128);
+        assert_eq!(level(&fb, 11, 19), 0);
+    }
+}
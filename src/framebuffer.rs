@@ -1,66 +1,353 @@
-use crate::bmp::write_bmp_file;
+use std::io::Write;
+
+use nalgebra_glm::Mat3;
+
+use crate::blend::{composite, BlendMode};
+use crate::bmp::{write_bmp, write_bmp_file, write_bmp_with_format, write_bmp_file_with_format, BmpFormat};
+use crate::color::Color;
+use crate::depth::DepthCompare;
+use crate::error::Result;
+use crate::transform;
+use crate::fill::FillMode;
+use crate::line_impl::LineMode;
+use crate::stroke::{StrokePattern, StrokeStyle};
+use crate::png::{write_png, write_png_file, PngOptions};
+use crate::ppm::{write_pnm, write_pnm_file, PnmFormat};
+
+pub const MAX_CANVAS_SIDE: usize = 16_384; // Lado máximo de los lienzos que piden los SVG y las escenas, para no reservar memoria sin límite
 
 pub struct Framebuffer {
     pub width: usize,  // Ancho del framebuffer
     pub height: usize, // Alto del framebuffer
-    buffer: Vec<u32>,  // Buffer de píxeles
-    background_color: u32, // Color de fondo del framebuffer
-    current_color: u32,    // Color actual del framebuffer
+    buffer: Vec<u32>,  // Buffer de píxeles en formato 0xAARRGGBB
+    background_color: Color, // Color de fondo del framebuffer
+    current_color: Color,    // Color actual del framebuffer
+    blend_mode: BlendMode,   // Modo de mezcla usado al dibujar
+    line_mode: LineMode,     // Algoritmo usado para trazar líneas
+    fill_mode: FillMode,     // Muestreo usado al rellenar figuras
+    stroke_style: StrokeStyle, // Grosor, extremos y uniones de los trazos
+    stroke_pattern: StrokePattern, // Patrón de guiones de los trazos
+    transform: Mat3,               // Transformación aplicada a las figuras
+    transform_stack: Vec<Mat3>,    // Transformaciones guardadas con push_transform
+    depth_buffer: Vec<f32>,        // Profundidad de cada píxel, menor es más cercano
+    depth_compare: DepthCompare,   // Prueba de profundidad de los fragmentos
+    depth_clear_value: f32,        // Profundidad usada al limpiar el buffer de profundidad
 }
 
 impl Framebuffer {
     // Constructor
     pub fn new(width: usize, height: usize) -> Self {
         let buffer_size = width * height;
-        let background_color = 0x000000; // Color de fondo predeterminado (negro)
-        let buffer = vec![background_color; buffer_size]; // Crea un vector de tamaño buffer_size con el color de fondo
+        let background_color = Color::from_hex(0x000000); // Color de fondo predeterminado (negro)
+        let buffer = vec![background_color.to_argb(); buffer_size]; // Crea un vector de tamaño buffer_size con el color de fondo
         Framebuffer {
             width, // Asigna el ancho proporcionado al campo width de la estructura
             height, // Asigna el alto proporcionado al campo height de la estructura
             buffer, // Asigna el vector de píxeles al campo buffer de la estructura
             background_color, // Asigna el color de fondo proporcionado al campo background_color de la estructura
-            current_color: 0xFFFFFF, // Color actual predeterminado (blanco)
+            current_color: Color::from_hex(0xFFFFFF), // Color actual predeterminado (blanco)
+            blend_mode: BlendMode::SourceOver, // Modo de mezcla predeterminado
+            line_mode: LineMode::Bresenham, // Líneas exactas por defecto
+            fill_mode: FillMode::Aliased, // Rellenos sin suavizado por defecto
+            stroke_style: StrokeStyle::default(), // Trazos de un píxel por defecto
+            stroke_pattern: StrokePattern::Solid, // Trazos continuos por defecto
+            transform: transform::identity(), // Sin transformación por defecto
+            transform_stack: Vec::new(), // Ninguna transformación guardada
+            depth_buffer: vec![f32::INFINITY; buffer_size], // Todo empieza infinitamente lejos
+            depth_compare: DepthCompare::Less, // Gana el fragmento más cercano
+            depth_clear_value: f32::INFINITY,
+        }
+    }
+
+    // Crea un framebuffer a partir de un buffer de píxeles 0xAARRGGBB existente (por ejemplo, una imagen cargada)
+    pub fn from_buffer(width: usize, height: usize, buffer: Vec<u32>) -> Self {
+        assert_eq!(buffer.len(), width * height, "buffer size does not match dimensions");
+        Framebuffer {
+            width,
+            height,
+            buffer,
+            background_color: Color::from_hex(0x000000),
+            current_color: Color::from_hex(0xFFFFFF),
+            blend_mode: BlendMode::SourceOver,
+            line_mode: LineMode::Bresenham,
+            fill_mode: FillMode::Aliased,
+            stroke_style: StrokeStyle::default(),
+            stroke_pattern: StrokePattern::Solid,
+            transform: transform::identity(),
+            transform_stack: Vec::new(),
+            depth_buffer: vec![f32::INFINITY; width * height],
+            depth_compare: DepthCompare::Less,
+            depth_clear_value: f32::INFINITY,
         }
     }
 
     // Función para limpiar el framebuffer
     pub fn clear(&mut self) {
         // Usa el método fill de Vec para llenar todo el buffer con el color de fondo
-        self.buffer.fill(self.background_color);
+        self.buffer.fill(self.background_color.to_argb());
+    }
+
+    // Función para limpiar el buffer de profundidad con el valor de limpieza
+    pub fn clear_depth(&mut self) {
+        self.depth_buffer.fill(self.depth_clear_value);
     }
 
-    // Función para establecer un punto en el framebuffer
+    // Función para establecer un punto en el framebuffer con el color actual
     pub fn point(&mut self, x: isize, y: isize) {
+        self.blend_point(x, y, self.current_color);
+    }
+
+    // Función para mezclar un color con el punto existente usando el modo de mezcla actual
+    pub fn blend_point(&mut self, x: isize, y: isize, color: Color) {
         if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
             let index = (y as usize) * self.width + (x as usize); // Calcula el índice en el buffer para el punto (x, y)
-            self.buffer[index] = self.current_color; // Establece el color actual en el punto correspondiente en el buffer
+            let destination = Color::from_argb(self.buffer[index]); // Color que ya está en el buffer
+            self.buffer[index] = composite(color, destination, self.blend_mode).to_argb(); // Guarda el resultado de la mezcla
+        }
+    }
+
+    // Función para comprobar si un fragmento a esa profundidad pasaría la prueba, sin dibujarlo
+    pub fn passes_depth_test(&self, x: isize, y: isize, depth: f32) -> bool {
+        match self.get_depth(x, y) {
+            Some(stored) => self.depth_compare.passes(depth, stored),
+            None => false,
+        }
+    }
+
+    // Función para mezclar un fragmento que pasa la prueba de profundidad, guardando su profundidad;
+    // devuelve si se dibujó
+    pub fn depth_point(&mut self, x: isize, y: isize, depth: f32, color: Color) -> bool {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            let index = (y as usize) * self.width + (x as usize);
+            if self.depth_compare.passes(depth, self.depth_buffer[index]) {
+                self.depth_buffer[index] = depth;
+                self.blend_point(x, y, color);
+                return true;
+            }
+        }
+        false
+    }
+
+    // Función para obtener la profundidad guardada de un punto
+    pub fn get_depth(&self, x: isize, y: isize) -> Option<f32> {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            Some(self.depth_buffer[(y as usize) * self.width + (x as usize)])
+        } else {
+            None
         }
     }
 
     // Función para obtener el color de un punto en el framebuffer
-    pub fn get_point(&self, x: isize, y: isize) -> Option<u32> {
+    pub fn get_point(&self, x: isize, y: isize) -> Option<Color> {
         if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
             let index = (y as usize) * self.width + (x as usize); // Calcula el índice en el buffer para el punto (x, y)
-            Some(self.buffer[index]) // Devuelve el color del punto como Some(color)
+            Some(Color::from_argb(self.buffer[index])) // Devuelve el color del punto como Some(color)
         } else {
             None // Devuelve None si el punto está fuera de los límites del framebuffer
         }
     }
 
     // Establecer el color de fondo del framebuffer
-    pub fn set_background_color(&mut self, color: u32) {
+    pub fn set_background_color(&mut self, color: Color) {
         self.background_color = color; // Actualiza el color de fondo del framebuffer
     }
 
     // Establecer el color actual del framebuffer
-    pub fn set_current_color(&mut self, color: u32) {
+    pub fn set_current_color(&mut self, color: Color) {
         self.current_color = color; // Actualiza el color actual del framebuffer
     }
 
-    // Función para devolver una referencia al buffer de píxeles
-    pub fn render_buffer(&self, file_path: &str) {
+    // Establecer el modo de mezcla usado por point y las primitivas de dibujo
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    // Obtener el modo de mezcla actual
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    // Establecer el algoritmo usado por draw_line y los contornos de polígonos
+    pub fn set_line_mode(&mut self, mode: LineMode) {
+        self.line_mode = mode;
+    }
+
+    // Obtener el algoritmo de líneas actual
+    pub fn line_mode(&self) -> LineMode {
+        self.line_mode
+    }
+
+    // Establecer si los rellenos de polígonos se suavizan según el área cubierta
+    pub fn set_fill_mode(&mut self, mode: FillMode) {
+        self.fill_mode = mode;
+    }
+
+    // Obtener el modo de relleno actual
+    pub fn fill_mode(&self) -> FillMode {
+        self.fill_mode
+    }
+
+    // Establecer el grosor, los extremos y las uniones usados al trazar
+    pub fn set_stroke_style(&mut self, style: StrokeStyle) {
+        self.stroke_style = style;
+    }
+
+    // Obtener el estilo de trazo actual
+    pub fn stroke_style(&self) -> StrokeStyle {
+        self.stroke_style
+    }
+
+    // Establecer el patrón de guiones, puntos o máscara de bits de los trazos
+    pub fn set_stroke_pattern(&mut self, pattern: StrokePattern) {
+        self.stroke_pattern = pattern;
+    }
+
+    // Obtener el patrón de trazo actual
+    pub fn stroke_pattern(&self) -> &StrokePattern {
+        &self.stroke_pattern
+    }
+
+    // Reemplazar la transformación aplicada a los vértices de las figuras; el trait Line dibuja en píxeles y no la usa
+    pub fn set_transform(&mut self, matrix: Mat3) {
+        self.transform = matrix;
+    }
+
+    // Obtener la transformación actual
+    pub fn transform(&self) -> Mat3 {
+        self.transform
+    }
+
+    // Volver a la identidad, sin tocar las transformaciones guardadas
+    pub fn reset_transform(&mut self) {
+        self.transform = transform::identity();
+    }
+
+    // Componer una transformación con la actual; se aplica antes que las anteriores
+    pub fn apply_transform(&mut self, matrix: &Mat3) {
+        self.transform *= matrix;
+    }
+
+    // Desplazar las figuras siguientes
+    pub fn translate(&mut self, tx: f32, ty: f32) {
+        self.apply_transform(&transform::translation(tx, ty));
+    }
+
+    // Girar las figuras siguientes alrededor del origen (radianes, sentido horario en pantalla)
+    pub fn rotate(&mut self, angle: f32) {
+        self.apply_transform(&transform::rotation(angle));
+    }
+
+    // Escalar las figuras siguientes desde el origen
+    pub fn scale(&mut self, sx: f32, sy: f32) {
+        self.apply_transform(&transform::scaling(sx, sy));
+    }
+
+    // Inclinar las figuras siguientes (ángulos en radianes)
+    pub fn skew(&mut self, x_angle: f32, y_angle: f32) {
+        self.apply_transform(&transform::skewing(x_angle, y_angle));
+    }
+
+    // Guardar la transformación actual para recuperarla con pop_transform
+    pub fn push_transform(&mut self) {
+        self.transform_stack.push(self.transform);
+    }
+
+    // Recuperar la última transformación guardada; devuelve false si no había ninguna
+    pub fn pop_transform(&mut self) -> bool {
+        match self.transform_stack.pop() {
+            Some(matrix) => {
+                self.transform = matrix;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Establecer la prueba que decide si un fragmento tapa al que ya está en el píxel
+    pub fn set_depth_compare(&mut self, compare: DepthCompare) {
+        self.depth_compare = compare;
+    }
+
+    // Obtener la prueba de profundidad actual
+    pub fn depth_compare(&self) -> DepthCompare {
+        self.depth_compare
+    }
+
+    // Establecer la profundidad que escribe clear_depth (infinito por defecto)
+    pub fn set_depth_clear_value(&mut self, depth: f32) {
+        self.depth_clear_value = depth;
+    }
+
+    // Obtener el valor de limpieza de la profundidad
+    pub fn depth_clear_value(&self) -> f32 {
+        self.depth_clear_value
+    }
+
+    // Obtener el color de fondo del framebuffer
+    pub fn background_color(&self) -> Color {
+        self.background_color
+    }
+
+    // Obtener el color actual del framebuffer
+    pub fn current_color(&self) -> Color {
+        self.current_color
+    }
+
+    // Función para guardar el buffer de píxeles como archivo BMP
+    pub fn render_buffer(&self, file_path: &str) -> Result<()> {
         write_bmp_file(file_path, &self.buffer, self.width, self.height)
     }
+
+    // Escribe el framebuffer como BMP en cualquier destino que implemente Write
+    pub fn write_bmp<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_bmp(writer, &self.buffer, self.width, self.height)
+    }
+
+    // Guarda el buffer como BMP en el formato indicado (BGRA de 32 bits conserva la opacidad)
+    pub fn render_bmp(&self, file_path: &str, format: BmpFormat) -> Result<()> {
+        write_bmp_file_with_format(file_path, &self.buffer, self.width, self.height, format)
+    }
+
+    // Escribe el framebuffer como BMP en el formato indicado
+    pub fn write_bmp_with_format<W: Write>(&self, writer: &mut W, format: BmpFormat) -> Result<()> {
+        write_bmp_with_format(writer, &self.buffer, self.width, self.height, format)
+    }
+
+    // Codifica el framebuffer como BMP en memoria.
+    // Entra en pánico si el framebuffer está vacío o es demasiado grande para BMP.
+    pub fn to_bmp_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_bmp(&mut bytes).expect("framebuffer dimensions are not valid for BMP");
+        bytes
+    }
+
+    // Guarda el buffer de píxeles como archivo PNG
+    pub fn render_png(&self, file_path: &str, options: PngOptions) -> Result<()> {
+        write_png_file(file_path, &self.buffer, self.width, self.height, options)
+    }
+
+    // Escribe el framebuffer como PNG en cualquier destino que implemente Write
+    pub fn write_png<W: Write>(&self, writer: &mut W, options: PngOptions) -> Result<()> {
+        write_png(writer, &self.buffer, self.width, self.height, options)
+    }
+
+    // Codifica el framebuffer como PNG en memoria.
+    // Entra en pánico si el framebuffer está vacío o es demasiado grande para PNG.
+    pub fn to_png_bytes(&self, options: PngOptions) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_png(&mut bytes, options).expect("framebuffer dimensions are not valid for PNG");
+        bytes
+    }
+
+    // Guarda el buffer de píxeles como archivo PPM o PGM
+    pub fn render_pnm(&self, file_path: &str, format: PnmFormat) -> Result<()> {
+        write_pnm_file(file_path, &self.buffer, self.width, self.height, format)
+    }
+
+    // Escribe el framebuffer como PPM o PGM en cualquier destino que implemente Write
+    pub fn write_pnm<W: Write>(&self, writer: &mut W, format: PnmFormat) -> Result<()> {
+        write_pnm(writer, &self.buffer, self.width, self.height, format)
+    }
 }
 
 #[cfg(test)]
@@ -78,22 +365,22 @@ mod tests {
     #[test]
     fn test_clear() {
         let mut fb = Framebuffer::new(800, 600);
-        fb.set_background_color(0x00FF00); // Set background color to green
+        fb.set_background_color(Color::from_hex(0x00FF00)); // Set background color to green
         fb.clear();
 
         for pixel in fb.buffer.iter() {
-            assert_eq!(*pixel, 0x00FF00);
+            assert_eq!(*pixel, 0xFF00FF00); // Opaque green
         }
     }
 
     #[test]
     fn test_point() {
         let mut fb = Framebuffer::new(800, 600);
-        fb.set_current_color(0xFF0000); // Set current color to red
+        fb.set_current_color(Color::from_hex(0xFF0000)); // Set current color to red
         fb.point(400, 300);
 
         let index = 300 * 800 + 400;
-        assert_eq!(fb.buffer[index], 0xFF0000);
+        assert_eq!(fb.buffer[index], 0xFFFF0000); // Opaque red
 
         // Test negative coordinates
         fb.point(-1, -1); // Should not panic
@@ -103,10 +390,10 @@ mod tests {
     #[test]
     fn test_get_point() {
         let mut fb = Framebuffer::new(800, 600);
-        fb.set_current_color(0xFF0000); // Set current color to red
+        fb.set_current_color(Color::from_hex(0xFF0000)); // Set current color to red
         fb.point(400, 300);
 
-        assert_eq!(fb.get_point(400, 300), Some(0xFF0000));
+        assert_eq!(fb.get_point(400, 300), Some(Color::from_hex(0xFF0000)));
         assert_eq!(fb.get_point(800, 600), None); // Out of bounds
         assert_eq!(fb.get_point(-1, -1), None); // Out of bounds (negative coordinates)
     }
@@ -114,16 +401,121 @@ mod tests {
     #[test]
     fn test_set_colors() {
         let mut fb = Framebuffer::new(800, 600);
-        fb.set_background_color(0x00FF00); // Set background color to green
-        fb.set_current_color(0xFF0000); // Set current color to red
+        fb.set_background_color(Color::from_hex(0x00FF00)); // Set background color to green
+        fb.set_current_color(Color::from_hex(0xFF0000)); // Set current color to red
         fb.clear();
 
         for pixel in fb.buffer.iter() {
-            assert_eq!(*pixel, 0x00FF00);
+            assert_eq!(*pixel, 0xFF00FF00); // Opaque green
         }
 
         fb.point(400, 300);
         let index = 300 * 800 + 400;
-        assert_eq!(fb.buffer[index], 0xFF0000);
+        assert_eq!(fb.buffer[index], 0xFFFF0000); // Opaque red
+    }
+
+    #[test]
+    fn test_blend_modes() {
+        let mut fb = Framebuffer::new(2, 1);
+        fb.set_background_color(Color::from_hex(0xFFFFFF));
+        fb.clear();
+        assert_eq!(fb.blend_mode(), BlendMode::SourceOver);
+
+        // translucent red over white gives pink
+        fb.set_current_color(Color::from_hex(0xFF0000).with_alpha(128));
+        fb.point(0, 0);
+        assert_eq!(fb.get_point(0, 0), Some(Color::new(255, 127, 127)));
+
+        fb.set_blend_mode(BlendMode::Source);
+        fb.point(1, 0);
+        assert_eq!(fb.get_point(1, 0), Some(Color::from_hex(0xFF0000).with_alpha(128)));
+
+        fb.set_blend_mode(BlendMode::Multiply);
+        fb.blend_point(0, 0, Color::from_hex(0x00FF00));
+        assert_eq!(fb.get_point(0, 0), Some(Color::new(0, 127, 0)));
+    }
+
+    #[test]
+    fn test_transform_stack() {
+        let mut fb = Framebuffer::new(10, 10);
+        assert_eq!(fb.transform(), transform::identity());
+
+        fb.translate(5.0, 0.0);
+        fb.push_transform();
+        fb.scale(2.0, 2.0);
+        // the scale applies first, then the translation
+        assert_eq!(fb.transform(), transform::translation(5.0, 0.0) * transform::scaling(2.0, 2.0));
+
+        assert!(fb.pop_transform());
+        assert_eq!(fb.transform(), transform::translation(5.0, 0.0));
+        assert!(!fb.pop_transform()); // Nothing left to restore
+        assert_eq!(fb.transform(), transform::translation(5.0, 0.0));
+
+        fb.reset_transform();
+        assert_eq!(fb.transform(), transform::identity());
+    }
+
+    #[test]
+    fn test_depth_buffer() {
+        let mut fb = Framebuffer::new(10, 10);
+        let (red, blue) = (Color::from_hex(0xFF0000), Color::from_hex(0x0000FF));
+        assert_eq!(fb.get_depth(3, 3), Some(f32::INFINITY));
+
+        // the nearer fragment wins whatever the drawing order
+        assert!(fb.depth_point(3, 3, 0.5, red));
+        assert!(!fb.depth_point(3, 3, 0.75, blue));
+        assert_eq!(fb.get_point(3, 3), Some(red));
+        assert!(fb.depth_point(3, 3, 0.25, blue));
+        assert_eq!(fb.get_point(3, 3), Some(blue));
+        assert_eq!(fb.get_depth(3, 3), Some(0.25));
+        assert!(!fb.depth_point(10, 3, 0.0, red)); // Out of bounds
+        assert!(fb.passes_depth_test(3, 3, 0.1) && !fb.passes_depth_test(3, 3, 0.3));
+        assert_eq!(fb.get_depth(3, 3), Some(0.25)); // Testing does not write
+        assert_eq!(fb.get_depth(-1, 0), None);
+
+        // a reversed test with its matching clear value keeps the farthest fragment
+        fb.set_depth_compare(DepthCompare::Greater);
+        fb.set_depth_clear_value(0.0);
+        fb.clear_depth();
+        assert_eq!(fb.get_depth(3, 3), Some(0.0));
+        assert!(fb.depth_point(3, 3, 0.5, red));
+        assert!(!fb.depth_point(3, 3, 0.25, blue));
+        assert_eq!(fb.get_point(3, 3), Some(red));
+    }
+
+    #[test]
+    fn test_bmp_keeps_alpha() {
+        let mut fb = Framebuffer::new(2, 2);
+        fb.set_blend_mode(BlendMode::Source);
+        fb.set_current_color(Color::from_argb(0x40123456));
+        fb.point(1, 0);
+
+        let mut bytes = Vec::new();
+        fb.write_bmp_with_format(&mut bytes, BmpFormat::Bgra32).unwrap();
+        let loaded = crate::bmp::decode_bmp(&bytes).unwrap();
+        assert_eq!(loaded.get_point(1, 0), Some(Color::from_argb(0x40123456)));
+        assert_eq!(loaded.get_point(0, 0), Some(Color::from_hex(0x000000)));
+    }
+
+    #[test]
+    fn test_to_bmp_bytes() {
+        let mut fb = Framebuffer::new(3, 2);
+        fb.set_current_color(Color::from_hex(0xFF0000));
+        fb.point(0, 0);
+
+        let bytes = fb.to_bmp_bytes();
+        assert_eq!(&bytes[0..2], b"BM");
+        assert_eq!(bytes.len(), 54 + 12 * 2);
+        // the top row is stored last in a bottom-up BMP
+        assert_eq!(&bytes[54 + 12..54 + 15], &[0x00, 0x00, 0xFF]);
+    }
+
+    #[test]
+    fn test_to_png_bytes() {
+        let fb = Framebuffer::new(800, 600);
+        let stored = fb.to_png_bytes(PngOptions { compression: crate::png::Compression::Stored, ..PngOptions::default() });
+        let best = fb.to_png_bytes(PngOptions { compression: crate::png::Compression::Best, ..PngOptions::default() });
+        assert_eq!(&stored[1..4], b"PNG");
+        assert!(best.len() < stored.len() / 100);
     }
 }
\ No newline at end of file
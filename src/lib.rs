@@ -0,0 +1,33 @@
+//! A small software rasterizer: a framebuffer with lines, strokes, paths,
+//! polygon fills and depth-tested triangles projected through a camera, shaded
+//! and lit, encoded as BMP, PNG or Netpbm images.
+
+pub mod blend;
+pub mod bmp;
+pub mod camera;
+pub mod color;
+pub mod depth;
+pub mod error;
+pub mod fill;
+pub mod framebuffer;
+pub mod light;
+pub mod line_impl;
+pub mod mesh;
+pub mod obj;
+pub mod path;
+pub mod pipeline;
+pub mod png;
+pub mod ppm;
+pub mod scene;
+pub mod shader;
+pub mod shapes;
+pub mod stroke;
+pub mod svg;
+pub mod transform;
+pub mod triangle;
+mod toml;
+mod zlib;
+
+pub use color::Color;
+pub use error::{Error, Result};
+pub use framebuffer::Framebuffer;
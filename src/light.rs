@@ -0,0 +1,245 @@
+//! Lights and the Blinn-Phong lighting model.
+//!
+//! Surfaces are lit by an ambient term plus, for every light reaching them,
+//! Lambert diffuse and Blinn-Phong specular terms tinted by the light's color.
+//! Everything is computed with [`Color`] arithmetic, so each term saturates at
+//! full brightness. Positions and directions are in world space.
+
+use nalgebra_glm::{self as glm, Mat3, Vec3, Vec4};
+
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::mesh::Material;
+use crate::pipeline::Pipeline;
+use crate::shader::{draw_triangles, Fragment, FragmentShader, VertexShader};
+
+/// A source of light. Ranges fade point and spot lights smoothly to nothing at
+/// that distance; an infinite range never fades.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Light {
+    /// Parallel rays shining along `direction`, like the sun.
+    Directional { direction: Vec3, color: Color },
+    /// Light shining from `position` in every direction.
+    Point { position: Vec3, color: Color, range: f32 },
+    /// A cone of light from `position` along `direction`, full strength within
+    /// `inner_angle` of its axis and fading out towards `outer_angle`, in radians.
+    Spot { position: Vec3, direction: Vec3, color: Color, range: f32, inner_angle: f32, outer_angle: f32 },
+}
+
+impl Light {
+    pub fn color(&self) -> Color {
+        match *self {
+            Light::Directional { color, .. } | Light::Point { color, .. } | Light::Spot { color, .. } => color,
+        }
+    }
+
+    /// The unit direction from `point` towards the light, and the fraction of
+    /// the light reaching it; None when no direction can be told.
+    pub fn incidence(&self, point: Vec3) -> Option<(Vec3, f32)> {
+        match *self {
+            Light::Directional { direction, .. } => Some((-direction.try_normalize(0.0)?, 1.0)),
+            Light::Point { position, range, .. } => {
+                let towards = position - point;
+                Some((towards.try_normalize(0.0)?, falloff(towards.norm(), range)))
+            }
+            Light::Spot { position, direction, range, inner_angle, outer_angle, .. } => {
+                let towards = position - point;
+                let unit = towards.try_normalize(0.0)?;
+                // smoothstep from the outer edge of the cone to the inner one
+                let cos = -unit.dot(&direction.try_normalize(0.0)?);
+                let (inner, outer) = (inner_angle.cos(), outer_angle.cos());
+                let cone = if cos >= inner {
+                    1.0
+                } else if cos <= outer {
+                    0.0
+                } else {
+                    let t = (cos - outer) / (inner - outer);
+                    t * t * (3.0 - 2.0 * t)
+                };
+                Some((unit, falloff(towards.norm(), range) * cone))
+            }
+        }
+    }
+}
+
+// 1 at the light, easing down to 0 at the range
+fn falloff(distance: f32, range: f32) -> f32 {
+    let ratio = distance / range;
+    (1.0 - ratio * ratio).max(0.0).powi(2)
+}
+
+/// The lights of a scene and the ambient light filling it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Lighting {
+    pub ambient: Color,
+    pub lights: Vec<Light>,
+}
+
+impl Lighting {
+    /// The color of a surface point with unit `normal`, seen from `eye`. The
+    /// result keeps the material's opacity.
+    pub fn shade(&self, point: Vec3, normal: Vec3, eye: Vec3, material: &Material) -> Color {
+        let view = (eye - point).try_normalize(0.0).unwrap_or(normal);
+        let mut color = self.ambient * material.diffuse;
+        for light in &self.lights {
+            let Some((direction, strength)) = light.incidence(point) else {
+                continue;
+            };
+            let lambert = normal.dot(&direction);
+            if lambert <= 0.0 || strength <= 0.0 {
+                continue;
+            }
+            color = color + light.color() * material.diffuse * (lambert * strength);
+            if let Some(halfway) = (direction + view).try_normalize(0.0) {
+                let specular = normal.dot(&halfway).max(0.0).powf(material.shininess);
+                color = color + light.color() * material.specular * (specular * strength);
+            }
+        }
+        color.with_alpha(material.diffuse.a)
+    }
+}
+
+/// Phong shading: normals are interpolated across triangles and lit per pixel.
+/// Vertices are object-space positions with their normals, taken to world space
+/// by the pipeline's model matrix. Surfaces are lit on the side facing the eye,
+/// whichever way their normals point.
+pub struct PhongShader<'a> {
+    pipeline: &'a Pipeline,
+    lighting: &'a Lighting,
+    material: &'a Material,
+    // normals follow the inverse transpose of the model matrix to stay perpendicular
+    normal_matrix: Mat3,
+    eye: Vec3,
+}
+
+impl<'a> PhongShader<'a> {
+    pub fn new(pipeline: &'a Pipeline, lighting: &'a Lighting, material: &'a Material) -> Self {
+        let normal_matrix = glm::mat4_to_mat3(&glm::inverse_transpose(pipeline.model));
+        let eye = (glm::inverse(&pipeline.view) * Vec4::w()).xyz();
+        PhongShader { pipeline, lighting, material, normal_matrix, eye }
+    }
+}
+
+impl VertexShader for PhongShader<'_> {
+    type Vertex = (Vec3, Vec3);
+    type Varyings = (Vec3, Vec3);
+
+    fn vertex(&self, &(position, normal): &(Vec3, Vec3)) -> (Vec4, (Vec3, Vec3)) {
+        let world = (self.pipeline.model * Vec4::new(position.x, position.y, position.z, 1.0)).xyz();
+        (self.pipeline.to_clip(position), (world, self.normal_matrix * normal))
+    }
+}
+
+impl FragmentShader<(Vec3, Vec3)> for PhongShader<'_> {
+    fn fragment(&self, _: &Fragment, &(point, normal): &(Vec3, Vec3)) -> Option<Color> {
+        let normal = normal.try_normalize(0.0).unwrap_or_default();
+        let normal = if normal.dot(&(self.eye - point)) < 0.0 { -normal } else { normal };
+        Some(self.lighting.shade(point, normal, self.eye, self.material))
+    }
+}
+
+/// Fills the visible part of a triangle lit with per-pixel normals interpolated
+/// from its corners' normals, with depth testing.
+pub fn fill_lit_triangle(
+    framebuffer: &mut Framebuffer,
+    pipeline: &Pipeline,
+    lighting: &Lighting,
+    vertices: &[Vec3; 3],
+    normals: &[Vec3; 3],
+    material: &Material,
+) {
+    let shader = PhongShader::new(pipeline, lighting, material);
+    let corners = [(vertices[0], normals[0]), (vertices[1], normals[1]), (vertices[2], normals[2])];
+    draw_triangles(framebuffer, &pipeline.viewport, &shader, &shader, &corners, &[[0, 1, 2]]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{Camera, Projection};
+    use crate::pipeline::Viewport;
+
+    fn white(name: &str) -> Material {
+        Material { diffuse: Color::from_hex(0xFFFFFF), ..Material::new(name) }
+    }
+
+    #[test]
+    fn test_incidence() {
+        let sun = Light::Directional { direction: Vec3::new(0.0, -2.0, 0.0), color: Color::from_hex(0xFFFFFF) };
+        assert_eq!(sun.incidence(Vec3::new(5.0, 1.0, 3.0)), Some((Vec3::y(), 1.0)));
+
+        let bulb = Light::Point { position: Vec3::new(0.0, 4.0, 0.0), color: Color::from_hex(0xFFFFFF), range: 8.0 };
+        let (direction, strength) = bulb.incidence(Vec3::zeros()).unwrap();
+        assert_eq!(direction, Vec3::y());
+        assert!((strength - 0.5625).abs() < 1e-6);
+        assert_eq!(bulb.incidence(Vec3::new(0.0, -4.0, 0.0)).unwrap().1, 0.0);
+        assert_eq!(bulb.incidence(Vec3::new(0.0, 4.0, 0.0)), None);
+
+        // a cone pointing down, full within 30 degrees and dark beyond 45
+        let spot = Light::Spot {
+            position: Vec3::new(0.0, 1.0, 0.0),
+            direction: -Vec3::y(),
+            color: Color::from_hex(0xFFFFFF),
+            range: f32::INFINITY,
+            inner_angle: 30f32.to_radians(),
+            outer_angle: 45f32.to_radians(),
+        };
+        let strength = |x: f32| spot.incidence(Vec3::new(x, 0.0, 0.0)).unwrap().1;
+        assert_eq!(strength(0.5), 1.0);
+        assert!(strength(0.8) > 0.0 && strength(0.8) < 1.0);
+        assert_eq!(strength(1.1), 0.0);
+    }
+
+    #[test]
+    fn test_shade() {
+        let material = Material { specular: Color::from_hex(0xFFFFFF), shininess: 16.0, ..white("shiny") };
+        let red = Light::Directional { direction: -Vec3::y(), color: Color::from_hex(0xFF0000) };
+        let lighting = Lighting { ambient: Color::from_hex(0x202020), lights: vec![red] };
+        let up = Vec3::y();
+
+        // ambient alone where the light cannot reach
+        assert_eq!(lighting.shade(Vec3::zeros(), -up, Vec3::new(0.0, -1.0, 0.0), &material), Color::from_hex(0x202020));
+        // a highlight when the light reflects into the eye
+        assert_eq!(lighting.shade(Vec3::zeros(), up, Vec3::new(0.0, 5.0, 0.0), &material), Color::from_hex(0xFF2020));
+        // diffuse falls off with the angle, and the highlight fades away from the reflection
+        let tilted = Vec3::new(1.0, 1.0, 0.0).normalize();
+        let color = lighting.shade(Vec3::zeros(), tilted, Vec3::new(-5.0, 0.0, 0.0), &material);
+        assert_eq!((color.r, color.g), (0x20 + 180, 0x20));
+
+        let translucent = Material { diffuse: Color::new_rgba(255, 255, 255, 100), ..Material::new("glass") };
+        assert_eq!(lighting.shade(Vec3::zeros(), up, Vec3::y(), &translucent).a, 100);
+    }
+
+    #[test]
+    fn test_fill_lit_triangle() {
+        // a floor under a spot light, seen from above and in front
+        let projection = Projection::Perspective { fov_y: 1.2, near: 0.1, far: 50.0 };
+        let camera = Camera::new(Vec3::new(0.0, 4.0, 4.0), Vec3::zeros(), Vec3::y(), projection);
+        let pipeline = Pipeline::new(&camera, Viewport::new(60, 60)).unwrap();
+        let spot = Light::Spot {
+            position: Vec3::new(0.0, 2.0, 0.0),
+            direction: -Vec3::y(),
+            color: Color::from_hex(0xFFFFFF),
+            range: f32::INFINITY,
+            inner_angle: 0.3,
+            outer_angle: 0.5,
+        };
+        let lighting = Lighting { ambient: Color::from_hex(0x101010), lights: vec![spot] };
+        let floor = [Vec3::new(-4.0, 0.0, 4.0), Vec3::new(4.0, 0.0, 4.0), Vec3::new(0.0, 0.0, -4.0)];
+        let mut fb = Framebuffer::new(60, 60);
+        fill_lit_triangle(&mut fb, &pipeline, &lighting, &floor, &[Vec3::y(); 3], &white("floor"));
+
+        // lit under the light, ambient outside the cone
+        let center = pipeline.project(Vec3::zeros()).unwrap();
+        assert_eq!(fb.get_point(center.x.round() as isize, center.y.round() as isize), Some(Color::from_hex(0xFFFFFF)));
+        let aside = pipeline.project(Vec3::new(2.0, 0.0, 2.0)).unwrap();
+        assert_eq!(fb.get_point(aside.x.round() as isize, aside.y.round() as isize), Some(Color::from_hex(0x101010)));
+
+        // normals pointing away from the eye light the side facing it
+        let mut flipped = Framebuffer::new(60, 60);
+        fill_lit_triangle(&mut flipped, &pipeline, &lighting, &floor, &[-Vec3::y(); 3], &white("floor"));
+        for (x, y) in [(center.x, center.y), (aside.x, aside.y)] {
+            assert_eq!(flipped.get_point(x.round() as isize, y.round() as isize), fb.get_point(x.round() as isize, y.round() as isize));
+        }
+    }
+}
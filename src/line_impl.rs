@@ -1,10 +1,37 @@
+use nalgebra_glm::Vec3;
+
 use crate::framebuffer::Framebuffer;
+use crate::stroke;
+
+/// Rasterization used for strokes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum LineMode {
+    /// Integer Bresenham, pixel-exact and without blending.
+    #[default]
+    Bresenham,
+    /// Xiaolin Wu's algorithm, blending pixel coverage into the framebuffer.
+    AntiAliased,
+}
 
+/// Line drawing in device pixels. These methods do not use the framebuffer's
+/// transform; draw a [`Path`](crate::path::Path) with
+/// [`draw_path`](crate::shapes::draw_path) for lines that follow it.
+#[allow(non_snake_case)]
 pub trait Line {
     fn Line(&mut self, x1: isize, y1: isize, x2: isize, y2: isize);
+    /// Draws an anti-aliased line between sub-pixel endpoints.
+    fn line_aa(&mut self, x1: f32, y1: f32, x2: f32, y2: f32);
+    /// Draws a line with an explicit mode; Bresenham rounds the endpoints after
+    /// clipping the line to the framebuffer.
+    fn line_with_mode(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, mode: LineMode);
+    /// Draws a line with the framebuffer's current line mode and stroke style.
+    fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32);
+    /// Strokes connected segments, applying the stroke style's caps and joins.
+    fn stroke_polyline(&mut self, vertices: &[Vec3], closed: bool);
 }
 
 impl Line for Framebuffer {
+    #[allow(non_snake_case)]
     fn Line(&mut self, x1: isize, y1: isize, x2: isize, y2: isize) {
         let dx = (x2 - x1).abs();
         let sx = if x1 < x2 { 1 } else { -1 };
@@ -37,4 +64,348 @@ impl Line for Framebuffer {
             }
         }
     }
-}
\ No newline at end of file
+
+    fn line_aa(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        let (mut x1, mut y1, mut x2, mut y2) = (x1, y1, x2, y2);
+
+        // iterate along the major axis; swap so that it is always x and increasing
+        let steep = (y2 - y1).abs() > (x2 - x1).abs();
+        if steep {
+            std::mem::swap(&mut x1, &mut y1);
+            std::mem::swap(&mut x2, &mut y2);
+        }
+        if x1 > x2 {
+            std::mem::swap(&mut x1, &mut x2);
+            std::mem::swap(&mut y1, &mut y2);
+        }
+
+        let dx = x2 - x1;
+        let gradient = if dx == 0.0 { 1.0 } else { (y2 - y1) / dx };
+        let columns = if steep { self.height } else { self.width } as f32;
+
+        // plots (major, minor) with the given coverage, undoing the axis swap
+        let mut plot = |major: f32, minor: f32, coverage: f32| {
+            let (x, y) = if steep { (minor, major) } else { (major, minor) };
+            let color = self.current_color();
+            let alpha = (color.a as f32 * coverage.clamp(0.0, 1.0)).round() as u8;
+            if alpha > 0 {
+                self.blend_point(x as isize, y as isize, color.with_alpha(alpha));
+            }
+        };
+
+        // first endpoint, weighted by how much of its pixel the line covers
+        let x_end = x1.round();
+        let y_end = y1 + gradient * (x_end - x1);
+        let x_gap = 1.0 - fract(x1 + 0.5);
+        let x_start = x_end;
+        plot(x_start, y_end.floor(), (1.0 - fract(y_end)) * x_gap);
+        plot(x_start, y_end.floor() + 1.0, fract(y_end) * x_gap);
+        let y_first = y_end;
+
+        // second endpoint
+        let x_end = x2.round();
+        let y_end = y2 + gradient * (x_end - x2);
+        let x_gap = fract(x2 + 0.5);
+        let x_stop = x_end;
+        if x_stop > x_start {
+            plot(x_stop, y_end.floor(), (1.0 - fract(y_end)) * x_gap);
+            plot(x_stop, y_end.floor() + 1.0, fract(y_end) * x_gap);
+        }
+
+        // main loop: split each column between the two nearest pixels, walking only
+        // the columns inside the framebuffer
+        let first = (x_start + 1.0).max(0.0);
+        let last = (x_stop - 1.0).min(columns - 1.0);
+        if first <= last {
+            for column in 0..=(last - first) as usize {
+                let x = first + column as f32;
+                let inter_y = y_first + gradient * (x - x_start);
+                let base = inter_y.floor();
+                let fraction = inter_y - base;
+                plot(x, base, 1.0 - fraction);
+                plot(x, base + 1.0, fraction);
+            }
+        }
+    }
+
+    fn line_with_mode(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, mode: LineMode) {
+        match mode {
+            LineMode::Bresenham => {
+                // clip first, so far-off endpoints neither overflow nor walk off-canvas pixels
+                if let Some((x1, y1, x2, y2)) = clip_to_canvas(self, x1, y1, x2, y2) {
+                    self.Line(x1.round() as isize, y1.round() as isize, x2.round() as isize, y2.round() as isize);
+                }
+            }
+            LineMode::AntiAliased => self.line_aa(x1, y1, x2, y2),
+        }
+    }
+
+    fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        if self.stroke_style().is_hairline() && self.stroke_pattern().is_solid() {
+            let mode = self.line_mode();
+            self.line_with_mode(x1, y1, x2, y2, mode);
+        } else {
+            self.stroke_polyline(&[Vec3::new(x1, y1, 0.0), Vec3::new(x2, y2, 0.0)], false);
+        }
+    }
+
+    fn stroke_polyline(&mut self, vertices: &[Vec3], closed: bool) {
+        let width = self.stroke_style().width;
+        match stroke::dash_polyline(vertices, closed, self.stroke_pattern(), width) {
+            None => stroke_solid(self, vertices, closed),
+            Some(dashes) => {
+                let pixel_exact = self.stroke_style().is_hairline() && self.line_mode() == LineMode::Bresenham;
+                for mut dash in dashes {
+                    // Bresenham includes both endpoints, so a dash of length n would cover n + 1 pixels
+                    if pixel_exact {
+                        trim_end(&mut dash, 1.0);
+                    }
+                    stroke_solid(self, &dash, false);
+                }
+            }
+        }
+    }
+}
+
+fn stroke_solid(framebuffer: &mut Framebuffer, vertices: &[Vec3], closed: bool) {
+    if !framebuffer.stroke_style().is_hairline() {
+        stroke::stroke_polyline(framebuffer, vertices, closed);
+        return;
+    }
+
+    let mode = framebuffer.line_mode();
+    if vertices.len() == 1 {
+        let p = vertices[0];
+        framebuffer.line_with_mode(p.x, p.y, p.x, p.y, mode);
+    }
+    let segments = if closed { vertices.len() } else { vertices.len().saturating_sub(1) };
+    for i in 0..segments {
+        let start = vertices[i];
+        let end = vertices[(i + 1) % vertices.len()];
+        framebuffer.line_with_mode(start.x, start.y, end.x, end.y, mode);
+    }
+}
+
+// Shortens an open polyline by `amount` pixels from its end, keeping at least one point
+fn trim_end(vertices: &mut Vec<Vec3>, amount: f32) {
+    let mut amount = amount;
+    while vertices.len() > 1 {
+        let end = vertices[vertices.len() - 1];
+        let previous = vertices[vertices.len() - 2];
+        let length = (end - previous).xy().norm();
+        if length > amount {
+            let last = vertices.len() - 1;
+            vertices[last] = end.lerp(&previous, amount / length);
+            return;
+        }
+        amount -= length;
+        vertices.pop();
+    }
+}
+
+// Liang-Barsky clipping of a segment to the pixels of the framebuffer, whose
+// centers round to it; None when no part of it is on the canvas. Endpoints
+// already inside are kept as they are, and the arithmetic is done in f64 so
+// that far-off endpoints still cross the canvas in the right place.
+fn clip_to_canvas(framebuffer: &Framebuffer, x1: f32, y1: f32, x2: f32, y2: f32) -> Option<(f32, f32, f32, f32)> {
+    if ![x1, y1, x2, y2].iter().all(|value| value.is_finite()) {
+        return None;
+    }
+    let (x1, y1, x2, y2) = (x1 as f64, y1 as f64, x2 as f64, y2 as f64);
+    let (right, bottom) = (framebuffer.width as f64 - 0.5, framebuffer.height as f64 - 0.5);
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let (mut t0, mut t1) = (0.0, 1.0);
+    for (p, q) in [(-dx, x1 + 0.5), (dx, right - x1), (-dy, y1 + 0.5), (dy, bottom - y1)] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = q / p;
+        if p < 0.0 {
+            t0 = f64::max(t0, t);
+        } else {
+            t1 = f64::min(t1, t);
+        }
+        if t0 > t1 {
+            return None;
+        }
+    }
+    let point = |t: f64| ((x1 + t * dx) as f32, (y1 + t * dy) as f32);
+    let (start, end) = (point(t0), if t1 < 1.0 { point(t1) } else { (x2 as f32, y2 as f32) });
+    Some((start.0, start.1, end.0, end.1))
+}
+
+// The distance above the floor, which unlike f32::fract stays positive for negative values
+fn fract(value: f32) -> f32 {
+    value - value.floor()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::stroke::StrokePattern;
+
+    fn white_canvas() -> Framebuffer {
+        let mut fb = Framebuffer::new(20, 20);
+        fb.set_background_color(Color::from_hex(0xFFFFFF));
+        fb.clear();
+        fb.set_current_color(Color::from_hex(0x000000));
+        fb
+    }
+
+    // Ink laid down in a column, 1.0 being one fully black pixel
+    fn column_ink(fb: &Framebuffer, x: isize) -> f32 {
+        (0..fb.height as isize).map(|y| 1.0 - fb.get_point(x, y).unwrap().r as f32 / 255.0).sum()
+    }
+
+    #[test]
+    fn test_bresenham_endpoints() {
+        let mut fb = white_canvas();
+        fb.Line(2, 3, 12, 7);
+        assert_eq!(fb.get_point(2, 3), Some(Color::from_hex(0x000000)));
+        assert_eq!(fb.get_point(12, 7), Some(Color::from_hex(0x000000)));
+        assert_eq!(fb.get_point(12, 8), Some(Color::from_hex(0xFFFFFF)));
+    }
+
+    #[test]
+    fn test_bresenham_clips_far_endpoints() {
+        // a nearly flat line to a far-off endpoint stays on row 0 across the canvas
+        let mut fb = white_canvas();
+        fb.line_with_mode(0.0, 0.0, 1e15, 3.0, LineMode::Bresenham);
+        assert!((0..20).all(|x| fb.get_point(x, 0) == Some(Color::from_hex(0x000000))));
+        assert_eq!(fb.get_point(10, 1), Some(Color::from_hex(0xFFFFFF)));
+
+        let mut fb = white_canvas();
+        fb.line_with_mode(-1e12, -1e12, 1e12, 1e12, LineMode::Bresenham);
+        fb.line_with_mode(0.0, 5.0, f32::INFINITY, 5.0, LineMode::Bresenham);
+        assert!((0..20).all(|i| fb.get_point(i, i) == Some(Color::from_hex(0x000000))));
+        assert_eq!(fb.get_point(10, 5), Some(Color::from_hex(0xFFFFFF)));
+
+        // segments missing the canvas draw nothing
+        let mut fb = white_canvas();
+        fb.line_with_mode(-5.0, 30.0, 40.0, 25.0, LineMode::Bresenham);
+        assert!((0..20).all(|x| (0..20).all(|y| fb.get_point(x, y) == Some(Color::from_hex(0xFFFFFF)))));
+    }
+
+    #[test]
+    fn test_lines_ignore_the_transform() {
+        let mut fb = white_canvas();
+        fb.translate(5.0, 5.0);
+        fb.draw_line(2.0, 1.0, 8.0, 1.0);
+        fb.Line(2, 3, 8, 3);
+        assert_eq!(fb.get_point(4, 1), Some(Color::from_hex(0x000000)));
+        assert_eq!(fb.get_point(4, 3), Some(Color::from_hex(0x000000)));
+        assert_eq!(fb.get_point(9, 6), Some(Color::from_hex(0xFFFFFF)));
+    }
+
+    #[test]
+    fn test_aa_horizontal_line_is_solid() {
+        let mut fb = white_canvas();
+        fb.line_aa(2.0, 5.0, 10.0, 5.0);
+        for x in 3..10 {
+            assert_eq!(fb.get_point(x, 5), Some(Color::from_hex(0x000000)));
+            assert_eq!(fb.get_point(x, 6), Some(Color::from_hex(0xFFFFFF)));
+        }
+    }
+
+    #[test]
+    fn test_aa_splits_coverage() {
+        let mut fb = white_canvas();
+        // halfway between rows 5 and 6
+        fb.line_aa(2.0, 5.5, 10.0, 5.5);
+        let upper = fb.get_point(6, 5).unwrap();
+        let lower = fb.get_point(6, 6).unwrap();
+        assert!((upper.r as i32 - 128).abs() <= 1);
+        assert!((lower.r as i32 - 128).abs() <= 1);
+
+        // a diagonal deposits one pixel of ink per column
+        let mut fb = white_canvas();
+        fb.line_aa(1.0, 1.3, 15.0, 9.1);
+        for x in 2..15 {
+            assert!((column_ink(&fb, x) - 1.0).abs() < 0.02, "column {}", x);
+        }
+    }
+
+    #[test]
+    fn test_aa_endpoints_above_the_top_row() {
+        // 0.7 of a line at y = -0.3 falls on row 0, at the endpoint as in between
+        let mut fb = white_canvas();
+        fb.line_aa(2.5, -0.3, 12.0, -0.3);
+        let endpoint = fb.get_point(3, 0).unwrap();
+        assert_eq!(Some(endpoint), fb.get_point(6, 0));
+        assert!((endpoint.r as i32 - 77).abs() <= 1, "{}", endpoint);
+    }
+
+    #[test]
+    fn test_aa_clips_long_lines() {
+        // only the columns inside the framebuffer are walked
+        let mut fb = white_canvas();
+        fb.line_aa(0.0, 5.0, 1e8, 5.0);
+        assert!((1..20).all(|x| fb.get_point(x, 5) == Some(Color::from_hex(0x000000))));
+
+        let mut fb = white_canvas();
+        fb.line_aa(-1e7, -1e7, 1e7, 1e7);
+        assert!((0..20).all(|i| fb.get_point(i, i) == Some(Color::from_hex(0x000000))));
+        assert_eq!(fb.get_point(6, 5), Some(Color::from_hex(0xFFFFFF)));
+    }
+
+    #[test]
+    fn test_aa_steep_and_reversed() {
+        let mut a = white_canvas();
+        let mut b = white_canvas();
+        a.line_aa(3.0, 1.0, 7.5, 17.0);
+        b.line_aa(7.5, 17.0, 3.0, 1.0);
+        for y in 0..20 {
+            for x in 0..20 {
+                assert_eq!(a.get_point(x, y), b.get_point(x, y));
+            }
+        }
+        // steep lines cover every row they cross
+        for y in 2..17 {
+            assert!((0..20).any(|x| a.get_point(x, y) != Some(Color::from_hex(0xFFFFFF))));
+        }
+    }
+
+    #[test]
+    fn test_hairline_dashes_are_pixel_exact() {
+        let mut fb = white_canvas();
+        fb.set_stroke_pattern(StrokePattern::dashed(&[3.0, 2.0]));
+        fb.draw_line(0.0, 5.0, 19.0, 5.0);
+        let row: String = (0..20).map(|x| if fb.get_point(x, 5) == Some(Color::from_hex(0x000000)) { '#' } else { '.' }).collect();
+        assert_eq!(row, "###..###..###..###..");
+
+        let mut fb = white_canvas();
+        fb.set_stroke_pattern(StrokePattern::Dotted);
+        fb.draw_line(0.0, 5.0, 9.0, 5.0);
+        let row: String = (0..10).map(|x| if fb.get_point(x, 5) == Some(Color::from_hex(0x000000)) { '#' } else { '.' }).collect();
+        assert_eq!(row, "#.#.#.#.#.");
+    }
+
+    #[test]
+    fn test_pattern_continues_across_vertices() {
+        let mut fb = white_canvas();
+        fb.set_stroke_pattern(StrokePattern::bitmask(0b0111, 4));
+        // 10 pixels right, then down; the pattern does not restart at the corner
+        fb.stroke_polyline(&[Vec3::new(2.0, 2.0, 0.0), Vec3::new(12.0, 2.0, 0.0), Vec3::new(12.0, 12.0, 0.0)], false);
+        let ink = |x, y| fb.get_point(x, y) == Some(Color::from_hex(0x000000));
+        let row: String = (2..=12).map(|x| if ink(x, 2) { '#' } else { '.' }).collect();
+        let column: String = (2..=12).map(|y| if ink(12, y) { '#' } else { '.' }).collect();
+        assert_eq!(row, "###.###.###");
+        assert_eq!(column, "#.###.###.#");
+    }
+
+    #[test]
+    fn test_draw_line_uses_framebuffer_mode() {
+        let mut fb = white_canvas();
+        fb.draw_line(2.0, 5.5, 10.0, 5.5);
+        assert_eq!(fb.get_point(6, 6), Some(Color::from_hex(0x000000)));
+
+        let mut fb = white_canvas();
+        fb.set_line_mode(LineMode::AntiAliased);
+        fb.draw_line(2.0, 5.5, 10.0, 5.5);
+        assert_ne!(fb.get_point(6, 6), Some(Color::from_hex(0x000000)));
+    }
+}
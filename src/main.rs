@@ -1,171 +1,245 @@
-mod framebuffer;
-mod line_impl;
-mod bmp;
+use std::env;
+use std::path::Path;
+use std::process;
 
-use framebuffer::Framebuffer;
-use line_impl::Line;
 use nalgebra_glm::Vec3;
 
-/// Draws a polygon by connecting the given vertices with lines.
-/// The vertices must be provided in the order they are to be connected.
-fn draw_polygon(framebuffer: &mut Framebuffer, vertices: &[Vec3], line_color: u32) {
-    if vertices.len() < 3 {
-        println!("Need at least 3 vertices to draw a polygon");
-        return;
-    }
+use hello_world::bmp::BmpFormat;
+use hello_world::camera::{Camera, Projection};
+use hello_world::light::{Light, Lighting};
+use hello_world::mesh::{draw_wireframe, shade_mesh, Material};
+use hello_world::obj::read_obj_file;
+use hello_world::pipeline::{Pipeline, Viewport};
+use hello_world::png::PngOptions;
+use hello_world::ppm::PnmFormat;
+use hello_world::scene::read_scene_file;
+use hello_world::svg::{parse_svg, read_svg_file};
+use hello_world::{Color, Error, Framebuffer, Result};
+
+// The demo polygons, drawn as white outlines and then filled
+const DEMO_SVG: &str = include_str!("../demo.svg");
+
+const USAGE: &str = "usage: hello_world [render <scene.toml|image.svg|model.obj> [-o <output>] [--format bmp|png|ppm|pgm] [--wireframe]]
+
+Without arguments, renders the built-in demo to out.bmp. Models are framed
+automatically and drawn shaded, or as outlines with --wireframe.";
+
+// Size of the image a model is rendered to
+const MODEL_SIZE: (usize, usize) = (800, 600);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum OutputFormat {
+    Bmp,
+    Png,
+    Ppm,
+    Pgm,
+}
 
-    framebuffer.set_current_color(line_color);
+impl OutputFormat {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "bmp" => Some(OutputFormat::Bmp),
+            "png" => Some(OutputFormat::Png),
+            "ppm" => Some(OutputFormat::Ppm),
+            "pgm" => Some(OutputFormat::Pgm),
+            _ => None,
+        }
+    }
 
-    for i in 0..vertices.len() {
-        let next = (i + 1) % vertices.len();
-        let start = vertices[i];
-        let end = vertices[next];
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Bmp => "bmp",
+            OutputFormat::Png => "png",
+            OutputFormat::Ppm => "ppm",
+            OutputFormat::Pgm => "pgm",
+        }
+    }
 
-        // Convert from glm::Vec3 to (isize, isize) for framebuffer drawing
-        framebuffer.Line(
-            start.x as isize,
-            start.y as isize,
-            end.x as isize,
-            end.y as isize,
-        );
+    fn write(self, framebuffer: &Framebuffer, output: &str) -> Result<()> {
+        match self {
+            OutputFormat::Bmp => framebuffer.render_bmp(output, BmpFormat::Rgb24),
+            OutputFormat::Png => framebuffer.render_png(output, PngOptions::default()),
+            OutputFormat::Ppm => framebuffer.render_pnm(output, PnmFormat::RawRgb),
+            OutputFormat::Pgm => framebuffer.render_pnm(output, PnmFormat::RawGray),
+        }
     }
 }
 
-/// Fills a polygon using the scanline algorithm.
-fn fill_polygon(framebuffer: &mut Framebuffer, vertices: &[Vec3], fill_color: u32) {
-    if vertices.len() < 3 {
-        println!("Need at least 3 vertices to fill a polygon");
-        return;
+// What the command line asked for
+#[derive(Debug, PartialEq)]
+enum Command {
+    Demo,
+    Render { input: String, output: String, format: OutputFormat, wireframe: bool },
+}
+
+fn parse_args(args: &[String]) -> std::result::Result<Command, String> {
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(Command::Demo);
+    };
+    if command != "render" {
+        return Err(format!("unknown command \"{}\"", command));
     }
 
-    let mut edges: Vec<(isize, isize, isize, isize)> = Vec::new();
+    let mut input = None;
+    let mut output = None;
+    let mut format = None;
+    let mut wireframe = false;
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = Some(rest.next().ok_or_else(|| format!("{} needs a file name", arg))?.clone());
+            }
+            "-f" | "--format" => {
+                let name = rest.next().ok_or_else(|| format!("{} needs a format", arg))?;
+                format = Some(OutputFormat::from_name(name).ok_or_else(|| format!("unknown format \"{}\"", name))?);
+            }
+            "--wireframe" => wireframe = true,
+            _ if arg.starts_with('-') => return Err(format!("unknown option \"{}\"", arg)),
+            _ if input.is_none() => input = Some(arg.clone()),
+            _ => return Err(format!("unexpected argument \"{}\"", arg)),
+        }
+    }
+    let input = input.ok_or("render needs a scene file")?;
+
+    // an explicit format wins, otherwise the output's extension decides
+    let format = match (format, &output) {
+        (Some(format), _) => format,
+        (None, Some(output)) => {
+            let extension = Path::new(output).extension().and_then(|extension| extension.to_str()).unwrap_or("");
+            OutputFormat::from_name(extension)
+                .ok_or_else(|| format!("cannot tell the format of \"{}\", pass --format", output))?
+        }
+        (None, None) => OutputFormat::Bmp,
+    };
+    let output = output.unwrap_or_else(|| format!("out.{}", format.extension()));
+    Ok(Command::Render { input, output, format, wireframe })
+}
 
-    for i in 0..vertices.len() {
-        let next = (i + 1) % vertices.len();
-        let start = vertices[i];
-        let end = vertices[next];
-        edges.push((start.x as isize, start.y as isize, end.x as isize, end.y as isize));
+fn render(input: &str, wireframe: bool) -> Result<Framebuffer> {
+    let lowercase = input.to_ascii_lowercase();
+    if lowercase.ends_with(".obj") {
+        render_model(input, wireframe)
+    } else if lowercase.ends_with(".svg") {
+        let svg = read_svg_file(input)?;
+        let (width, height) = svg
+            .size()?
+            .ok_or_else(|| Error::InvalidData(format!("{} does not declare a width and height", input)))?;
+        let mut framebuffer = Framebuffer::new(width, height);
+        svg.render(&mut framebuffer)?;
+        Ok(framebuffer)
+    } else {
+        read_scene_file(input)?.render()
     }
+}
 
-    let min_y = vertices.iter().map(|v| v.y as isize).min().unwrap();
-    let max_y = vertices.iter().map(|v| v.y as isize).max().unwrap();
+fn render_model(input: &str, wireframe: bool) -> Result<Framebuffer> {
+    let mesh = read_obj_file(input)?;
+    let (min, max) = mesh.bounds().ok_or_else(|| Error::InvalidData(format!("{} has no vertices", input)))?;
 
-    framebuffer.set_current_color(fill_color);
+    // look from the front, above and to the right, just far enough to see the
+    // model's bounding sphere whole
+    let (center, radius) = ((min + max) / 2.0, ((max - min).norm() / 2.0).max(1e-3));
+    let fov_y = 45f32.to_radians();
+    let distance = radius / (fov_y / 2.0).sin();
+    let projection = Projection::Perspective { fov_y, near: distance * 0.01, far: distance + radius * 2.0 };
+    let eye = center + Vec3::new(1.0, 0.8, 2.0).normalize() * distance;
+    let camera = Camera::new(eye, center, Vec3::y(), projection);
 
-    for y in min_y..=max_y {
-        let mut intersections: Vec<isize> = Vec::new();
-        for &(x1, y1, x2, y2) in &edges {
-            if (y1 <= y && y < y2) || (y2 <= y && y < y1) {
-                let x = x1 + (y - y1) * (x2 - x1) / (y2 - y1);
-                intersections.push(x);
-            }
-        }
-        intersections.sort();
+    let (width, height) = MODEL_SIZE;
+    let mut framebuffer = Framebuffer::new(width, height);
+    framebuffer.set_background_color(Color::from_hex(0x202020));
+    framebuffer.clear();
+    let pipeline = Pipeline::new(&camera, Viewport::new(width, height))?;
+    if wireframe {
+        draw_wireframe(&mut framebuffer, &pipeline, &mesh, Color::from_hex(0xFFFFFF));
+    } else {
+        // a key light from above the camera's left shoulder and a dim fill from the right
+        let lighting = Lighting {
+            ambient: Color::from_hex(0x303030),
+            lights: vec![
+                Light::Directional { direction: Vec3::new(0.5, -1.0, -1.0), color: Color::from_hex(0xD0D0D0) },
+                Light::Directional { direction: Vec3::new(-1.0, -0.2, -0.3), color: Color::from_hex(0x404040) },
+            ],
+        };
+        let material = Material {
+            diffuse: Color::from_hex(0xCCCCCC),
+            specular: Color::from_hex(0x404040),
+            ..Material::new("default")
+        };
+        shade_mesh(&mut framebuffer, &pipeline, &mesh, &lighting, &material);
+    }
+    Ok(framebuffer)
+}
 
-        for i in (0..intersections.len()).step_by(2) {
-            if i + 1 < intersections.len() {
-                framebuffer.Line(intersections[i], y, intersections[i + 1], y);
-            }
+fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Demo => {
+            let svg = parse_svg(DEMO_SVG)?;
+            let (width, height) = svg.size()?.unwrap_or((800, 600));
+            let mut framebuffer = Framebuffer::new(width, height);
+            svg.render(&mut framebuffer)?;
+
+            // Save the framebuffer as a BMP file
+            framebuffer.render_buffer("out.bmp")
         }
+        Command::Render { input, output, format, wireframe } => format.write(&render(&input, wireframe)?, &output),
     }
 }
 
 fn main() {
-    let width = 800;
-    let height = 600;
-    let mut framebuffer = Framebuffer::new(width, height);
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    let command = match parse_args(&args) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(err) = run(command) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
 
-    // Clear the framebuffer with a white background
-    framebuffer.set_background_color(0xFFFFFF);
-    framebuffer.clear();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
 
-    // Define los vertices del poligono
-    let vertices1 = vec![
-        Vec3::new(165.0, 380.0, 0.0),
-        Vec3::new(185.0, 360.0, 0.0),
-        Vec3::new(180.0, 330.0, 0.0),
-        Vec3::new(207.0, 345.0, 0.0),
-        Vec3::new(233.0, 330.0, 0.0),
-        Vec3::new(230.0, 360.0, 0.0),
-        Vec3::new(250.0, 380.0, 0.0),
-        Vec3::new(220.0, 385.0, 0.0),
-        Vec3::new(205.0, 410.0, 0.0),
-        Vec3::new(193.0, 383.0, 0.0),
-    ];
-
-    let vertices2 = vec![
-        Vec3::new(321.0, 335.0, 0.0),
-        Vec3::new(288.0, 286.0, 0.0),
-        Vec3::new(339.0, 251.0, 0.0),
-        Vec3::new(374.0, 302.0, 0.0),
-    ];    
-
-    let vertices3 = vec![
-        Vec3::new(377.0, 249.0, 0.0),
-        Vec3::new(411.0, 197.0, 0.0),
-        Vec3::new(436.0, 249.0, 0.0),
-    ];
-
-    let vertices4 = vec![
-        Vec3::new(413.0, 177.0, 0.0),
-        Vec3::new(448.0, 159.0, 0.0),
-        Vec3::new(502.0, 88.0, 0.0),
-        Vec3::new(553.0, 53.0, 0.0),
-        Vec3::new(535.0, 36.0, 0.0),
-        Vec3::new(676.0, 37.0, 0.0),
-        Vec3::new(660.0, 52.0, 0.0),
-        Vec3::new(750.0, 145.0, 0.0),
-        Vec3::new(761.0, 179.0, 0.0),
-        Vec3::new(672.0, 192.0, 0.0),
-        Vec3::new(659.0, 214.0, 0.0),
-        Vec3::new(615.0, 214.0, 0.0),
-        Vec3::new(632.0, 230.0, 0.0),
-        Vec3::new(580.0, 230.0, 0.0),
-        Vec3::new(597.0, 215.0, 0.0),
-        Vec3::new(552.0, 214.0, 0.0),
-        Vec3::new(517.0, 144.0, 0.0),
-        Vec3::new(466.0, 180.0, 0.0),
-    ];
-    
-    let vertices5 = vec![
-        Vec3::new(682.0, 175.0, 0.0),
-        Vec3::new(708.0, 120.0, 0.0),
-        Vec3::new(735.0, 148.0, 0.0),
-        Vec3::new(739.0, 170.0, 0.0),
-    ];
-
-
-
-    // Define the colors
-    let line_color1 = 0xFFFFFF;
-    let fill_color1 = 0xFFFF00;
-
-    let line_color2 = 0xFFFFFF;
-    let fill_color2 = 0x0000FF;
-    
-    let line_color3 = 0xFFFFFF;
-    let fill_color3 = 0xFF0000;
-    
-    let line_color4 = 0xFFFFFF;
-    let fill_color4 = 0x00FF00;
-
-    let line_color5 = 0xFFFFFF;
-    let fill_color5 = 0xFFFFFF;
-
-    // Draw a polygon using the defined vertices
-    draw_polygon(&mut framebuffer, &vertices1, line_color1);
-    draw_polygon(&mut framebuffer, &vertices2, line_color2);
-    draw_polygon(&mut framebuffer, &vertices3, line_color3);
-    draw_polygon(&mut framebuffer, &vertices4, line_color4);
-    draw_polygon(&mut framebuffer, &vertices5, line_color5);
-
-    // Fill the polygon
-    fill_polygon(&mut framebuffer, &vertices1, fill_color1);
-    fill_polygon(&mut framebuffer, &vertices2, fill_color2);
-    fill_polygon(&mut framebuffer, &vertices3, fill_color3);
-    fill_polygon(&mut framebuffer, &vertices4, fill_color4);
-    fill_polygon(&mut framebuffer, &vertices5, fill_color5);
-
-    // Save the framebuffer as a BMP file
-    framebuffer.render_buffer("out.bmp");
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&[]), Ok(Command::Demo));
+        assert_eq!(
+            parse_args(&args("render scene.toml -o out.png --format png")),
+            Ok(Command::Render { input: "scene.toml".into(), output: "out.png".into(), format: OutputFormat::Png, wireframe: false })
+        );
+        // the format follows the output name, or names the default output
+        assert_eq!(
+            parse_args(&args("render scene.toml -o picture.PGM")),
+            Ok(Command::Render { input: "scene.toml".into(), output: "picture.PGM".into(), format: OutputFormat::Pgm, wireframe: false })
+        );
+        assert_eq!(
+            parse_args(&args("render scene.toml --format ppm")),
+            Ok(Command::Render { input: "scene.toml".into(), output: "out.ppm".into(), format: OutputFormat::Ppm, wireframe: false })
+        );
+        assert_eq!(
+            parse_args(&args("render model.obj --wireframe")),
+            Ok(Command::Render { input: "model.obj".into(), output: "out.bmp".into(), format: OutputFormat::Bmp, wireframe: true })
+        );
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        for line in ["draw scene.toml", "render", "render a.toml b.toml", "render a.toml -o", "render a.toml --format gif", "render a.toml -o out.gif", "render a.toml --verbose"] {
+            assert!(parse_args(&args(line)).is_err(), "{:?} should be rejected", line);
+        }
+    }
 }
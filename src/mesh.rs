@@ -0,0 +1,305 @@
+//! Polygon meshes and their rendering through a [`Pipeline`].
+
+use std::collections::HashSet;
+
+use nalgebra_glm::{Vec2, Vec3};
+
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::light::{Lighting, PhongShader};
+use crate::pipeline::Pipeline;
+use crate::shader::draw_triangles;
+
+/// A named surface: its color, and the highlights it shows when lit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub name: String,
+    pub diffuse: Color,
+    /// The color of specular highlights; black for a matte surface.
+    pub specular: Color,
+    /// The Blinn-Phong exponent: the higher, the smaller and sharper the highlights.
+    pub shininess: f32,
+}
+
+impl Material {
+    /// A matte material with the light gray that MTL files use when `Kd` is
+    /// missing.
+    pub fn new(name: &str) -> Self {
+        Material { name: name.to_string(), diffuse: Color::new(204, 204, 204), specular: Color::new(0, 0, 0), shininess: 32.0 }
+    }
+}
+
+/// A corner of a face: indices into the mesh's positions, texture coordinates
+/// and normals.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FaceVertex {
+    pub position: usize,
+    pub uv: Option<usize>,
+    pub normal: Option<usize>,
+}
+
+/// A polygon of the mesh and the triangles covering it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Face {
+    pub vertices: Vec<FaceVertex>,
+    /// Triangles as indices into `vertices`.
+    pub triangles: Vec<[usize; 3]>,
+    /// Index into the mesh's materials.
+    pub material: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Mesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<Vec2>,
+    pub faces: Vec<Face>,
+    pub materials: Vec<Material>,
+}
+
+impl Mesh {
+    /// The corners of the smallest axis-aligned box holding every position.
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let first = *self.positions.first()?;
+        Some(self.positions.iter().fold((first, first), |(min, max), position| (min.inf(position), max.sup(position))))
+    }
+
+    /// Every triangle of the mesh, as positions, with the index of its material.
+    pub fn triangles(&self) -> impl Iterator<Item = ([Vec3; 3], Option<usize>)> + '_ {
+        self.faces.iter().flat_map(move |face| {
+            face.triangles
+                .iter()
+                .map(move |triangle| (triangle.map(|corner| self.positions[face.vertices[corner].position]), face.material))
+        })
+    }
+
+    /// Replaces the mesh's materials with the library's materials of the same name.
+    pub fn apply_materials(&mut self, library: &[Material]) {
+        for material in &mut self.materials {
+            if let Some(found) = library.iter().find(|found| found.name == material.name) {
+                *material = found.clone();
+            }
+        }
+    }
+
+    /// The unit normal of a face's plane, on the side it turns counterclockwise
+    /// from; zero for a degenerate face.
+    pub fn face_normal(&self, face: &Face) -> Vec3 {
+        let points: Vec<Vec3> = face.vertices.iter().map(|vertex| self.positions[vertex.position]).collect();
+        newell_normal(&points).try_normalize(0.0).unwrap_or_default()
+    }
+
+    /// The diffuse color of a face's material, or `default` without one.
+    pub fn face_color(&self, material: Option<usize>, default: Color) -> Color {
+        material.map_or(default, |index| self.materials[index].diffuse)
+    }
+}
+
+/// Splits a planar polygon into triangles by ear clipping, as indices into
+/// `points` keeping the polygon's winding. Concave polygons are covered exactly;
+/// degenerate ones fall back to a fan.
+pub(crate) fn triangulate(points: &[Vec3]) -> Vec<[usize; 3]> {
+    let count = points.len();
+    if count < 3 {
+        return Vec::new();
+    }
+    let fan = |remaining: &[usize]| (1..remaining.len() - 1).map(|i| [remaining[0], remaining[i], remaining[i + 1]]).collect();
+    let mut remaining: Vec<usize> = (0..count).collect();
+    if count == 3 {
+        return fan(&remaining);
+    }
+
+    let normal = newell_normal(points);
+    let axis = normal.iamax();
+    if normal[axis] == 0.0 {
+        return fan(&remaining);
+    }
+    // drop the dominant axis, flipping the projection so the polygon turns counterclockwise
+    let (u, v) = [(1, 2), (2, 0), (0, 1)][axis];
+    let sign = normal[axis].signum();
+    let flat: Vec<Vec2> = points.iter().map(|point| Vec2::new(point[u], point[v] * sign)).collect();
+    let cross = |o: Vec2, a: Vec2, b: Vec2| (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x);
+
+    let mut triangles = Vec::with_capacity(count - 2);
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let [prev, current, next] = [remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]];
+            let (a, b, c) = (flat[prev], flat[current], flat[next]);
+            // a convex corner with no other corner inside or on its triangle
+            cross(a, b, c) > 0.0
+                && remaining.iter().all(|&other| {
+                    let p = flat[other];
+                    [prev, current, next].contains(&other) || cross(a, b, p) < 0.0 || cross(b, c, p) < 0.0 || cross(c, a, p) < 0.0
+                })
+        });
+        let Some(i) = ear else {
+            triangles.extend(fan(&remaining));
+            return triangles;
+        };
+        triangles.push([remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]]);
+        remaining.remove(i);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+// Newell's method gives the normal of a polygon's best-fitting plane, scaled by
+// twice its area and pointing where the polygon turns counterclockwise
+fn newell_normal(points: &[Vec3]) -> Vec3 {
+    let mut normal = Vec3::zeros();
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        normal += Vec3::new((a.y - b.y) * (a.z + b.z), (a.z - b.z) * (a.x + b.x), (a.x - b.x) * (a.y + b.y));
+    }
+    normal
+}
+
+/// Draws the outline of every face with the framebuffer's line mode and stroke
+/// style. Edges shared by two faces are drawn once.
+pub fn draw_wireframe(framebuffer: &mut Framebuffer, pipeline: &Pipeline, mesh: &Mesh, color: Color) {
+    let mut drawn = HashSet::new();
+    for face in &mesh.faces {
+        for (i, from) in face.vertices.iter().enumerate() {
+            let to = face.vertices[(i + 1) % face.vertices.len()];
+            let edge = (from.position.min(to.position), from.position.max(to.position));
+            if drawn.insert(edge) {
+                pipeline.draw_line(framebuffer, mesh.positions[edge.0], mesh.positions[edge.1], color);
+            }
+        }
+    }
+}
+
+/// Fills every face with its material's diffuse color, or `default_color` when
+/// it has none, hiding what is behind with the depth buffer.
+pub fn fill_mesh(framebuffer: &mut Framebuffer, pipeline: &Pipeline, mesh: &Mesh, default_color: Color) {
+    for (triangle, material) in mesh.triangles() {
+        pipeline.fill_triangle(framebuffer, &triangle, mesh.face_color(material, default_color));
+    }
+}
+
+/// Fills every face lit by `lighting`, with normals interpolated from its
+/// vertices' normals, or the face's own normal where they have none. Faces
+/// without a material use `default_material`.
+pub fn shade_mesh(framebuffer: &mut Framebuffer, pipeline: &Pipeline, mesh: &Mesh, lighting: &Lighting, default_material: &Material) {
+    for face in &mesh.faces {
+        let material = face.material.map_or(default_material, |index| &mesh.materials[index]);
+        let face_normal = mesh.face_normal(face);
+        let vertices: Vec<(Vec3, Vec3)> = face
+            .vertices
+            .iter()
+            .map(|vertex| (mesh.positions[vertex.position], vertex.normal.map_or(face_normal, |index| mesh.normals[index])))
+            .collect();
+        let shader = PhongShader::new(pipeline, lighting, material);
+        draw_triangles(framebuffer, &pipeline.viewport, &shader, &shader, &vertices, &face.triangles);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{Camera, Projection};
+    use crate::light::Light;
+    use crate::pipeline::Viewport;
+
+    // a unit cube's front face in red and its back face in blue
+    fn two_squares() -> Mesh {
+        let corner = |position| FaceVertex { position, uv: None, normal: None };
+        let square = |start: usize, material| Face {
+            vertices: (start..start + 4).map(corner).collect(),
+            triangles: vec![[0, 1, 2], [0, 2, 3]],
+            material: Some(material),
+        };
+        let mut positions = Vec::new();
+        for z in [0.5, -0.5] {
+            positions.extend([Vec3::new(-0.5, -0.5, z), Vec3::new(0.5, -0.5, z), Vec3::new(0.5, 0.5, z), Vec3::new(-0.5, 0.5, z)]);
+        }
+        let mut red = Material::new("red");
+        red.diffuse = Color::from_hex(0xFF0000);
+        let mut blue = Material::new("blue");
+        blue.diffuse = Color::from_hex(0x0000FF);
+        Mesh { positions, faces: vec![square(4, 1), square(0, 0)], materials: vec![red, blue], ..Mesh::default() }
+    }
+
+    fn pipeline() -> Pipeline {
+        let projection = Projection::Perspective { fov_y: 1.0, near: 0.1, far: 10.0 };
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 3.0), Vec3::zeros(), Vec3::y(), projection);
+        Pipeline::new(&camera, Viewport::new(40, 40)).unwrap()
+    }
+
+    #[test]
+    fn test_bounds_and_triangles() {
+        let mesh = two_squares();
+        assert_eq!(mesh.bounds(), Some((Vec3::new(-0.5, -0.5, -0.5), Vec3::new(0.5, 0.5, 0.5))));
+        assert_eq!(Mesh::default().bounds(), None);
+        assert_eq!(mesh.triangles().count(), 4);
+        assert_eq!(mesh.face_color(mesh.faces[0].material, Color::default()), Color::from_hex(0x0000FF));
+        assert_eq!(mesh.face_normal(&mesh.faces[1]), Vec3::z());
+    }
+
+    #[test]
+    fn test_triangulate() {
+        // an L-shaped hexagon in the xz plane, starting next to its concave corner
+        // at (1, 1) where a fan would reach outside
+        let outline = [(2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0), (0.0, 0.0), (2.0, 0.0)];
+        let points: Vec<Vec3> = outline.iter().map(|&(x, z)| Vec3::new(x, 5.0, z)).collect();
+        for points in [points.clone(), points.iter().rev().copied().collect()] {
+            let triangles = triangulate(&points);
+            assert_eq!(triangles.len(), 4);
+            let area: f32 = triangles
+                .iter()
+                .map(|&[a, b, c]| (points[b] - points[a]).cross(&(points[c] - points[a])).norm() / 2.0)
+                .sum();
+            assert!((area - 3.0).abs() < 1e-5, "area {}", area);
+        }
+        assert_eq!(triangulate(&points[..3]), vec![[0, 1, 2]]);
+        assert!(triangulate(&points[..2]).is_empty());
+    }
+
+    #[test]
+    fn test_fill_hides_farther_faces() {
+        let mut fb = Framebuffer::new(40, 40);
+        // the far square is drawn last but stays hidden
+        let mut mesh = two_squares();
+        mesh.faces.reverse();
+        fill_mesh(&mut fb, &pipeline(), &mesh, Color::from_hex(0xFFFFFF));
+        assert_eq!(fb.get_point(20, 20), Some(Color::from_hex(0xFF0000)));
+        assert_eq!(fb.get_point(0, 0), Some(Color::from_hex(0x000000)));
+    }
+
+    #[test]
+    fn test_shade_mesh() {
+        let mut mesh = two_squares();
+        let lighting = |direction| Lighting {
+            ambient: Color::from_hex(0x000000),
+            lights: vec![Light::Directional { direction, color: Color::from_hex(0xFFFFFF) }],
+        };
+        let shade = |mesh: &Mesh, direction| {
+            let mut fb = Framebuffer::new(40, 40);
+            shade_mesh(&mut fb, &pipeline(), mesh, &lighting(direction), &Material::new("default"));
+            fb.get_point(20, 20).unwrap()
+        };
+        // the near face takes its own normal, facing the camera
+        assert_eq!(shade(&mesh, -Vec3::z()), Color::from_hex(0xFF0000));
+        assert_eq!(shade(&mesh, Vec3::new(1.0, 0.0, -1.0)), Color::from_hex(0xB40000));
+
+        // vertex normals turned away from the light leave it dark
+        mesh.normals.push(Vec3::new(1.0, 0.0, 0.2));
+        for vertex in &mut mesh.faces[1].vertices {
+            vertex.normal = Some(0);
+        }
+        assert_eq!(shade(&mesh, Vec3::new(1.0, 0.0, -1.0)), Color::from_hex(0x000000));
+    }
+
+    #[test]
+    fn test_wireframe() {
+        let mut fb = Framebuffer::new(40, 40);
+        draw_wireframe(&mut fb, &pipeline(), &two_squares(), Color::from_hex(0xFFFFFF));
+        // the outlines are drawn, not the diagonals or the insides
+        let white = Some(Color::from_hex(0xFFFFFF));
+        let edge = (0..40).find(|&x| fb.get_point(x, 20) == white).unwrap();
+        assert!(edge > 5 && edge < 20);
+        assert_ne!(fb.get_point(20, 20), white);
+        assert_ne!(fb.get_point(17, 22), white);
+    }
+}
@@ -0,0 +1,258 @@
+//! Wavefront OBJ models and their MTL material libraries.
+//!
+//! Reads positions (`v`), texture coordinates (`vt`), normals (`vn`) and faces
+//! (`f`) in every index form, with negative indices counting back from the
+//! latest element. Faces with more than three corners are triangulated. `usemtl`
+//! picks materials from the libraries named by `mtllib`, of which the diffuse
+//! color `Kd`, the specular color `Ks`, the specular exponent `Ns` and the
+//! dissolve `d` are used. Groups, smoothing and the other statements are ignored.
+
+use std::fs;
+use std::path::Path;
+
+use nalgebra_glm::{Vec2, Vec3};
+
+use crate::color::Color;
+use crate::error::{Error, Result};
+use crate::mesh::{triangulate, Face, FaceVertex, Material, Mesh};
+
+/// Reads an OBJ file together with the material libraries it names, which are
+/// looked up next to it.
+pub fn read_obj_file(file_path: &str) -> Result<Mesh> {
+    let text = fs::read_to_string(file_path)?;
+    let mut mesh = parse_obj(&text)?;
+    let directory = Path::new(file_path).parent().unwrap_or(Path::new(""));
+    for line in text.lines() {
+        if let Some(("mtllib", libraries)) = statement(line) {
+            for library in libraries.split_whitespace() {
+                mesh.apply_materials(&parse_mtl(&fs::read_to_string(directory.join(library))?)?);
+            }
+        }
+    }
+    Ok(mesh)
+}
+
+/// Parses an OBJ model. Materials named by `usemtl` keep the default gray until
+/// a library is applied with [`Mesh::apply_materials`].
+pub fn parse_obj(text: &str) -> Result<Mesh> {
+    let mut mesh = Mesh::default();
+    let mut material = None;
+    for (number, line) in text.lines().enumerate() {
+        let Some((keyword, rest)) = statement(line) else {
+            continue;
+        };
+        let error = |message: String| Error::InvalidData(format!("OBJ line {}: {}", number + 1, message));
+        let values = || floats(rest).map_err(error);
+        match keyword {
+            "v" => match values()?[..] {
+                [x, y, z, ..] => mesh.positions.push(Vec3::new(x, y, z)),
+                _ => return Err(error("a position needs x, y and z".to_string())),
+            },
+            "vn" => match values()?[..] {
+                [x, y, z] => mesh.normals.push(Vec3::new(x, y, z)),
+                _ => return Err(error("a normal needs x, y and z".to_string())),
+            },
+            "vt" => match values()?[..] {
+                [u] => mesh.uvs.push(Vec2::new(u, 0.0)),
+                [u, v, ..] => mesh.uvs.push(Vec2::new(u, v)),
+                _ => return Err(error("a texture coordinate needs u".to_string())),
+            },
+            "f" => {
+                let vertices = rest
+                    .split_whitespace()
+                    .map(|corner| face_vertex(&mesh, corner))
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(error)?;
+                if vertices.len() < 3 {
+                    return Err(error(format!("a face needs at least 3 corners, got {}", vertices.len())));
+                }
+                let points: Vec<Vec3> = vertices.iter().map(|vertex| mesh.positions[vertex.position]).collect();
+                mesh.faces.push(Face { triangles: triangulate(&points), vertices, material });
+            }
+            "usemtl" => {
+                let name = rest.trim();
+                material = Some(match mesh.materials.iter().position(|material| material.name == name) {
+                    Some(index) => index,
+                    None => {
+                        mesh.materials.push(Material::new(name));
+                        mesh.materials.len() - 1
+                    }
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(mesh)
+}
+
+/// Parses an MTL material library.
+pub fn parse_mtl(text: &str) -> Result<Vec<Material>> {
+    let mut materials: Vec<Material> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let Some((keyword, rest)) = statement(line) else {
+            continue;
+        };
+        let error = |message: &str| Error::InvalidData(format!("MTL line {}: {}", number + 1, message));
+        if keyword == "newmtl" {
+            materials.push(Material::new(rest.trim()));
+            continue;
+        }
+        let (Some(material), "Kd" | "Ks" | "Ns" | "d") = (materials.last_mut(), keyword) else {
+            continue;
+        };
+        let values = floats(rest).map_err(|message| error(&message))?;
+        let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as i32;
+        match (keyword, &values[..]) {
+            ("Kd", &[r, g, b]) => material.diffuse = Color::new(channel(r), channel(g), channel(b)).with_alpha(material.diffuse.a),
+            ("Ks", &[r, g, b]) => material.specular = Color::new(channel(r), channel(g), channel(b)),
+            ("Ns", &[exponent]) => material.shininess = exponent.max(0.0),
+            ("d", &[dissolve]) => material.diffuse = material.diffuse.with_alpha(channel(dissolve) as u8),
+            ("Kd" | "Ks", _) => return Err(error("a color needs r, g and b")),
+            ("Ns", _) => return Err(error("a specular exponent needs one value")),
+            _ => return Err(error("a dissolve needs one value")),
+        }
+    }
+    Ok(materials)
+}
+
+// The keyword of a line and the rest of it, without comments; None for blank lines
+fn statement(line: &str) -> Option<(&str, &str)> {
+    let line = line.split('#').next().unwrap_or("").trim();
+    if line.is_empty() {
+        return None;
+    }
+    Some(line.split_once(char::is_whitespace).unwrap_or((line, "")))
+}
+
+fn floats(text: &str) -> std::result::Result<Vec<f32>, String> {
+    text.split_whitespace()
+        .map(|value| value.parse::<f32>().ok().filter(|value| value.is_finite()).ok_or_else(|| format!("invalid number \"{}\"", value)))
+        .collect()
+}
+
+// A face corner "v", "v/vt", "v//vn" or "v/vt/vn"
+fn face_vertex(mesh: &Mesh, corner: &str) -> std::result::Result<FaceVertex, String> {
+    let mut parts = corner.split('/');
+    let mut index = |count: usize, kind: &str| -> std::result::Result<Option<usize>, String> {
+        match parts.next() {
+            None | Some("") => Ok(None),
+            Some(part) => resolve(part, count).map(Some).ok_or_else(|| format!("invalid {} index \"{}\" in \"{}\"", kind, part, corner)),
+        }
+    };
+    let position = index(mesh.positions.len(), "position")?.ok_or_else(|| format!("face corner \"{}\" has no position", corner))?;
+    let uv = index(mesh.uvs.len(), "texture coordinate")?;
+    let normal = index(mesh.normals.len(), "normal")?;
+    Ok(FaceVertex { position, uv, normal })
+}
+
+// OBJ indices start at 1, and negative ones count back from the end
+fn resolve(index: &str, count: usize) -> Option<usize> {
+    let index: isize = index.parse().ok()?;
+    let resolved = if index < 0 { count as isize + index } else { index - 1 };
+    (0..count as isize).contains(&resolved).then_some(resolved as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUBE: &str = "# a cube
+mtllib cube.mtl
+o cube
+v -1 -1 -1
+v  1 -1 -1
+v  1  1 -1
+v -1  1 -1
+v -1 -1  1
+v  1 -1  1
+v  1  1  1
+v -1  1  1
+vt 0 0
+vt 1 0
+vt 1 1
+vn 0 0 1
+vn 0 0 -1
+usemtl red
+f 5/1/1 6/2/1 7/3/1 8//1
+f -7//2 -8//2 -5//2 -6//2
+usemtl blue
+s off
+f 1 2 6 5
+f 4 8 7 3
+usemtl red
+f 1 5 8 4
+f 2/1 3/2 7/3 6
+";
+
+    #[test]
+    fn test_parse_obj() {
+        let mesh = parse_obj(CUBE).unwrap();
+        assert_eq!((mesh.positions.len(), mesh.uvs.len(), mesh.normals.len()), (8, 3, 2));
+        assert_eq!(mesh.faces.len(), 6);
+        assert!(mesh.faces.iter().all(|face| face.triangles.len() == 2));
+        assert_eq!(mesh.faces[0].vertices[0], FaceVertex { position: 4, uv: Some(0), normal: Some(0) });
+        assert_eq!(mesh.faces[0].vertices[3], FaceVertex { position: 7, uv: None, normal: Some(0) });
+        // negative indices count back from the last position
+        assert_eq!(mesh.faces[1].vertices[0].position, 1);
+        assert_eq!(mesh.faces[5].vertices[3], FaceVertex { position: 5, uv: None, normal: None });
+
+        let names: Vec<&str> = mesh.materials.iter().map(|material| material.name.as_str()).collect();
+        assert_eq!(names, ["red", "blue"]);
+        let materials: Vec<Option<usize>> = mesh.faces.iter().map(|face| face.material).collect();
+        assert_eq!(materials, [Some(0), Some(0), Some(1), Some(1), Some(0), Some(0)]);
+    }
+
+    #[test]
+    fn test_parse_mtl() {
+        let materials = parse_mtl(
+            "newmtl red\nNs 10\nKd 1.0 0.0 0.0\nKs 0.5 0.5 0.5\nd 0.5\n\n# comment\nnewmtl blue\nKd 0 0 1\nmap_Kd blue.png\nnewmtl plain\n",
+        )
+        .unwrap();
+        assert_eq!(materials.len(), 3);
+        assert_eq!(materials[0].diffuse, Color::new_rgba(255, 0, 0, 128));
+        assert_eq!((materials[0].specular, materials[0].shininess), (Color::new(128, 128, 128), 10.0));
+        assert_eq!(materials[1].diffuse, Color::from_hex(0x0000FF));
+        assert_eq!(materials[2].diffuse, Material::new("plain").diffuse);
+
+        let mut mesh = parse_obj(CUBE).unwrap();
+        mesh.apply_materials(&materials);
+        assert_eq!(mesh.face_color(mesh.faces[2].material, Color::default()), Color::from_hex(0x0000FF));
+        assert!(parse_mtl("newmtl a\nKd 1 0").is_err());
+        assert!(parse_mtl("newmtl a\nNs").is_err());
+    }
+
+    // A scratch directory of this process, removed even when an assertion fails
+    struct ScratchDir(std::path::PathBuf);
+
+    impl Drop for ScratchDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_read_obj_file() {
+        let scratch = ScratchDir(std::env::temp_dir().join(format!("obj_test_cube_{}", std::process::id())));
+        let directory = &scratch.0;
+        fs::create_dir_all(directory).unwrap();
+        fs::write(directory.join("cube.obj"), CUBE).unwrap();
+        fs::write(directory.join("cube.mtl"), "newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\n").unwrap();
+        let mesh = read_obj_file(directory.join("cube.obj").to_str().unwrap()).unwrap();
+        assert_eq!(mesh.materials[0].diffuse, Color::from_hex(0xFF0000));
+        assert_eq!(mesh.materials[1].diffuse, Color::from_hex(0x0000FF));
+
+        // a missing library is an error
+        fs::remove_file(directory.join("cube.mtl")).unwrap();
+        assert!(matches!(read_obj_file(directory.join("cube.obj").to_str().unwrap()), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn test_obj_errors() {
+        let invalid = ["v 1 2", "v 1 2 x", "v 0 0 0\nv 1 0 0\nf 1 2", "v 0 0 0\nf 1 2 3", "v 0 0 0\nf 1 1 0", "v 0 0 0\nf 1 1 -2"];
+        for text in invalid.into_iter().chain(["v 0 0 0\nf 1/1 1 1"]) {
+            assert!(matches!(parse_obj(text), Err(Error::InvalidData(_))), "{:?} should not parse", text);
+        }
+        let error = parse_obj("v 0 0 0\n\nf 1 1 9").unwrap_err();
+        assert!(error.to_string().contains("line 3"), "{}", error);
+    }
+}
@@ -0,0 +1,444 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use nalgebra_glm::{Mat3, Vec2, Vec3};
+
+use crate::transform::transform_vec2;
+
+/// One drawing command of a [`Path`]. Arcs are stored as cubic Béziers.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PathSegment {
+    MoveTo(Vec2),
+    LineTo(Vec2),
+    /// Control point and end point.
+    QuadTo(Vec2, Vec2),
+    /// Two control points and end point.
+    CubicTo(Vec2, Vec2, Vec2),
+    /// Connects the current point back to the start of the subpath.
+    Close,
+}
+
+/// A flattened subpath: the vertices of a polyline and whether it is closed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subpath {
+    pub vertices: Vec<Vec3>,
+    pub closed: bool,
+}
+
+/// A vector path made of lines and Bézier curves, split into subpaths by `move_to`.
+///
+/// A curve issued before any `move_to` starts at its first control point, and a
+/// command following `close` starts a new subpath where the closed one began.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Path {
+    segments: Vec<PathSegment>,
+    start: Option<Vec2>,   // first point of the current subpath
+    current: Option<Vec2>, // end point of the last command
+    open: bool,            // whether the last command left a subpath open
+}
+
+// Deepest subdivision of a curve, 65536 segments
+const MAX_FLATTEN_DEPTH: u32 = 16;
+
+impl Path {
+    pub fn new() -> Self {
+        Path::default()
+    }
+
+    /// Starts a new subpath at (x, y).
+    pub fn move_to(&mut self, x: f32, y: f32) -> &mut Self {
+        let point = Vec2::new(x, y);
+        self.segments.push(PathSegment::MoveTo(point));
+        self.start = Some(point);
+        self.current = Some(point);
+        self.open = true;
+        self
+    }
+
+    /// Adds a straight line to (x, y); with no current point it only moves there.
+    pub fn line_to(&mut self, x: f32, y: f32) -> &mut Self {
+        if self.current.is_none() {
+            return self.move_to(x, y);
+        }
+        let to = Vec2::new(x, y);
+        self.begin_segment(to);
+        self.segments.push(PathSegment::LineTo(to));
+        self.current = Some(to);
+        self
+    }
+
+    /// Adds a quadratic Bézier curve with control point (cx, cy).
+    pub fn quad_to(&mut self, cx: f32, cy: f32, x: f32, y: f32) -> &mut Self {
+        let (control, to) = (Vec2::new(cx, cy), Vec2::new(x, y));
+        self.begin_segment(control);
+        self.segments.push(PathSegment::QuadTo(control, to));
+        self.current = Some(to);
+        self
+    }
+
+    /// Adds a cubic Bézier curve with control points (c1x, c1y) and (c2x, c2y).
+    pub fn cubic_to(&mut self, c1x: f32, c1y: f32, c2x: f32, c2y: f32, x: f32, y: f32) -> &mut Self {
+        let (c1, c2, to) = (Vec2::new(c1x, c1y), Vec2::new(c2x, c2y), Vec2::new(x, y));
+        self.begin_segment(c1);
+        self.segments.push(PathSegment::CubicTo(c1, c2, to));
+        self.current = Some(to);
+        self
+    }
+
+    /// Adds an elliptical arc to (x, y) with the parameters of the SVG `A` command:
+    /// radii, rotation of the x axis in radians, and the flags choosing which of the
+    /// four possible arcs is drawn. Radii too small to reach the end point are scaled
+    /// up, and a zero radius draws a straight line.
+    #[allow(clippy::too_many_arguments)]
+    pub fn arc_to(&mut self, rx: f32, ry: f32, x_axis_rotation: f32, large_arc: bool, sweep: bool, x: f32, y: f32) -> &mut Self {
+        let to = Vec2::new(x, y);
+        let Some(from) = self.current else {
+            return self.move_to(x, y);
+        };
+        if from == to {
+            return self;
+        }
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if rx == 0.0 || ry == 0.0 {
+            return self.line_to(x, y);
+        }
+
+        // endpoint to center parameterization, SVG 1.1 appendix F.6.5
+        let (sin, cos) = x_axis_rotation.sin_cos();
+        let half = (from - to) / 2.0;
+        let p = Vec2::new(cos * half.x + sin * half.y, -sin * half.x + cos * half.y);
+        let lambda = (p.x / rx).powi(2) + (p.y / ry).powi(2);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+        let numerator = (rx * ry).powi(2) - (rx * p.y).powi(2) - (ry * p.x).powi(2);
+        let denominator = (rx * p.y).powi(2) + (ry * p.x).powi(2);
+        let mut factor = (numerator / denominator).max(0.0).sqrt();
+        if large_arc == sweep {
+            factor = -factor;
+        }
+        let center_prime = Vec2::new(factor * rx * p.y / ry, -factor * ry * p.x / rx);
+        let mid = (from + to) / 2.0;
+        let center = Vec2::new(
+            cos * center_prime.x - sin * center_prime.y + mid.x,
+            sin * center_prime.x + cos * center_prime.y + mid.y,
+        );
+
+        let angle = |v: Vec2| v.y.atan2(v.x);
+        let start_angle = angle(Vec2::new((p.x - center_prime.x) / rx, (p.y - center_prime.y) / ry));
+        let end_angle = angle(Vec2::new((-p.x - center_prime.x) / rx, (-p.y - center_prime.y) / ry));
+        let mut delta = (end_angle - start_angle).rem_euclid(TAU);
+        if !sweep && delta > 0.0 {
+            delta -= TAU;
+        }
+
+        // one cubic per quarter turn at most
+        let pieces = (delta.abs() / FRAC_PI_2).ceil().max(1.0) as usize;
+        let step = delta / pieces as f32;
+        let k = 4.0 / 3.0 * (step / 4.0).tan();
+        let point_at = |theta: f32| {
+            let (s, c) = theta.sin_cos();
+            let local = Vec2::new(rx * c, ry * s);
+            let tangent = Vec2::new(-rx * s, ry * c);
+            let rotate = |v: Vec2| Vec2::new(cos * v.x - sin * v.y, sin * v.x + cos * v.y);
+            (center + rotate(local), rotate(tangent))
+        };
+        for i in 0..pieces {
+            let theta0 = start_angle + step * i as f32;
+            let (p0, t0) = point_at(theta0);
+            let (mut p3, t3) = point_at(theta0 + step);
+            if i == pieces - 1 {
+                p3 = to; // land exactly on the requested end point
+            }
+            let (c1, c2) = (p0 + t0 * k, p3 - t3 * k);
+            self.cubic_to(c1.x, c1.y, c2.x, c2.y, p3.x, p3.y);
+        }
+        self
+    }
+
+    /// Adds a closed rectangle whose corners are elliptical arcs with radii rx, ry,
+    /// clamped to half the width and height.
+    pub fn rounded_rect(&mut self, x: f32, y: f32, width: f32, height: f32, rx: f32, ry: f32) -> &mut Self {
+        let rx = rx.abs().min(width.abs() / 2.0);
+        let ry = ry.abs().min(height.abs() / 2.0);
+        self.move_to(x + rx, y).line_to(x + width - rx, y);
+        self.arc_to(rx, ry, 0.0, false, true, x + width, y + ry).line_to(x + width, y + height - ry);
+        self.arc_to(rx, ry, 0.0, false, true, x + width - rx, y + height).line_to(x + rx, y + height);
+        self.arc_to(rx, ry, 0.0, false, true, x, y + height - ry).line_to(x, y + ry);
+        self.arc_to(rx, ry, 0.0, false, true, x + rx, y).close()
+    }
+
+    /// Adds a closed axis-aligned ellipse centered on (cx, cy).
+    pub fn ellipse(&mut self, cx: f32, cy: f32, rx: f32, ry: f32) -> &mut Self {
+        self.move_to(cx + rx, cy);
+        self.arc_to(rx, ry, 0.0, false, true, cx - rx, cy);
+        self.arc_to(rx, ry, 0.0, false, true, cx + rx, cy).close()
+    }
+
+    /// Closes the current subpath with a straight line back to its start.
+    pub fn close(&mut self) -> &mut Self {
+        if self.open {
+            self.segments.push(PathSegment::Close);
+            self.current = self.start;
+            self.open = false;
+        }
+        self
+    }
+
+    /// The end point of the last command, where the next one starts.
+    pub fn current_point(&self) -> Option<Vec2> {
+        self.current
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    /// The path with every point mapped through an affine transform. Affine maps
+    /// keep lines straight and take Bézier curves to Bézier curves, so the result
+    /// is exact.
+    pub fn transformed(&self, matrix: &Mat3) -> Path {
+        let map = |point: Vec2| transform_vec2(matrix, point);
+        let segments = self
+            .segments
+            .iter()
+            .map(|segment| match *segment {
+                PathSegment::MoveTo(point) => PathSegment::MoveTo(map(point)),
+                PathSegment::LineTo(to) => PathSegment::LineTo(map(to)),
+                PathSegment::QuadTo(control, to) => PathSegment::QuadTo(map(control), map(to)),
+                PathSegment::CubicTo(c1, c2, to) => PathSegment::CubicTo(map(c1), map(c2), map(to)),
+                PathSegment::Close => PathSegment::Close,
+            })
+            .collect();
+        Path { segments, start: self.start.map(map), current: self.current.map(map), open: self.open }
+    }
+
+    /// Approximates the curves with line segments that stay within `tolerance`
+    /// pixels of the exact curve, subdividing more where the curve bends more.
+    pub fn flatten(&self, tolerance: f32) -> Vec<Subpath> {
+        let tolerance = tolerance.max(1e-3);
+        let mut subpaths: Vec<Subpath> = Vec::new();
+        let mut current: Option<Subpath> = None;
+        let mut last = Vec2::zeros();
+
+        for segment in &self.segments {
+            match *segment {
+                PathSegment::MoveTo(point) => {
+                    subpaths.extend(current.take());
+                    current = Some(Subpath { vertices: vec![to_vec3(point)], closed: false });
+                    last = point;
+                }
+                PathSegment::LineTo(to) => {
+                    push_vertex(&mut current, last, to);
+                    last = to;
+                }
+                PathSegment::QuadTo(control, to) => {
+                    // a quadratic is the cubic with control points 2/3 of the way to its own
+                    let c1 = last + (control - last) * (2.0 / 3.0);
+                    let c2 = to + (control - to) * (2.0 / 3.0);
+                    flatten_cubic(&mut current, [last, c1, c2, to], tolerance, MAX_FLATTEN_DEPTH);
+                    last = to;
+                }
+                PathSegment::CubicTo(c1, c2, to) => {
+                    flatten_cubic(&mut current, [last, c1, c2, to], tolerance, MAX_FLATTEN_DEPTH);
+                    last = to;
+                }
+                PathSegment::Close => {
+                    if let Some(mut subpath) = current.take() {
+                        let first = subpath.vertices[0];
+                        // the closing edge is implied
+                        if subpath.vertices.len() > 1 && subpath.vertices[subpath.vertices.len() - 1] == first {
+                            subpath.vertices.pop();
+                        }
+                        subpath.closed = true;
+                        last = first.xy();
+                        subpaths.push(subpath);
+                    }
+                }
+            }
+        }
+        subpaths.extend(current);
+        subpaths
+    }
+
+    // Starts a subpath for a drawing command with no current point, or after `close`
+    fn begin_segment(&mut self, first_point: Vec2) {
+        if !self.open {
+            let start = self.current.unwrap_or(first_point);
+            self.move_to(start.x, start.y);
+        }
+    }
+}
+
+fn to_vec3(point: Vec2) -> Vec3 {
+    Vec3::new(point.x, point.y, 0.0)
+}
+
+// Appends a vertex, starting a subpath at `from` when there is none
+fn push_vertex(current: &mut Option<Subpath>, from: Vec2, to: Vec2) {
+    let subpath = current.get_or_insert_with(|| Subpath { vertices: vec![to_vec3(from)], closed: false });
+    subpath.vertices.push(to_vec3(to));
+}
+
+// Recursive de Casteljau subdivision until the control points lie within
+// `tolerance` of the chord, which bounds the distance to the curve
+fn flatten_cubic(current: &mut Option<Subpath>, points: [Vec2; 4], tolerance: f32, depth: u32) {
+    let [p0, p1, p2, p3] = points;
+    if depth == 0 || (distance_to_segment(p1, p0, p3) <= tolerance && distance_to_segment(p2, p0, p3) <= tolerance) {
+        push_vertex(current, p0, p3);
+        return;
+    }
+
+    let p01 = (p0 + p1) / 2.0;
+    let p12 = (p1 + p2) / 2.0;
+    let p23 = (p2 + p3) / 2.0;
+    let p012 = (p01 + p12) / 2.0;
+    let p123 = (p12 + p23) / 2.0;
+    let mid = (p012 + p123) / 2.0;
+    flatten_cubic(current, [p0, p01, p012, mid], tolerance, depth - 1);
+    flatten_cubic(current, [mid, p123, p23, p3], tolerance, depth - 1);
+}
+
+fn distance_to_segment(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let direction = end - start;
+    let length_squared = direction.norm_squared();
+    if length_squared == 0.0 {
+        return (point - start).norm();
+    }
+    let t = ((point - start).dot(&direction) / length_squared).clamp(0.0, 1.0);
+    (point - (start + direction * t)).norm()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::{scaling, translation};
+
+    fn assert_near(a: Vec3, b: Vec2) {
+        assert!((a.xy() - b).norm() < 1e-3, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_lines_and_subpaths() {
+        let mut path = Path::new();
+        path.move_to(1.0, 1.0).line_to(5.0, 1.0).line_to(5.0, 4.0).close();
+        path.line_to(0.0, 4.0); // starts again where the closed subpath began
+        path.move_to(10.0, 10.0).line_to(12.0, 10.0);
+
+        let subpaths = path.flatten(0.1);
+        assert_eq!(subpaths.len(), 3);
+        assert!(subpaths[0].closed);
+        assert_eq!(subpaths[0].vertices, vec![Vec3::new(1.0, 1.0, 0.0), Vec3::new(5.0, 1.0, 0.0), Vec3::new(5.0, 4.0, 0.0)]);
+        assert_eq!(subpaths[1].vertices, vec![Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 4.0, 0.0)]);
+        assert!(!subpaths[2].closed);
+        assert_eq!(path.current_point(), Some(Vec2::new(12.0, 10.0)));
+
+        // a line with no current point starts its own subpath
+        let mut path = Path::new();
+        path.line_to(3.0, 3.0).line_to(4.0, 3.0);
+        assert_eq!(path.flatten(0.1)[0].vertices.len(), 2);
+    }
+
+    #[test]
+    fn test_curves_stay_within_tolerance() {
+        let mut path = Path::new();
+        path.move_to(0.0, 0.0).quad_to(50.0, 100.0, 100.0, 0.0);
+        let vertices = &path.flatten(0.25)[0].vertices;
+        assert_near(vertices[0], Vec2::new(0.0, 0.0));
+        assert_near(vertices[vertices.len() - 1], Vec2::new(100.0, 0.0));
+        // every vertex lies on y = x (100 - x) / 50, the exact parabola
+        for v in vertices {
+            assert!((v.y - v.x * (100.0 - v.x) / 50.0).abs() < 0.01);
+        }
+        // and the chords between them never stray far from it
+        for pair in vertices.windows(2) {
+            let mid = (pair[0] + pair[1]) / 2.0;
+            assert!(mid.x * (100.0 - mid.x) / 50.0 - mid.y <= 0.25);
+        }
+
+        // tighter tolerances produce more segments, straight curves need only one
+        let mut path = Path::new();
+        path.move_to(0.0, 0.0).cubic_to(0.0, 80.0, 100.0, 80.0, 100.0, 0.0);
+        assert!(path.flatten(0.05)[0].vertices.len() > path.flatten(1.0)[0].vertices.len());
+        let mut straight = Path::new();
+        straight.move_to(0.0, 0.0).cubic_to(10.0, 10.0, 20.0, 20.0, 30.0, 30.0);
+        assert_eq!(straight.flatten(0.1)[0].vertices.len(), 2);
+    }
+
+    #[test]
+    fn test_arc_to() {
+        // half circles of radius 10 from (0, 0) to (20, 0), on either side
+        for (sweep, side) in [(true, -1.0), (false, 1.0)] {
+            let mut path = Path::new();
+            path.move_to(0.0, 0.0).arc_to(10.0, 10.0, 0.0, false, sweep, 20.0, 0.0);
+            let vertices = &path.flatten(0.01)[0].vertices;
+            assert_near(vertices[vertices.len() - 1], Vec2::new(20.0, 0.0));
+            for v in vertices {
+                assert!(((v.xy() - Vec2::new(10.0, 0.0)).norm() - 10.0).abs() < 0.05);
+                assert!(v.y * side >= -1e-3, "{:?} is on the wrong side", v);
+            }
+        }
+
+        // the large arc of a circle of radius 10 through two points 10 apart
+        let mut path = Path::new();
+        path.move_to(0.0, 0.0).arc_to(10.0, 10.0, 0.0, true, true, 10.0, 0.0);
+        let vertices = &path.flatten(0.01)[0].vertices;
+        let lowest = vertices.iter().map(|v| v.y).fold(f32::MAX, f32::min);
+        assert!((lowest - (-8.66 - 10.0)).abs() < 0.05);
+
+        // radii too small are scaled up, zero radii draw a line
+        let mut path = Path::new();
+        path.move_to(0.0, 0.0).arc_to(1.0, 1.0, 0.0, false, true, 20.0, 0.0);
+        let vertices = &path.flatten(0.01)[0].vertices;
+        assert!(vertices.iter().all(|v| ((v.xy() - Vec2::new(10.0, 0.0)).norm() - 10.0).abs() < 0.05));
+        let mut path = Path::new();
+        path.move_to(0.0, 0.0).arc_to(0.0, 5.0, 0.0, false, true, 20.0, 0.0);
+        assert_eq!(path.segments()[1], PathSegment::LineTo(Vec2::new(20.0, 0.0)));
+    }
+
+    #[test]
+    fn test_shape_builders() {
+        let mut path = Path::new();
+        path.rounded_rect(0.0, 0.0, 10.0, 6.0, 0.0, 0.0);
+        let subpaths = path.flatten(0.1);
+        assert!(subpaths[0].closed);
+        let corners: Vec<Vec2> = subpaths[0].vertices.iter().map(|v| v.xy()).collect();
+        for corner in [Vec2::new(10.0, 0.0), Vec2::new(10.0, 6.0), Vec2::new(0.0, 6.0), Vec2::new(0.0, 0.0)] {
+            assert!(corners.contains(&corner));
+        }
+
+        let mut path = Path::new();
+        path.ellipse(5.0, 5.0, 4.0, 2.0);
+        for v in &path.flatten(0.01)[0].vertices {
+            let (dx, dy) = ((v.x - 5.0) / 4.0, (v.y - 5.0) / 2.0);
+            assert!((dx * dx + dy * dy - 1.0).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_transformed() {
+        let mut path = Path::new();
+        path.move_to(1.0, 0.0).quad_to(2.0, 2.0, 3.0, 0.0).line_to(3.0, 1.0);
+        let moved = path.transformed(&(translation(10.0, 0.0) * scaling(2.0, 1.0)));
+        assert_eq!(
+            moved.segments(),
+            &[
+                PathSegment::MoveTo(Vec2::new(12.0, 0.0)),
+                PathSegment::QuadTo(Vec2::new(14.0, 2.0), Vec2::new(16.0, 0.0)),
+                PathSegment::LineTo(Vec2::new(16.0, 1.0)),
+            ]
+        );
+        assert_eq!(moved.current_point(), Some(Vec2::new(16.0, 1.0)));
+
+        // a transformed circle is the transformed ellipse
+        let mut circle = Path::new();
+        circle.ellipse(0.0, 0.0, 1.0, 1.0);
+        for v in &circle.transformed(&scaling(4.0, 2.0)).flatten(0.01)[0].vertices {
+            assert!(((v.x / 4.0).powi(2) + (v.y / 2.0).powi(2) - 1.0).abs() < 0.01);
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! The vertex pipeline from object space to framebuffer pixels.
+//!
+//! Model, view and projection matrices take points to clip space, where
+//! geometry is clipped against the view volume. The perspective divide then
+//! gives normalized device coordinates, and the viewport maps those onto pixels.
+
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::error::Result;
+use crate::framebuffer::Framebuffer;
+use crate::line_impl::Line;
+use crate::triangle::fill_triangle;
+
+/// A rectangle of pixels that normalized device coordinates are mapped onto.
+///
+/// NDC x and y run from -1 to 1 between the outer edges of the rectangle, with y
+/// pointing up. Depth goes from [-1, 1] to [0, 1], so nearer stays smaller, as the
+/// framebuffer's default depth test expects.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    /// A viewport covering a whole framebuffer of the given size.
+    pub fn new(width: usize, height: usize) -> Self {
+        Viewport { x: 0.0, y: 0.0, width: width as f32, height: height as f32 }
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.width / self.height
+    }
+
+    /// The screen position of a point in normalized device coordinates: pixels in
+    /// x and y (centers at integers), depth in z.
+    pub fn map(&self, ndc: Vec3) -> Vec3 {
+        Vec3::new(
+            self.x + (ndc.x + 1.0) * self.width / 2.0 - 0.5,
+            self.y + (1.0 - ndc.y) * self.height / 2.0 - 0.5,
+            (ndc.z + 1.0) / 2.0,
+        )
+    }
+}
+
+/// A vertex of a clipped triangle, with its barycentric weights relative to the
+/// original triangle so vertex attributes can be interpolated to it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ClippedVertex {
+    pub position: Vec4,
+    pub weights: Vec3,
+}
+
+/// Clips a clip-space triangle against the view volume (-w <= x, y, z <= w) and
+/// returns the visible part as a convex polygon, empty when nothing is visible.
+pub fn clip_triangle(positions: &[Vec4; 3]) -> Vec<ClippedVertex> {
+    let mut polygon: Vec<ClippedVertex> = positions
+        .iter()
+        .zip([Vec3::x(), Vec3::y(), Vec3::z()])
+        .map(|(&position, weights)| ClippedVertex { position, weights })
+        .collect();
+
+    // Sutherland-Hodgman, one plane at a time
+    for plane in 0..PLANES.len() {
+        if polygon.iter().all(|vertex| distance(vertex.position, plane) >= 0.0) {
+            continue;
+        }
+        let mut clipped = Vec::with_capacity(polygon.len() + 1);
+        for (i, &current) in polygon.iter().enumerate() {
+            let next = polygon[(i + 1) % polygon.len()];
+            let (d_current, d_next) = (distance(current.position, plane), distance(next.position, plane));
+            if d_current >= 0.0 {
+                clipped.push(current);
+            }
+            if (d_current >= 0.0) != (d_next >= 0.0) {
+                let t = d_current / (d_current - d_next);
+                clipped.push(ClippedVertex {
+                    position: current.position + (next.position - current.position) * t,
+                    weights: current.weights + (next.weights - current.weights) * t,
+                });
+            }
+        }
+        if clipped.len() < 3 {
+            return Vec::new();
+        }
+        polygon = clipped;
+    }
+    polygon
+}
+
+/// Clips a clip-space segment against the view volume, returning the visible part.
+pub fn clip_line(from: Vec4, to: Vec4) -> Option<(Vec4, Vec4)> {
+    // Liang-Barsky: narrow the visible range of the parameter plane by plane
+    let (mut enter, mut exit) = (0.0f32, 1.0f32);
+    for plane in 0..PLANES.len() {
+        let (d_from, d_to) = (distance(from, plane), distance(to, plane));
+        if d_from < 0.0 && d_to < 0.0 {
+            return None;
+        }
+        let t = d_from / (d_from - d_to);
+        if d_from < 0.0 {
+            enter = enter.max(t);
+        } else if d_to < 0.0 {
+            exit = exit.min(t);
+        }
+    }
+    if enter > exit {
+        return None;
+    }
+    Some((from + (to - from) * enter, from + (to - from) * exit))
+}
+
+// The six planes of the view volume, as the axis they bound and its sign:
+// w + x >= 0, w - x >= 0 and so on
+const PLANES: [(usize, f32); 6] = [(0, 1.0), (0, -1.0), (1, 1.0), (1, -1.0), (2, 1.0), (2, -1.0)];
+
+// Signed distance to one of the planes of the view volume, positive inside
+fn distance(position: Vec4, plane: usize) -> f32 {
+    let (axis, sign) = PLANES[plane];
+    position.w + sign * position[axis]
+}
+
+/// The matrices taking object space to the pixels of a viewport.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Pipeline {
+    pub model: Mat4,
+    pub view: Mat4,
+    pub projection: Mat4,
+    pub viewport: Viewport,
+}
+
+impl Pipeline {
+    /// A pipeline seeing through the camera onto the viewport, with an identity
+    /// model matrix.
+    pub fn new(camera: &Camera, viewport: Viewport) -> Result<Self> {
+        Ok(Pipeline {
+            model: Mat4::identity(),
+            view: camera.view_matrix()?,
+            projection: camera.projection_matrix(viewport.aspect())?,
+            viewport,
+        })
+    }
+
+    /// The combined model-view-projection matrix.
+    pub fn matrix(&self) -> Mat4 {
+        self.projection * self.view * self.model
+    }
+
+    pub fn to_clip(&self, point: Vec3) -> Vec4 {
+        self.matrix() * Vec4::new(point.x, point.y, point.z, 1.0)
+    }
+
+    /// Divides a clip-space position by w and maps it onto the viewport.
+    pub fn to_screen(&self, position: Vec4) -> Vec3 {
+        self.viewport.map(position.xyz() / position.w)
+    }
+
+    /// The screen position of a point, or None when it is outside the view volume.
+    pub fn project(&self, point: Vec3) -> Option<Vec3> {
+        let position = self.to_clip(point);
+        (0..PLANES.len()).all(|plane| distance(position, plane) >= 0.0).then(|| self.to_screen(position))
+    }
+
+    /// Fills the visible part of a triangle with depth testing.
+    pub fn fill_triangle(&self, framebuffer: &mut Framebuffer, vertices: &[Vec3; 3], color: Color) {
+        let polygon = clip_triangle(&vertices.map(|vertex| self.to_clip(vertex)));
+        let screen: Vec<Vec3> = polygon.iter().map(|vertex| self.to_screen(vertex.position)).collect();
+        for i in 1..screen.len().saturating_sub(1) {
+            fill_triangle(framebuffer, &[screen[0], screen[i], screen[i + 1]], color);
+        }
+    }
+
+    /// Draws the visible part of a segment with the framebuffer's line mode and
+    /// stroke style. Lines are not depth tested.
+    pub fn draw_line(&self, framebuffer: &mut Framebuffer, from: Vec3, to: Vec3, color: Color) {
+        if let Some((from, to)) = clip_line(self.to_clip(from), self.to_clip(to)) {
+            let (from, to) = (self.to_screen(from), self.to_screen(to));
+            framebuffer.set_current_color(color);
+            framebuffer.draw_line(from.x, from.y, to.x, to.y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Projection;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn camera(projection: Projection) -> Camera {
+        Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::zeros(), Vec3::y(), projection)
+    }
+
+    fn perspective() -> Pipeline {
+        let projection = Projection::Perspective { fov_y: FRAC_PI_2, near: 1.0, far: 100.0 };
+        Pipeline::new(&camera(projection), Viewport::new(40, 40)).unwrap()
+    }
+
+    fn lit(fb: &Framebuffer) -> usize {
+        (0..fb.height as isize)
+            .flat_map(|y| (0..fb.width as isize).map(move |x| (x, y)))
+            .filter(|&(x, y)| fb.get_point(x, y) != Some(Color::from_hex(0x000000)))
+            .count()
+    }
+
+    #[test]
+    fn test_viewport() {
+        let viewport = Viewport::new(40, 20);
+        assert_eq!(viewport.map(Vec3::new(-1.0, 1.0, -1.0)), Vec3::new(-0.5, -0.5, 0.0));
+        assert_eq!(viewport.map(Vec3::new(1.0, -1.0, 1.0)), Vec3::new(39.5, 19.5, 1.0));
+        assert_eq!(viewport.map(Vec3::zeros()), Vec3::new(19.5, 9.5, 0.5));
+        assert_eq!(viewport.aspect(), 2.0);
+    }
+
+    #[test]
+    fn test_project() {
+        let pipeline = perspective();
+        let center = pipeline.project(Vec3::zeros()).unwrap();
+        assert!((center.xy() - nalgebra_glm::Vec2::new(19.5, 19.5)).norm() < 1e-4);
+        // up in the world is up on screen, right is right
+        let corner = pipeline.project(Vec3::new(2.5, 2.5, 0.0)).unwrap();
+        assert!((corner.xy() - nalgebra_glm::Vec2::new(29.5, 9.5)).norm() < 1e-4);
+        // behind the camera and beyond the far plane are not visible
+        assert_eq!(pipeline.project(Vec3::new(0.0, 0.0, 10.0)), None);
+        assert_eq!(pipeline.project(Vec3::new(0.0, 0.0, -200.0)), None);
+    }
+
+    #[test]
+    fn test_clip_triangle() {
+        let inside = [Vec4::new(0.0, 0.0, 0.0, 1.0), Vec4::new(0.5, 0.0, 0.0, 1.0), Vec4::new(0.0, 0.5, 0.0, 1.0)];
+        assert_eq!(clip_triangle(&inside).len(), 3);
+        let outside = inside.map(|v| v + Vec4::new(3.0, 0.0, 0.0, 0.0));
+        assert!(clip_triangle(&outside).is_empty());
+
+        // one corner past the right plane becomes a quad whose weights rebuild the positions
+        let crossing = [Vec4::new(0.0, 0.0, 0.0, 1.0), Vec4::new(2.0, 0.0, 0.0, 1.0), Vec4::new(0.0, 0.5, 0.0, 1.0)];
+        let polygon = clip_triangle(&crossing);
+        assert_eq!(polygon.len(), 4);
+        for vertex in &polygon {
+            assert!(vertex.position.x <= 1.0 + 1e-6);
+            let rebuilt = crossing[0] * vertex.weights.x + crossing[1] * vertex.weights.y + crossing[2] * vertex.weights.z;
+            assert!((rebuilt - vertex.position).norm() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_clip_line() {
+        let (from, to) = clip_line(Vec4::new(-3.0, 0.0, 0.0, 1.0), Vec4::new(0.0, 0.0, 0.0, 1.0)).unwrap();
+        assert_eq!((from.x, to.x), (-1.0, 0.0));
+        assert_eq!(clip_line(Vec4::new(-3.0, 0.0, 0.0, 1.0), Vec4::new(-2.0, 0.0, 0.0, 1.0)), None);
+        // crossing the corner region without entering the volume
+        assert_eq!(clip_line(Vec4::new(-3.0, 0.0, 0.0, 1.0), Vec4::new(0.0, 3.0, 0.0, 1.0)), None);
+    }
+
+    #[test]
+    fn test_perspective_and_orthographic_sizes() {
+        let square = |z: f32| {
+            [
+                [Vec3::new(-1.0, -1.0, z), Vec3::new(1.0, -1.0, z), Vec3::new(1.0, 1.0, z)],
+                [Vec3::new(-1.0, -1.0, z), Vec3::new(1.0, 1.0, z), Vec3::new(-1.0, 1.0, z)],
+            ]
+        };
+        let area = |pipeline: &Pipeline, z: f32| {
+            let mut fb = Framebuffer::new(40, 40);
+            for triangle in square(z) {
+                pipeline.fill_triangle(&mut fb, &triangle, Color::from_hex(0xFFFFFF));
+            }
+            lit(&fb)
+        };
+
+        // twice as far looks half as wide: a quarter of the pixels
+        let pipeline = perspective();
+        assert_eq!(area(&pipeline, 0.0), 64);
+        assert_eq!(area(&pipeline, -5.0), 16);
+
+        let orthographic = Projection::Orthographic { height: 10.0, near: 1.0, far: 100.0 };
+        let pipeline = Pipeline::new(&camera(orthographic), Viewport::new(40, 40)).unwrap();
+        assert_eq!(area(&pipeline, 0.0), area(&pipeline, -5.0));
+    }
+
+    #[test]
+    fn test_near_plane_clipping() {
+        // a floor running from behind the camera into the distance
+        let pipeline = perspective();
+        let floor = [Vec3::new(-50.0, -1.0, 20.0), Vec3::new(50.0, -1.0, 20.0), Vec3::new(0.0, -1.0, -90.0)];
+        let mut fb = Framebuffer::new(40, 40);
+        pipeline.fill_triangle(&mut fb, &floor, Color::from_hex(0xFFFFFF));
+        // visible below the horizon only
+        assert_eq!(fb.get_point(20, 39), Some(Color::from_hex(0xFFFFFF)));
+        assert_eq!(fb.get_point(20, 10), Some(Color::from_hex(0x000000)));
+        assert!((0..40).all(|x| fb.get_depth(x, 39).unwrap() >= 0.0));
+
+        let mut fb = Framebuffer::new(40, 40);
+        pipeline.draw_line(&mut fb, Vec3::new(0.0, -1.0, 20.0), Vec3::new(0.0, -1.0, -90.0), Color::from_hex(0xFFFFFF));
+        assert_eq!(fb.get_point(20, 39), Some(Color::from_hex(0xFFFFFF)));
+        assert_eq!(fb.get_point(20, 10), Some(Color::from_hex(0x000000)));
+    }
+}
@@ -0,0 +1,336 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::error::{Error, Result};
+use crate::zlib;
+
+pub use crate::zlib::Compression;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+const PNG_BIT_DEPTH: u8 = 8;
+const PNG_COLOR_TYPE_RGB: u8 = 2;
+const PNG_BYTES_PER_PIXEL: usize = 3;
+// IDAT payloads are split so no single chunk grows unbounded
+const PNG_MAX_IDAT_SIZE: usize = 1 << 20;
+
+/// Row filter applied before compression (PNG specification, section 9).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum PngFilter {
+    None,
+    Sub,
+    Up,
+    Average,
+    Paeth,
+    /// Picks the filter with the smallest sum of absolute differences for every row.
+    #[default]
+    Adaptive,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct PngOptions {
+    pub compression: Compression,
+    pub filter: PngFilter,
+}
+
+pub fn write_png_file(
+    file_path: &str,
+    buffer: &[u32],
+    width: usize,
+    height: usize,
+    options: PngOptions,
+) -> Result<()> {
+    check_dimensions(buffer, width, height)?;
+
+    let file = File::create(file_path)?;
+    let mut writer = BufWriter::new(file);
+
+    write_png(&mut writer, buffer, width, height, options)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_png<W: Write>(
+    writer: &mut W,
+    buffer: &[u32],
+    width: usize,
+    height: usize,
+    options: PngOptions,
+) -> Result<()> {
+    check_dimensions(buffer, width, height)?;
+
+    writer.write_all(&PNG_SIGNATURE)?;
+
+    // IHDR: dimensions, 8-bit RGB, deflate, adaptive filtering, no interlace
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[PNG_BIT_DEPTH, PNG_COLOR_TYPE_RGB, 0, 0, 0]);
+    write_chunk(writer, b"IHDR", &header)?;
+
+    let filtered = filter_scanlines(buffer, width, height, options);
+    let compressed = zlib::compress(&filtered, options.compression);
+    for data in compressed.chunks(PNG_MAX_IDAT_SIZE) {
+        write_chunk(writer, b"IDAT", data)?;
+    }
+
+    write_chunk(writer, b"IEND", &[])
+}
+
+// PNG dimensions must be non-zero and fit in 31 bits
+fn check_dimensions(buffer: &[u32], width: usize, height: usize) -> Result<()> {
+    let limit = i32::MAX as usize;
+    if width == 0 || height == 0 || width > limit || height > limit || buffer.len() != width * height {
+        return Err(Error::InvalidDimensions { width, height });
+    }
+    Ok(())
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+
+    // the CRC covers the chunk type and data, not the length
+    let crc = !crc32_update(crc32_update(0xFFFF_FFFF, kind), data);
+    writer.write_all(&crc.to_be_bytes())?;
+    Ok(())
+}
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+}
+
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc = CRC_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
+/// CRC-32 as used by PNG chunks.
+pub fn crc32(data: &[u8]) -> u32 {
+    !crc32_update(0xFFFF_FFFF, data)
+}
+
+// Converts the buffer to RGB scanlines, each prefixed with its filter type byte
+fn filter_scanlines(buffer: &[u32], width: usize, height: usize, options: PngOptions) -> Vec<u8> {
+    let stride = width * PNG_BYTES_PER_PIXEL;
+    let mut out = Vec::with_capacity((stride + 1) * height);
+    let mut previous = vec![0u8; stride];
+    let mut current = vec![0u8; stride];
+    let mut candidate = vec![0u8; stride];
+    let mut best = vec![0u8; stride];
+
+    // filtering cannot help stored data, so skip the work
+    let filter = if options.compression == Compression::Stored { PngFilter::None } else { options.filter };
+
+    for y in 0..height {
+        for x in 0..width {
+            let color = buffer[y * width + x];
+            current[x * 3] = (color >> 16) as u8;
+            current[x * 3 + 1] = (color >> 8) as u8;
+            current[x * 3 + 2] = color as u8;
+        }
+
+        let filter_type = match filter {
+            PngFilter::Adaptive => {
+                let mut best_type = 0;
+                let mut best_score = u64::MAX;
+                for filter_type in 0..5 {
+                    apply_filter(filter_type, &current, &previous, &mut candidate);
+                    // treat bytes as signed so small negative deltas score low
+                    let score: u64 = candidate.iter().map(|&b| (b as i8).unsigned_abs() as u64).sum();
+                    if score < best_score {
+                        best_score = score;
+                        best_type = filter_type;
+                        best.copy_from_slice(&candidate);
+                    }
+                }
+                best_type
+            }
+            fixed => {
+                let filter_type = fixed as u8;
+                apply_filter(filter_type, &current, &previous, &mut best);
+                filter_type
+            }
+        };
+
+        out.push(filter_type);
+        out.extend_from_slice(&best);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    out
+}
+
+fn apply_filter(filter_type: u8, row: &[u8], previous: &[u8], out: &mut [u8]) {
+    for i in 0..row.len() {
+        let a = if i >= PNG_BYTES_PER_PIXEL { row[i - PNG_BYTES_PER_PIXEL] } else { 0 };
+        let b = previous[i];
+        let c = if i >= PNG_BYTES_PER_PIXEL { previous[i - PNG_BYTES_PER_PIXEL] } else { 0 };
+        let predictor = match filter_type {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((a as u16 + b as u16) / 2) as u8,
+            _ => paeth(a, b, c),
+        };
+        out[i] = row[i].wrapping_sub(predictor);
+    }
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Chunk {
+        kind: [u8; 4],
+        data: Vec<u8>,
+    }
+
+    // Splits an encoded PNG into chunks, checking every CRC on the way
+    fn parse_chunks(png: &[u8]) -> Vec<Chunk> {
+        assert_eq!(&png[..8], &PNG_SIGNATURE);
+        let mut chunks = Vec::new();
+        let mut pos = 8;
+        while pos < png.len() {
+            let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let kind: [u8; 4] = png[pos + 4..pos + 8].try_into().unwrap();
+            let data = png[pos + 8..pos + 8 + len].to_vec();
+            let crc = u32::from_be_bytes(png[pos + 8 + len..pos + 12 + len].try_into().unwrap());
+            assert_eq!(crc, crc32(&png[pos + 4..pos + 8 + len]), "bad CRC for {:?}", kind);
+            chunks.push(Chunk { kind, data });
+            pos += 12 + len;
+        }
+        chunks
+    }
+
+    // Reverses the filters to recover 0xRRGGBB pixels
+    fn decode_pixels(png: &[u8]) -> (usize, usize, Vec<u32>) {
+        let chunks = parse_chunks(png);
+        let header = &chunks[0];
+        assert_eq!(&header.kind, b"IHDR");
+        let width = u32::from_be_bytes(header.data[0..4].try_into().unwrap()) as usize;
+        let height = u32::from_be_bytes(header.data[4..8].try_into().unwrap()) as usize;
+
+        let idat: Vec<u8> = chunks.iter().filter(|c| &c.kind == b"IDAT").flat_map(|c| c.data.clone()).collect();
+        let raw = zlib::decompress(&idat);
+        let stride = width * 3;
+        assert_eq!(raw.len(), (stride + 1) * height);
+
+        let mut previous = vec![0u8; stride];
+        let mut pixels = Vec::new();
+        for row in raw.chunks(stride + 1) {
+            let mut current = row[1..].to_vec();
+            for i in 0..stride {
+                let a = if i >= 3 { current[i - 3] } else { 0 };
+                let b = previous[i];
+                let c = if i >= 3 { previous[i - 3] } else { 0 };
+                let predictor = match row[0] {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((a as u16 + b as u16) / 2) as u8,
+                    4 => paeth(a, b, c),
+                    other => panic!("invalid filter type {}", other),
+                };
+                current[i] = current[i].wrapping_add(predictor);
+            }
+            for x in 0..width {
+                pixels.push((current[x * 3] as u32) << 16 | (current[x * 3 + 1] as u32) << 8 | current[x * 3 + 2] as u32);
+            }
+            previous = current;
+        }
+        (width, height, pixels)
+    }
+
+    fn gradient(width: usize, height: usize) -> Vec<u32> {
+        (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as u32, (i / width) as u32);
+                ((x * 7) & 0xFF) << 16 | ((y * 3) & 0xFF) << 8 | ((x ^ y) & 0xFF)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_header_and_chunks() {
+        let mut png = Vec::new();
+        write_png(&mut png, &gradient(5, 3), 5, 3, PngOptions::default()).unwrap();
+        let chunks = parse_chunks(&png);
+
+        assert_eq!(&chunks[0].kind, b"IHDR");
+        assert_eq!(chunks[0].data, vec![0, 0, 0, 5, 0, 0, 0, 3, 8, 2, 0, 0, 0]);
+        assert_eq!(&chunks.last().unwrap().kind, b"IEND");
+        assert!(chunks.last().unwrap().data.is_empty());
+    }
+
+    #[test]
+    fn test_round_trip_every_option() {
+        let filters = [
+            PngFilter::None,
+            PngFilter::Sub,
+            PngFilter::Up,
+            PngFilter::Average,
+            PngFilter::Paeth,
+            PngFilter::Adaptive,
+        ];
+        for &(width, height) in &[(1, 1), (3, 2), (17, 9), (64, 40)] {
+            let buffer = gradient(width, height);
+            for compression in [Compression::Stored, Compression::Fast, Compression::Best] {
+                for filter in filters {
+                    let mut png = Vec::new();
+                    write_png(&mut png, &buffer, width, height, PngOptions { compression, filter }).unwrap();
+                    assert_eq!(decode_pixels(&png), (width, height, buffer.clone()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_dimensions() {
+        let mut png = Vec::new();
+        assert!(matches!(
+            write_png(&mut png, &[], 0, 10, PngOptions::default()),
+            Err(Error::InvalidDimensions { width: 0, height: 10 })
+        ));
+        assert!(matches!(
+            write_png(&mut png, &[0; 3], 2, 2, PngOptions::default()),
+            Err(Error::InvalidDimensions { .. })
+        ));
+        assert!(png.is_empty());
+    }
+}
@@ -0,0 +1,299 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use crate::error::{Error, Result};
+use crate::framebuffer::Framebuffer;
+
+const PNM_MAX_VALUE: u32 = 255;
+// Netpbm recommends that plain format lines stay under 70 characters
+const PNM_MAX_LINE_LENGTH: usize = 70;
+
+/// Netpbm variants: plain (ASCII) or raw (binary), grayscale or RGB.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PnmFormat {
+    /// P2, plain PGM
+    PlainGray,
+    /// P3, plain PPM
+    PlainRgb,
+    /// P5, raw PGM
+    RawGray,
+    /// P6, raw PPM
+    RawRgb,
+}
+
+impl PnmFormat {
+    fn magic(self) -> &'static [u8; 2] {
+        match self {
+            PnmFormat::PlainGray => b"P2",
+            PnmFormat::PlainRgb => b"P3",
+            PnmFormat::RawGray => b"P5",
+            PnmFormat::RawRgb => b"P6",
+        }
+    }
+
+    fn from_magic(magic: &[u8]) -> Option<Self> {
+        match magic {
+            b"P2" => Some(PnmFormat::PlainGray),
+            b"P3" => Some(PnmFormat::PlainRgb),
+            b"P5" => Some(PnmFormat::RawGray),
+            b"P6" => Some(PnmFormat::RawRgb),
+            _ => None,
+        }
+    }
+
+    fn is_gray(self) -> bool {
+        matches!(self, PnmFormat::PlainGray | PnmFormat::RawGray)
+    }
+
+    fn is_plain(self) -> bool {
+        matches!(self, PnmFormat::PlainGray | PnmFormat::PlainRgb)
+    }
+}
+
+pub fn write_pnm_file(
+    file_path: &str,
+    buffer: &[u32],
+    width: usize,
+    height: usize,
+    format: PnmFormat,
+) -> Result<()> {
+    check_dimensions(buffer, width, height)?;
+
+    let file = File::create(file_path)?;
+    let mut writer = BufWriter::new(file);
+
+    write_pnm(&mut writer, buffer, width, height, format)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_pnm<W: Write>(
+    writer: &mut W,
+    buffer: &[u32],
+    width: usize,
+    height: usize,
+    format: PnmFormat,
+) -> Result<()> {
+    check_dimensions(buffer, width, height)?;
+
+    // header: magic number, dimensions and maximum sample value
+    writer.write_all(format.magic())?;
+    write!(writer, "\n{} {}\n{}\n", width, height, PNM_MAX_VALUE)?;
+
+    let samples: Vec<u8> = if format.is_gray() {
+        buffer.iter().map(|&color| luma(color)).collect()
+    } else {
+        buffer.iter().flat_map(|&color| [(color >> 16) as u8, (color >> 8) as u8, color as u8]).collect()
+    };
+
+    if !format.is_plain() {
+        writer.write_all(&samples)?;
+        return Ok(());
+    }
+
+    let mut line = String::new();
+    for sample in samples {
+        let text = sample.to_string();
+        if !line.is_empty() && line.len() + 1 + text.len() > PNM_MAX_LINE_LENGTH {
+            writeln!(writer, "{}", line)?;
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&text);
+    }
+    if !line.is_empty() {
+        writeln!(writer, "{}", line)?;
+    }
+    Ok(())
+}
+
+fn check_dimensions(buffer: &[u32], width: usize, height: usize) -> Result<()> {
+    if width == 0 || height == 0 || buffer.len() != width * height {
+        return Err(Error::InvalidDimensions { width, height });
+    }
+    Ok(())
+}
+
+// Rec. 601 luma, rounded to the nearest integer
+fn luma(color: u32) -> u8 {
+    let r = (color >> 16) & 0xFF;
+    let g = (color >> 8) & 0xFF;
+    let b = color & 0xFF;
+    ((r * 299 + g * 587 + b * 114 + 500) / 1000) as u8
+}
+
+pub fn read_pnm_file(file_path: &str) -> Result<Framebuffer> {
+    let data = fs::read(file_path)?;
+    decode_pnm(&data)
+}
+
+pub fn decode_pnm(data: &[u8]) -> Result<Framebuffer> {
+    let format = data
+        .get(0..2)
+        .and_then(PnmFormat::from_magic)
+        .ok_or_else(|| Error::UnsupportedFormat("not a PGM or PPM file".to_string()))?;
+
+    let mut reader = HeaderReader { data, pos: 2 };
+    let width = reader.number()? as usize;
+    let height = reader.number()? as usize;
+    let max_value = reader.number()?;
+
+    if width == 0 || height == 0 {
+        return Err(Error::InvalidDimensions { width, height });
+    }
+    if max_value == 0 || max_value > 65535 {
+        return Err(Error::InvalidData(format!("invalid maximum value {}", max_value)));
+    }
+
+    let channels = if format.is_gray() { 1 } else { 3 };
+    let count = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .ok_or(Error::InvalidDimensions { width, height })?;
+
+    let samples: Vec<u32> = if format.is_plain() {
+        (0..count).map(|_| reader.number()).collect::<Result<_>>()?
+    } else {
+        // exactly one whitespace byte separates the header from the raster
+        let start = reader.pos + 1;
+        let sample_size = if max_value < 256 { 1 } else { 2 };
+        let raster = data
+            .get(start..)
+            .filter(|raster| raster.len() / sample_size >= count)
+            .ok_or_else(|| Error::InvalidData("PNM raster is truncated".to_string()))?;
+        if sample_size == 1 {
+            raster[..count].iter().map(|&b| b as u32).collect()
+        } else {
+            raster.chunks(2).take(count).map(|pair| (pair[0] as u32) << 8 | pair[1] as u32).collect()
+        }
+    };
+
+    // rescale every sample to 0..=255, rejecting values above the declared maximum
+    let mut scaled = Vec::with_capacity(count);
+    for sample in samples {
+        if sample > max_value {
+            return Err(Error::InvalidData(format!("sample {} exceeds maximum value {}", sample, max_value)));
+        }
+        scaled.push((sample * 255 + max_value / 2) / max_value);
+    }
+
+    // Netpbm has no alpha, so every pixel is opaque
+    let buffer = if format.is_gray() {
+        scaled.iter().map(|&v| 0xFF00_0000 | (v << 16) | (v << 8) | v).collect()
+    } else {
+        scaled.chunks(3).map(|rgb| 0xFF00_0000 | (rgb[0] << 16) | (rgb[1] << 8) | rgb[2]).collect()
+    };
+
+    Ok(Framebuffer::from_buffer(width, height, buffer))
+}
+
+// Reads whitespace-separated decimal numbers, skipping `#` comments
+struct HeaderReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl HeaderReader<'_> {
+    fn number(&mut self) -> Result<u32> {
+        loop {
+            match self.data.get(self.pos) {
+                Some(b'#') => {
+                    while self.data.get(self.pos).is_some_and(|&b| b != b'\n' && b != b'\r') {
+                        self.pos += 1;
+                    }
+                }
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                _ => break,
+            }
+        }
+
+        let start = self.pos;
+        while self.data.get(self.pos).is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(Error::InvalidData("expected a number in PNM data".to_string()));
+        }
+        std::str::from_utf8(&self.data[start..self.pos])
+            .unwrap()
+            .parse()
+            .map_err(|_| Error::InvalidData("number out of range in PNM data".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIXELS: [u32; 6] = [0xFF0000, 0x00FF00, 0x0000FF, 0x123456, 0xFFFFFF, 0x000000];
+
+    fn encode(format: PnmFormat) -> Vec<u8> {
+        let mut data = Vec::new();
+        write_pnm(&mut data, &PIXELS, 3, 2, format).unwrap();
+        data
+    }
+
+    fn pixels(fb: &Framebuffer) -> Vec<u32> {
+        (0..fb.height as isize)
+            .flat_map(|y| (0..fb.width as isize).map(move |x| (x, y)))
+            .map(|(x, y)| fb.get_point(x, y).unwrap().to_hex())
+            .collect()
+    }
+
+    #[test]
+    fn test_write_plain_rgb() {
+        let text = String::from_utf8(encode(PnmFormat::PlainRgb)).unwrap();
+        assert_eq!(text, "P3\n3 2\n255\n255 0 0 0 255 0 0 0 255 18 52 86 255 255 255 0 0 0\n");
+    }
+
+    #[test]
+    fn test_write_raw_gray() {
+        let data = encode(PnmFormat::RawGray);
+        assert_eq!(&data[..11], b"P5\n3 2\n255\n");
+        assert_eq!(&data[11..], &[76, 150, 29, 46, 255, 0]);
+    }
+
+    #[test]
+    fn test_plain_lines_are_short() {
+        let buffer = vec![0xFFFFFF; 100];
+        let mut data = Vec::new();
+        write_pnm(&mut data, &buffer, 10, 10, PnmFormat::PlainRgb).unwrap();
+        let text = String::from_utf8(data).unwrap();
+        assert!(text.lines().all(|line| line.len() <= PNM_MAX_LINE_LENGTH));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for format in [PnmFormat::PlainRgb, PnmFormat::RawRgb] {
+            let fb = decode_pnm(&encode(format)).unwrap();
+            assert_eq!((fb.width, fb.height), (3, 2));
+            assert_eq!(pixels(&fb), PIXELS);
+        }
+        for format in [PnmFormat::PlainGray, PnmFormat::RawGray] {
+            let fb = decode_pnm(&encode(format)).unwrap();
+            let expected: Vec<u32> = PIXELS.iter().map(|&c| luma(c) as u32 * 0x010101).collect();
+            assert_eq!(pixels(&fb), expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_comments_and_max_value() {
+        let fb = decode_pnm(b"P2 # gray\n# size next\n2 1\n# max\n15\n0 15\n").unwrap();
+        assert_eq!(pixels(&fb), vec![0x000000, 0xFFFFFF]);
+
+        let mut wide = b"P6\n1 1\n65535\n".to_vec();
+        wide.extend_from_slice(&[0xFF, 0xFF, 0x80, 0x00, 0x00, 0x00]);
+        assert_eq!(pixels(&decode_pnm(&wide).unwrap()), vec![0xFF8000]);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert!(matches!(decode_pnm(b"P4\n1 1\n"), Err(Error::UnsupportedFormat(_))));
+        assert!(matches!(decode_pnm(b"P3\n0 1\n255\n"), Err(Error::InvalidDimensions { .. })));
+        assert!(matches!(decode_pnm(b"P3\n1 1\n255\n1 2\n"), Err(Error::InvalidData(_))));
+        assert!(matches!(decode_pnm(b"P2\n1 1\n10\n11\n"), Err(Error::InvalidData(_))));
+        assert!(matches!(decode_pnm(b"P6\n2 1\n255\n\x01\x02\x03"), Err(Error::InvalidData(_))));
+    }
+}
@@ -0,0 +1,415 @@
+//! Declarative scenes read from TOML files.
+//!
+//! ```toml
+//! [canvas]
+//! width = 800              # at most 16384 pixels on a side
+//! height = 600
+//! background = "#FFFFFF"   # optional, black by default
+//! antialias = true         # optional, smooths strokes and fills
+//!
+//! [[shapes]]
+//! type = "polygon"
+//! points = [[10, 10], [90, 10], [50, 80]]
+//! fill = "#FFFF00"
+//! stroke = "navy"
+//! stroke_width = 2
+//! ```
+//!
+//! Shape types and their geometry:
+//! - `polygon`: `points`, or `contours` (a list of point lists) for shapes with holes
+//! - `polyline`: `points`
+//! - `line`: `x1`, `y1`, `x2`, `y2`
+//! - `rect`: `x`, `y`, `width`, `height` and an optional corner `radius`
+//! - `circle`: `cx`, `cy`, `r`
+//! - `ellipse`: `cx`, `cy`, `rx`, `ry`
+//! - `path`: `d`, in SVG path syntax
+//!
+//! Every shape takes `fill` and `stroke` colors (as in SVG, including `"none"`),
+//! `fill_rule` (`"nonzero"` or `"evenodd"`), `stroke_width`, `line_cap`,
+//! `line_join`, `miter_limit` and a `dash` array. Shapes are neither filled nor
+//! stroked unless asked to. A `transform` in SVG syntax, such as
+//! `"translate(100 50) rotate(30)"`, places the shape's geometry.
+
+use std::fs;
+
+use crate::color::Color;
+use crate::error::{Error, Result};
+use crate::fill::{FillMode, FillRule};
+use crate::framebuffer::{Framebuffer, MAX_CANVAS_SIDE};
+use crate::line_impl::LineMode;
+use crate::path::Path;
+use crate::shapes::{draw_path, fill_path};
+use crate::stroke::{LineCap, LineJoin, StrokePattern, StrokeStyle};
+use crate::svg::{paint, parse_path_data, parse_transform};
+use crate::toml::{self, Table, Value};
+
+/// A shape of a scene with its paint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneShape {
+    pub path: Path,
+    pub fill: Option<Color>,
+    pub fill_rule: FillRule,
+    pub stroke: Option<Color>,
+    pub stroke_style: StrokeStyle,
+    pub stroke_pattern: StrokePattern,
+}
+
+/// A canvas and the shapes painted on it, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scene {
+    pub width: usize,
+    pub height: usize,
+    pub background: Color,
+    pub anti_aliased: bool,
+    pub shapes: Vec<SceneShape>,
+}
+
+impl Scene {
+    /// Paints the scene onto a new framebuffer, each shape's fill before its stroke.
+    pub fn render(&self) -> Result<Framebuffer> {
+        let mut framebuffer = Framebuffer::new(self.width, self.height);
+        framebuffer.set_background_color(self.background);
+        framebuffer.clear();
+        if self.anti_aliased {
+            framebuffer.set_line_mode(LineMode::AntiAliased);
+            framebuffer.set_fill_mode(FillMode::AntiAliased);
+        }
+
+        for shape in &self.shapes {
+            if let Some(fill) = shape.fill {
+                fill_path(&mut framebuffer, &shape.path, shape.fill_rule, fill)?;
+            }
+            if let Some(stroke) = shape.stroke {
+                framebuffer.set_stroke_style(shape.stroke_style);
+                framebuffer.set_stroke_pattern(shape.stroke_pattern.clone());
+                draw_path(&mut framebuffer, &shape.path, stroke)?;
+            }
+        }
+        Ok(framebuffer)
+    }
+}
+
+pub fn read_scene_file(file_path: &str) -> Result<Scene> {
+    let text = fs::read_to_string(file_path)?;
+    parse_scene(&text)
+}
+
+pub fn parse_scene(text: &str) -> Result<Scene> {
+    let document = toml::parse(text)?;
+
+    let canvas = Fields::new(&document, "scene").table("canvas")?;
+    let width = canvas.integer("width")?;
+    let height = canvas.integer("height")?;
+    let valid = |side: i64| side > 0 && side <= MAX_CANVAS_SIDE as i64;
+    if !valid(width) || !valid(height) {
+        return Err(Error::InvalidDimensions { width: width.max(0) as usize, height: height.max(0) as usize });
+    }
+    let background = canvas.optional_color("background")?.unwrap_or(Color::from_hex(0x000000));
+    let anti_aliased = match canvas.get("antialias") {
+        Some(value) => value.as_bool().ok_or_else(|| canvas.error("\"antialias\" must be true or false"))?,
+        None => false,
+    };
+
+    let mut shapes = Vec::new();
+    if let Some(entries) = toml::get(&document, "shapes") {
+        let entries = entries.as_array().ok_or_else(|| Error::InvalidData("scene: \"shapes\" must be an array of tables".to_string()))?;
+        for (index, entry) in entries.iter().enumerate() {
+            let table = entry
+                .as_table()
+                .ok_or_else(|| Error::InvalidData(format!("scene shape {}: expected a table", index + 1)))?;
+            shapes.push(parse_shape(&Fields::new(table, &format!("scene shape {}", index + 1)))?);
+        }
+    }
+
+    Ok(Scene { width: width as usize, height: height as usize, background, anti_aliased, shapes })
+}
+
+fn parse_shape(fields: &Fields) -> Result<SceneShape> {
+    let mut path = Path::new();
+    let kind = fields.string("type")?;
+    match kind {
+        "polygon" | "polyline" => {
+            let contours = match (fields.get("points"), fields.get("contours")) {
+                (Some(points), None) => vec![fields.points("points", points)?],
+                (None, Some(contours)) if kind == "polygon" => contours
+                    .as_array()
+                    .ok_or_else(|| fields.error("\"contours\" must be a list of point lists"))?
+                    .iter()
+                    .map(|contour| fields.points("contours", contour))
+                    .collect::<Result<Vec<_>>>()?,
+                _ => return Err(fields.error(&format!("a {} needs \"points\"", kind))),
+            };
+            for contour in contours {
+                for (i, &(x, y)) in contour.iter().enumerate() {
+                    if i == 0 {
+                        path.move_to(x, y);
+                    } else {
+                        path.line_to(x, y);
+                    }
+                }
+                if kind == "polygon" {
+                    path.close();
+                }
+            }
+        }
+        "line" => {
+            path.move_to(fields.number("x1")?, fields.number("y1")?).line_to(fields.number("x2")?, fields.number("y2")?);
+        }
+        "rect" => {
+            let (width, height) = (fields.number("width")?, fields.number("height")?);
+            if width < 0.0 || height < 0.0 {
+                return Err(fields.error(&format!("negative rect size {}x{}", width, height)));
+            }
+            let radius = fields.optional_number("radius")?.unwrap_or(0.0);
+            path.rounded_rect(fields.number("x")?, fields.number("y")?, width, height, radius, radius);
+        }
+        "circle" | "ellipse" => {
+            let (rx, ry) = if kind == "circle" {
+                let r = fields.number("r")?;
+                (r, r)
+            } else {
+                (fields.number("rx")?, fields.number("ry")?)
+            };
+            if rx < 0.0 || ry < 0.0 {
+                return Err(fields.error(&format!("negative {} radius", kind)));
+            }
+            path.ellipse(fields.number("cx")?, fields.number("cy")?, rx, ry);
+        }
+        "path" => path = parse_path_data(fields.string("d")?)?,
+        _ => return Err(fields.error(&format!("unknown shape type \"{}\"", kind))),
+    }
+    if fields.get("transform").is_some() {
+        path = path.transformed(&parse_transform(fields.string("transform")?)?);
+    }
+
+    let fill_rule = match fields.get("fill_rule").map(|_| fields.string("fill_rule")).transpose()? {
+        None | Some("nonzero") => FillRule::NonZero,
+        Some("evenodd") => FillRule::EvenOdd,
+        Some(other) => return Err(fields.error(&format!("unknown fill rule \"{}\"", other))),
+    };
+
+    let mut stroke_style = StrokeStyle::new(fields.optional_number("stroke_width")?.unwrap_or(1.0).max(0.0));
+    if fields.get("line_cap").is_some() {
+        stroke_style = stroke_style.with_cap(match fields.string("line_cap")? {
+            "butt" => LineCap::Butt,
+            "round" => LineCap::Round,
+            "square" => LineCap::Square,
+            other => return Err(fields.error(&format!("unknown line cap \"{}\"", other))),
+        });
+    }
+    if fields.get("line_join").is_some() {
+        stroke_style = stroke_style.with_join(match fields.string("line_join")? {
+            "miter" => LineJoin::Miter,
+            "round" => LineJoin::Round,
+            "bevel" => LineJoin::Bevel,
+            other => return Err(fields.error(&format!("unknown line join \"{}\"", other))),
+        });
+    }
+    if let Some(limit) = fields.optional_number("miter_limit")? {
+        stroke_style = stroke_style.with_miter_limit(limit);
+    }
+    let stroke_pattern = match fields.get("dash") {
+        Some(dash) => {
+            let lengths = dash
+                .as_array()
+                .ok_or_else(|| fields.error("\"dash\" must be a list of lengths"))?
+                .iter()
+                .map(|length| length.as_float().map(|length| length as f32).ok_or_else(|| fields.error("\"dash\" must be a list of lengths")))
+                .collect::<Result<Vec<_>>>()?;
+            StrokePattern::dashed(&lengths)
+        }
+        None => StrokePattern::Solid,
+    };
+
+    Ok(SceneShape {
+        path,
+        fill: fields.optional_color("fill")?,
+        fill_rule,
+        stroke: fields.optional_color("stroke")?,
+        stroke_style,
+        stroke_pattern,
+    })
+}
+
+// Typed access to a table's keys, with errors naming where the table came from
+struct Fields<'a> {
+    table: &'a Table,
+    context: String,
+}
+
+impl<'a> Fields<'a> {
+    fn new(table: &'a Table, context: &str) -> Self {
+        Fields { table, context: context.to_string() }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::InvalidData(format!("{}: {}", self.context, message))
+    }
+
+    fn get(&self, key: &str) -> Option<&'a Value> {
+        toml::get(self.table, key)
+    }
+
+    fn table(&self, key: &str) -> Result<Fields<'a>> {
+        let table = self
+            .get(key)
+            .and_then(Value::as_table)
+            .ok_or_else(|| self.error(&format!("missing table [{}]", key)))?;
+        Ok(Fields::new(table, key))
+    }
+
+    fn string(&self, key: &str) -> Result<&'a str> {
+        self.get(key).and_then(Value::as_str).ok_or_else(|| self.error(&format!("missing string \"{}\"", key)))
+    }
+
+    fn integer(&self, key: &str) -> Result<i64> {
+        self.get(key).and_then(Value::as_integer).ok_or_else(|| self.error(&format!("missing integer \"{}\"", key)))
+    }
+
+    fn number(&self, key: &str) -> Result<f32> {
+        self.optional_number(key)?.ok_or_else(|| self.error(&format!("missing number \"{}\"", key)))
+    }
+
+    fn optional_number(&self, key: &str) -> Result<Option<f32>> {
+        match self.get(key) {
+            Some(value) => {
+                let number = value.as_float().ok_or_else(|| self.error(&format!("\"{}\" must be a number", key)))?;
+                Ok(Some(number as f32))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn optional_color(&self, key: &str) -> Result<Option<Color>> {
+        match self.get(key) {
+            Some(value) => {
+                let text = value.as_str().ok_or_else(|| self.error(&format!("\"{}\" must be a color string", key)))?;
+                paint(text)
+            }
+            None => Ok(None),
+        }
+    }
+
+    // A list of [x, y] pairs
+    fn points(&self, key: &str, value: &Value) -> Result<Vec<(f32, f32)>> {
+        let invalid = || self.error(&format!("\"{}\" must be a list of [x, y] points", key));
+        value
+            .as_array()
+            .ok_or_else(invalid)?
+            .iter()
+            .map(|point| match point.as_array() {
+                Some([x, y]) => Ok((x.as_float().ok_or_else(invalid)? as f32, y.as_float().ok_or_else(invalid)? as f32)),
+                _ => Err(invalid()),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scene() {
+        let scene = parse_scene(
+            r##"
+            [canvas]
+            width = 40
+            height = 30
+            background = "#FFFFFF"
+
+            [[shapes]]
+            type = "polygon"
+            contours = [
+                [[2, 2], [20, 2], [20, 20], [2, 20]],
+                [[8, 8], [14, 8], [14, 14], [8, 14]],
+            ]
+            fill = "#00FF00"
+            fill_rule = "evenodd"
+
+            [[shapes]]
+            type = "circle"
+            cx = 30
+            cy = 15
+            r = 5.5
+            stroke = "red"
+            stroke_width = 3
+            line_cap = "round"
+            dash = [4, 2]
+
+            [[shapes]]
+            type = "path"
+            d = "M0 29 H39"
+            stroke = "blue"
+
+            [[shapes]]
+            type = "path"
+            d = "M0 0 H39"
+            stroke = "#FF00FF"
+            transform = "translate(0 25)"
+            "##,
+        )
+        .unwrap();
+        assert_eq!((scene.width, scene.height), (40, 30));
+        assert_eq!(scene.background, Color::from_hex(0xFFFFFF));
+        assert!(!scene.anti_aliased);
+        assert_eq!(scene.shapes.len(), 4);
+        assert_eq!(scene.shapes[0].fill_rule, FillRule::EvenOdd);
+        assert_eq!(scene.shapes[0].stroke, None);
+        assert_eq!(scene.shapes[1].fill, None);
+        assert_eq!(scene.shapes[1].stroke_style, StrokeStyle::new(3.0).with_cap(LineCap::Round));
+        assert_eq!(scene.shapes[1].stroke_pattern, StrokePattern::dashed(&[4.0, 2.0]));
+
+        let fb = scene.render().unwrap();
+        assert_eq!(fb.get_point(4, 4), Some(Color::from_hex(0x00FF00)));
+        assert_eq!(fb.get_point(11, 11), Some(Color::from_hex(0xFFFFFF)));
+        assert_eq!(fb.get_point(20, 29), Some(Color::from_hex(0x0000FF)));
+        assert_eq!(fb.get_point(30, 15), Some(Color::from_hex(0xFFFFFF)));
+        // the transformed path is moved down from the top row
+        assert_eq!(fb.get_point(20, 25), Some(Color::from_hex(0xFF00FF)));
+        assert_eq!(fb.get_point(20, 0), Some(Color::from_hex(0xFFFFFF)));
+    }
+
+    #[test]
+    fn test_huge_coordinates() {
+        // strokes far larger than the canvas only draw the part on it
+        let scene = parse_scene(
+            r##"
+            [canvas]
+            width = 20
+            height = 20
+
+            [[shapes]]
+            type = "rect"
+            x = 2
+            y = 3
+            width = 1e12
+            height = 1e12
+            stroke = "blue"
+            "##,
+        )
+        .unwrap();
+        let fb = scene.render().unwrap();
+        assert_eq!(fb.get_point(10, 3), Some(Color::from_hex(0x0000FF)));
+        assert_eq!(fb.get_point(2, 10), Some(Color::from_hex(0x0000FF)));
+        assert_eq!(fb.get_point(10, 10), Some(Color::from_hex(0x000000)));
+    }
+
+    #[test]
+    fn test_scene_errors() {
+        let canvas = "[canvas]\nwidth = 10\nheight = 10\n";
+        let with_shape = |shape: &str| parse_scene(&format!("{}[[shapes]]\n{}", canvas, shape));
+        assert!(matches!(parse_scene("[[shapes]]\ntype = \"rect\""), Err(Error::InvalidData(_))));
+        assert!(matches!(parse_scene("[canvas]\nwidth = 0\nheight = 5"), Err(Error::InvalidDimensions { .. })));
+        assert!(matches!(
+            parse_scene("[canvas]\nwidth = 1000000\nheight = 1000000"),
+            Err(Error::InvalidDimensions { width: 1000000, height: 1000000 })
+        ));
+        assert!(matches!(with_shape("type = \"star\""), Err(Error::InvalidData(_))));
+        assert!(matches!(with_shape("type = \"circle\"\ncx = 1\ncy = 1"), Err(Error::InvalidData(_))));
+        assert!(matches!(with_shape("type = \"polygon\"\npoints = [[1, 2, 3]]"), Err(Error::InvalidData(_))));
+        assert!(matches!(with_shape("type = \"line\"\nx1 = 0\ny1 = 0\nx2 = 5\ny2 = \"5\""), Err(Error::InvalidData(_))));
+        assert!(matches!(with_shape("type = \"path\"\nd = \"M0 0 L5 5\"\nfill = \"chartreuse\""), Err(Error::UnsupportedFormat(_))));
+        assert!(with_shape("type = \"rect\"\nx = 1\ny = 1\nwidth = 4\nheight = 4\nfill = \"red\"").is_ok());
+    }
+}
@@ -0,0 +1,283 @@
+//! Programmable shading: vertex and fragment shaders with interpolated varyings.
+//!
+//! A [`VertexShader`] turns each input vertex into a clip-space position and a
+//! set of varyings. Triangles are clipped, divided by w and mapped onto the
+//! viewport; across every covered pixel the varyings are interpolated with
+//! perspective correction, and the [`FragmentShader`] decides the pixel's color.
+//! Fragments are depth tested before shading, and write their depth only when
+//! the shader does not discard them.
+
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::pipeline::{clip_triangle, Viewport};
+use crate::triangle::rasterize_triangle;
+
+/// A value that can be interpolated across a triangle: a weighted sum of the
+/// values at its three corners, with weights summing to 1.
+pub trait Varying: Copy {
+    fn interpolate(values: [Self; 3], weights: Vec3) -> Self;
+}
+
+impl Varying for () {
+    fn interpolate(_: [Self; 3], _: Vec3) -> Self {}
+}
+
+impl Varying for f32 {
+    fn interpolate(values: [Self; 3], weights: Vec3) -> Self {
+        values[0] * weights.x + values[1] * weights.y + values[2] * weights.z
+    }
+}
+
+// Vectors interpolate component by component
+macro_rules! impl_varying_for_vectors {
+    ($($vector:ty),*) => {
+        $(impl Varying for $vector {
+            fn interpolate(values: [Self; 3], weights: Vec3) -> Self {
+                values[0] * weights.x + values[1] * weights.y + values[2] * weights.z
+            }
+        })*
+    };
+}
+
+impl_varying_for_vectors!(Vec2, Vec3, Vec4);
+
+impl Varying for Color {
+    fn interpolate(values: [Self; 3], weights: Vec3) -> Self {
+        let channel = |value: fn(&Color) -> u8| f32::interpolate(values.map(|color| value(&color) as f32), weights).round() as i32;
+        Color::new_rgba(channel(|c| c.r), channel(|c| c.g), channel(|c| c.b), channel(|c| c.a))
+    }
+}
+
+// Tuples of varyings interpolate element by element
+macro_rules! impl_varying_for_tuples {
+    ($(($($name:ident $index:tt),*)),*) => {
+        $(impl<$($name: Varying),*> Varying for ($($name,)*) {
+            fn interpolate(values: [Self; 3], weights: Vec3) -> Self {
+                ($($name::interpolate(values.map(|value| value.$index), weights),)*)
+            }
+        })*
+    };
+}
+
+impl_varying_for_tuples!((A 0, B 1), (A 0, B 1, C 2), (A 0, B 1, C 2, D 3));
+
+/// Runs once per vertex, like a GPU vertex shader.
+pub trait VertexShader {
+    /// What a vertex is made of: a position, and whatever else the shader reads.
+    type Vertex;
+    type Varyings: Varying;
+
+    /// The vertex's clip-space position and its varyings.
+    fn vertex(&self, vertex: &Self::Vertex) -> (Vec4, Self::Varyings);
+}
+
+/// Where a fragment is and what it covers.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Fragment {
+    pub x: isize,
+    pub y: isize,
+    /// The depth the fragment is tested and stored with.
+    pub depth: f32,
+    /// Whether the triangle is counterclockwise in normalized device coordinates.
+    pub front_facing: bool,
+}
+
+/// Runs once per covered pixel that passes the depth test, with the varyings
+/// interpolated to its center.
+pub trait FragmentShader<V> {
+    /// The fragment's color, or None to discard it, leaving the pixel and its
+    /// depth untouched.
+    fn fragment(&self, fragment: &Fragment, varyings: &V) -> Option<Color>;
+}
+
+/// Draws indexed triangles through the shaders onto the viewport.
+pub fn draw_triangles<VS, FS>(
+    framebuffer: &mut Framebuffer,
+    viewport: &Viewport,
+    vertex_shader: &VS,
+    fragment_shader: &FS,
+    vertices: &[VS::Vertex],
+    triangles: &[[usize; 3]],
+) where
+    VS: VertexShader,
+    FS: FragmentShader<VS::Varyings>,
+{
+    let shaded: Vec<(Vec4, VS::Varyings)> = vertices.iter().map(|vertex| vertex_shader.vertex(vertex)).collect();
+    for triangle in triangles {
+        let corners = triangle.map(|index| shaded[index]);
+        let varyings = corners.map(|(_, varyings)| varyings);
+
+        // varyings are linear in clip space, so the clipper's weights carry them over
+        let polygon = clip_triangle(&corners.map(|(position, _)| position));
+        let clipped: Vec<(Vec4, Vec3, VS::Varyings)> = polygon
+            .iter()
+            .map(|vertex| {
+                let position = vertex.position;
+                let screen = viewport.map(position.xyz() / position.w);
+                (position, screen, VS::Varyings::interpolate(varyings, vertex.weights))
+            })
+            .collect();
+        for i in 1..clipped.len().saturating_sub(1) {
+            draw_clipped(framebuffer, fragment_shader, [clipped[0], clipped[i], clipped[i + 1]]);
+        }
+    }
+}
+
+// Rasterizes one triangle of a clipped polygon
+fn draw_clipped<V: Varying, FS: FragmentShader<V>>(framebuffer: &mut Framebuffer, fragment_shader: &FS, corners: [(Vec4, Vec3, V); 3]) {
+    let screen = corners.map(|(_, screen, _)| screen);
+    let varyings = corners.map(|(_, _, varyings)| varyings);
+    let inverse_w = Vec3::new(1.0 / corners[0].0.w, 1.0 / corners[1].0.w, 1.0 / corners[2].0.w);
+    let depths = Vec3::new(screen[0].z, screen[1].z, screen[2].z);
+    // counterclockwise in NDC turns clockwise on screen, where y points down
+    let (ab, ac) = (screen[1] - screen[0], screen[2] - screen[0]);
+    let front_facing = ab.x * ac.y - ab.y * ac.x < 0.0;
+
+    rasterize_triangle(framebuffer.width, framebuffer.height, &screen, |x, y, weights| {
+        // depth is affine on screen; the varyings are affine only after dividing by w
+        let depth = weights.dot(&depths);
+        if !framebuffer.passes_depth_test(x, y, depth) {
+            return;
+        }
+        let perspective = weights.component_mul(&inverse_w);
+        let perspective = perspective / perspective.sum();
+        let fragment = Fragment { x, y, depth, front_facing };
+        if let Some(color) = fragment_shader.fragment(&fragment, &V::interpolate(varyings, perspective)) {
+            framebuffer.depth_point(x, y, depth, color);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{Camera, Projection};
+    use crate::pipeline::Pipeline;
+    use std::cell::Cell;
+
+    // Passes the world position through as a varying
+    struct WorldPosition<'a>(&'a Pipeline);
+
+    impl VertexShader for WorldPosition<'_> {
+        type Vertex = Vec3;
+        type Varyings = Vec3;
+
+        fn vertex(&self, vertex: &Vec3) -> (Vec4, Vec3) {
+            (self.0.to_clip(*vertex), *vertex)
+        }
+    }
+
+    // Checks that every fragment's interpolated world position projects back onto it
+    struct ProjectsBack<'a> {
+        pipeline: &'a Pipeline,
+        fragments: Cell<usize>,
+    }
+
+    impl FragmentShader<Vec3> for ProjectsBack<'_> {
+        fn fragment(&self, fragment: &Fragment, world: &Vec3) -> Option<Color> {
+            let screen = self.pipeline.to_screen(self.pipeline.to_clip(*world));
+            assert!((screen.x - fragment.x as f32).abs() < 0.01 && (screen.y - fragment.y as f32).abs() < 0.01);
+            assert!((screen.z - fragment.depth).abs() < 1e-4);
+            self.fragments.set(self.fragments.get() + 1);
+            Some(Color::from_hex(0xFFFFFF))
+        }
+    }
+
+    struct VertexColors<'a>(&'a Pipeline);
+
+    impl VertexShader for VertexColors<'_> {
+        type Vertex = (Vec3, Color);
+        type Varyings = (Color, f32);
+
+        fn vertex(&self, (position, color): &(Vec3, Color)) -> (Vec4, (Color, f32)) {
+            (self.0.to_clip(*position), (*color, position.x))
+        }
+    }
+
+    // Gouraud shading on the left half, discarding the right half
+    struct LeftHalf;
+
+    impl FragmentShader<(Color, f32)> for LeftHalf {
+        fn fragment(&self, _: &Fragment, &(color, x): &(Color, f32)) -> Option<Color> {
+            (x < 0.0).then_some(color)
+        }
+    }
+
+    fn pipeline() -> Pipeline {
+        let projection = Projection::Perspective { fov_y: 1.2, near: 0.5, far: 50.0 };
+        let camera = Camera::new(Vec3::new(0.0, 1.0, 3.0), Vec3::new(0.0, 0.0, -2.0), Vec3::y(), projection);
+        Pipeline::new(&camera, Viewport::new(60, 40)).unwrap()
+    }
+
+    #[test]
+    fn test_varyings() {
+        let weights = Vec3::new(0.5, 0.25, 0.25);
+        assert_eq!(f32::interpolate([4.0, 8.0, 0.0], weights), 4.0);
+        assert_eq!(Vec2::interpolate([Vec2::x(), Vec2::y(), Vec2::zeros()], weights), Vec2::new(0.5, 0.25));
+        let colors = [Color::from_hex(0xFF0000), Color::from_hex(0x00FF00), Color::new_rgba(0, 0, 255, 0)];
+        assert_eq!(Color::interpolate(colors, weights), Color::new_rgba(128, 64, 64, 191));
+        assert_eq!(<(f32, ())>::interpolate([(1.0, ()), (2.0, ()), (3.0, ())], weights), (1.75, ()));
+    }
+
+    #[test]
+    fn test_perspective_correct_interpolation() {
+        // a floor receding into the distance and crossing the near plane
+        let pipeline = pipeline();
+        let floor = [Vec3::new(-20.0, 0.0, 5.0), Vec3::new(20.0, 0.0, 5.0), Vec3::new(20.0, 0.0, -40.0), Vec3::new(-20.0, 0.0, -40.0)];
+        let shader = ProjectsBack { pipeline: &pipeline, fragments: Cell::new(0) };
+        let mut fb = Framebuffer::new(60, 40);
+        draw_triangles(&mut fb, &pipeline.viewport, &WorldPosition(&pipeline), &shader, &floor, &[[0, 1, 2], [0, 2, 3]]);
+        assert!(shader.fragments.get() > 500);
+        assert_eq!(fb.get_point(30, 39), Some(Color::from_hex(0xFFFFFF)));
+    }
+
+    #[test]
+    fn test_discard_and_gouraud() {
+        let pipeline = pipeline();
+        let (red, blue) = (Color::from_hex(0xFF0000), Color::from_hex(0x0000FF));
+        let quad = [
+            (Vec3::new(-2.0, -1.0, -2.0), red),
+            (Vec3::new(2.0, -1.0, -2.0), blue),
+            (Vec3::new(2.0, 2.0, -2.0), blue),
+            (Vec3::new(-2.0, 2.0, -2.0), red),
+        ];
+        let mut fb = Framebuffer::new(60, 40);
+        draw_triangles(&mut fb, &pipeline.viewport, &VertexColors(&pipeline), &LeftHalf, &quad, &[[0, 1, 2], [0, 2, 3]]);
+
+        // the left half blends from red towards purple, the discarded right half keeps no depth
+        let left = fb.get_point(20, 20).unwrap();
+        assert!(left.r > left.b && left.b > 0, "{}", left);
+        assert_eq!(fb.get_point(40, 20), Some(Color::from_hex(0x000000)));
+        assert!(fb.get_depth(20, 20).unwrap() < 1.0);
+        assert_eq!(fb.get_depth(40, 20), Some(f32::INFINITY));
+    }
+
+    #[test]
+    fn test_front_facing() {
+        struct Facing;
+        impl FragmentShader<()> for Facing {
+            fn fragment(&self, fragment: &Fragment, _: &()) -> Option<Color> {
+                Some(if fragment.front_facing { Color::from_hex(0x00FF00) } else { Color::from_hex(0xFF0000) })
+            }
+        }
+        struct Positions<'a>(&'a Pipeline);
+        impl VertexShader for Positions<'_> {
+            type Vertex = Vec3;
+            type Varyings = ();
+            fn vertex(&self, vertex: &Vec3) -> (Vec4, ()) {
+                (self.0.to_clip(*vertex), ())
+            }
+        }
+
+        // counterclockwise seen from the camera is the front
+        let pipeline = pipeline();
+        let triangle = [Vec3::new(-1.0, 0.0, -2.0), Vec3::new(1.0, 0.0, -2.0), Vec3::new(0.0, 1.5, -2.0)];
+        for (order, expected) in [([0, 1, 2], 0x00FF00), ([0, 2, 1], 0xFF0000)] {
+            let mut fb = Framebuffer::new(60, 40);
+            draw_triangles(&mut fb, &pipeline.viewport, &Positions(&pipeline), &Facing, &triangle, &[order]);
+            assert_eq!(fb.get_point(30, 18), Some(Color::from_hex(expected)));
+        }
+    }
+}
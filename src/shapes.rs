@@ -0,0 +1,930 @@
+//! Polygons, paths, ellipses, arcs and rectangles drawn on a [`Framebuffer`].
+//!
+//! Every shape is placed through the framebuffer's transform. The pixel-exact
+//! primitives (circles, ellipses, arcs and rectangles) keep their midpoint
+//! rasterization under whole-pixel translations; any other transform draws them
+//! as paths, stroked with the framebuffer's stroke style. Stroke widths are in
+//! pixels and are not scaled by the transform.
+
+use std::f32::consts::{PI, TAU};
+
+use nalgebra_glm::Vec3;
+
+use crate::color::Color;
+use crate::error::{Error, Result};
+use crate::fill::fill_contours;
+use crate::framebuffer::Framebuffer;
+use crate::line_impl::Line;
+use crate::path::Path;
+use crate::transform::{pixel_translation, transform_point};
+
+pub use crate::fill::{FillMode, FillRule};
+
+/// The largest radius, in pixels, the pixel-exact circles, ellipses, arcs and
+/// rounded rectangles accept.
+pub const MAX_RADIUS: isize = 1 << 20;
+
+/// Draws a polygon by connecting the given vertices with lines.
+/// The vertices must be provided in the order they are to be connected.
+pub fn draw_polygon(framebuffer: &mut Framebuffer, vertices: &[Vec3], line_color: Color) -> Result<()> {
+    if vertices.len() < 3 {
+        return Err(Error::InvalidGeometry(format!("a polygon needs at least 3 vertices, got {}", vertices.len())));
+    }
+
+    framebuffer.set_current_color(line_color);
+    let vertices = transformed(framebuffer, vertices);
+
+    // Stroke the closed outline so the stroke style's joins connect consecutive edges
+    framebuffer.stroke_polyline(&vertices, true);
+    Ok(())
+}
+
+/// Fills a polygon made of one or more contours (an outline plus any holes)
+/// with the given fill rule and the framebuffer's fill mode.
+///
+/// In the aliased mode a pixel is filled when its center is inside the polygon;
+/// centers exactly on an edge follow the top-left rule, so polygons sharing an
+/// edge never leave gaps or draw a pixel twice.
+pub fn fill_polygon<C: AsRef<[Vec3]>>(framebuffer: &mut Framebuffer, contours: &[C], fill_rule: FillRule, fill_color: Color) -> Result<()> {
+    if contours.is_empty() {
+        return Err(Error::InvalidGeometry("a polygon needs at least one contour".to_string()));
+    }
+    if let Some(contour) = contours.iter().find(|contour| contour.as_ref().len() < 3) {
+        return Err(Error::InvalidGeometry(format!(
+            "every contour needs at least 3 vertices, got {}",
+            contour.as_ref().len()
+        )));
+    }
+
+    let contours: Vec<Vec<Vec3>> = contours.iter().map(|contour| transformed(framebuffer, contour.as_ref())).collect();
+    framebuffer.set_current_color(fill_color);
+    fill_contours(framebuffer, &contours, fill_rule);
+    Ok(())
+}
+
+fn transformed(framebuffer: &Framebuffer, vertices: &[Vec3]) -> Vec<Vec3> {
+    let matrix = framebuffer.transform();
+    vertices.iter().map(|&vertex| transform_point(&matrix, vertex)).collect()
+}
+
+// Flattening tolerance for paths, in pixels
+const PATH_TOLERANCE: f32 = 0.1;
+
+/// Strokes every subpath of the path with the framebuffer's stroke style, line
+/// mode and pattern.
+pub fn draw_path(framebuffer: &mut Framebuffer, path: &Path, line_color: Color) -> Result<()> {
+    framebuffer.set_current_color(line_color);
+    // flatten after transforming, so the tolerance holds on screen
+    for subpath in path.transformed(&framebuffer.transform()).flatten(PATH_TOLERANCE) {
+        framebuffer.stroke_polyline(&subpath.vertices, subpath.closed);
+    }
+    Ok(())
+}
+
+/// Fills the path with the given fill rule, closing every subpath implicitly.
+/// Subpaths are contours of one shape, so they can cut holes in each other.
+pub fn fill_path(framebuffer: &mut Framebuffer, path: &Path, fill_rule: FillRule, fill_color: Color) -> Result<()> {
+    let contours: Vec<Vec<Vec3>> = path
+        .transformed(&framebuffer.transform())
+        .flatten(PATH_TOLERANCE)
+        .into_iter().map(|subpath| subpath.vertices).filter(|vertices| vertices.len() > 2).collect();
+    framebuffer.set_current_color(fill_color);
+    fill_contours(framebuffer, &contours, fill_rule);
+    Ok(())
+}
+
+/// Draws the outline of a circle with the midpoint algorithm.
+/// The center and radius are in whole pixels.
+pub fn draw_circle(framebuffer: &mut Framebuffer, center: (isize, isize), radius: isize, color: Color) -> Result<()> {
+    draw_ellipse(framebuffer, center, radius, radius, color)
+}
+
+/// Fills a circle, outline included.
+pub fn fill_circle(framebuffer: &mut Framebuffer, center: (isize, isize), radius: isize, color: Color) -> Result<()> {
+    fill_ellipse(framebuffer, center, radius, radius, color)
+}
+
+/// Draws the outline of an axis-aligned ellipse with the midpoint algorithm.
+pub fn draw_ellipse(framebuffer: &mut Framebuffer, center: (isize, isize), rx: isize, ry: isize, color: Color) -> Result<()> {
+    check_radii(rx, ry)?;
+    let rounded = RoundedBox::new(center.0, center.1, center.0, center.1, rx, ry);
+    draw_rounded_box(framebuffer, &rounded, color)
+}
+
+/// Fills an axis-aligned ellipse, outline included.
+pub fn fill_ellipse(framebuffer: &mut Framebuffer, center: (isize, isize), rx: isize, ry: isize, color: Color) -> Result<()> {
+    check_radii(rx, ry)?;
+    let rounded = RoundedBox::new(center.0, center.1, center.0, center.1, rx, ry);
+    fill_rounded_box(framebuffer, &rounded, color)
+}
+
+/// Draws the part of an ellipse's outline between two angles.
+///
+/// Angles are in radians, measured at the center from the positive x axis towards
+/// positive y (clockwise on screen). The arc runs from `start_angle` towards
+/// increasing angles until `end_angle`; a sweep of a full turn or more draws the
+/// whole ellipse.
+pub fn draw_arc(
+    framebuffer: &mut Framebuffer,
+    center: (isize, isize),
+    rx: isize,
+    ry: isize,
+    start_angle: f32,
+    end_angle: f32,
+    color: Color,
+) -> Result<()> {
+    check_radii(rx, ry)?;
+    let sweep = Sweep::new(start_angle, end_angle)?;
+    let Some(center) = place(framebuffer, center) else {
+        return draw_path(framebuffer, &sweep.path(center, rx, ry, false, 0.0), color);
+    };
+    let rounded = RoundedBox::new(center.0, center.1, center.0, center.1, rx, ry);
+    let pixels = rounded.outline((0, 0), canvas(framebuffer)).into_iter().filter(|&(x, y)| sweep.contains(x - center.0, y - center.1)).collect();
+    plot(framebuffer, pixels, color);
+    Ok(())
+}
+
+/// Draws the outline of a pie slice: an arc plus the two radii closing it.
+/// Angles follow [`draw_arc`].
+pub fn draw_pie(
+    framebuffer: &mut Framebuffer,
+    center: (isize, isize),
+    rx: isize,
+    ry: isize,
+    start_angle: f32,
+    end_angle: f32,
+    color: Color,
+) -> Result<()> {
+    check_radii(rx, ry)?;
+    let sweep = Sweep::new(start_angle, end_angle)?;
+    let Some(center) = place(framebuffer, center) else {
+        return draw_path(framebuffer, &sweep.path(center, rx, ry, true, 0.0), color);
+    };
+    let rounded = RoundedBox::new(center.0, center.1, center.0, center.1, rx, ry);
+    let mut pixels: Vec<(isize, isize)> =
+        rounded.outline((0, 0), canvas(framebuffer)).into_iter().filter(|&(x, y)| sweep.contains(x - center.0, y - center.1)).collect();
+    if !sweep.full {
+        for angle in [start_angle, end_angle] {
+            let (dx, dy) = ellipse_point(rx, ry, angle);
+            line_pixels(center, (center.0 + dx, center.1 + dy), &mut pixels);
+        }
+    }
+    plot(framebuffer, pixels, color);
+    Ok(())
+}
+
+/// Fills a pie slice of an ellipse. Angles follow [`draw_arc`].
+pub fn fill_pie(
+    framebuffer: &mut Framebuffer,
+    center: (isize, isize),
+    rx: isize,
+    ry: isize,
+    start_angle: f32,
+    end_angle: f32,
+    color: Color,
+) -> Result<()> {
+    check_radii(rx, ry)?;
+    let sweep = Sweep::new(start_angle, end_angle)?;
+    let Some(center) = place(framebuffer, center) else {
+        return fill_path(framebuffer, &sweep.path(center, rx, ry, true, 0.5), FillRule::NonZero, color);
+    };
+    let rounded = RoundedBox::new(center.0, center.1, center.0, center.1, rx, ry);
+    framebuffer.set_current_color(color);
+    for (y, left, right) in rounded.spans((0, 0), canvas(framebuffer)) {
+        for x in left..=right {
+            if sweep.contains(x - center.0, y - center.1) {
+                framebuffer.point(x, y);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Draws the outline of an axis-aligned rectangle covering `width` x `height`
+/// pixels from its top-left corner.
+pub fn draw_rect(framebuffer: &mut Framebuffer, x: isize, y: isize, width: isize, height: isize, color: Color) -> Result<()> {
+    draw_rounded_rect(framebuffer, x, y, width, height, 0, color)
+}
+
+/// Fills an axis-aligned rectangle covering `width` x `height` pixels.
+pub fn fill_rect(framebuffer: &mut Framebuffer, x: isize, y: isize, width: isize, height: isize, color: Color) -> Result<()> {
+    fill_rounded_rect(framebuffer, x, y, width, height, 0, color)
+}
+
+/// Draws the outline of a rectangle whose corners are quarter circles.
+/// The radius is clamped to half the shorter side.
+pub fn draw_rounded_rect(
+    framebuffer: &mut Framebuffer,
+    x: isize,
+    y: isize,
+    width: isize,
+    height: isize,
+    radius: isize,
+    color: Color,
+) -> Result<()> {
+    match rounded_rect(x, y, width, height, radius)? {
+        Some(rounded) => draw_rounded_box(framebuffer, &rounded, color),
+        None => Ok(()),
+    }
+}
+
+/// Fills a rectangle whose corners are quarter circles, outline included.
+pub fn fill_rounded_rect(
+    framebuffer: &mut Framebuffer,
+    x: isize,
+    y: isize,
+    width: isize,
+    height: isize,
+    radius: isize,
+    color: Color,
+) -> Result<()> {
+    match rounded_rect(x, y, width, height, radius)? {
+        Some(rounded) => fill_rounded_box(framebuffer, &rounded, color),
+        None => Ok(()),
+    }
+}
+
+fn draw_rounded_box(framebuffer: &mut Framebuffer, rounded: &RoundedBox, color: Color) -> Result<()> {
+    match pixel_translation(&framebuffer.transform()) {
+        Some(offset) => plot(framebuffer, rounded.outline(offset, canvas(framebuffer)), color),
+        None => draw_path(framebuffer, &rounded.path(0.0), color)?,
+    }
+    Ok(())
+}
+
+fn fill_rounded_box(framebuffer: &mut Framebuffer, rounded: &RoundedBox, color: Color) -> Result<()> {
+    let Some(offset) = pixel_translation(&framebuffer.transform()) else {
+        return fill_path(framebuffer, &rounded.path(0.5), FillRule::NonZero, color);
+    };
+    framebuffer.set_current_color(color);
+    for (y, left, right) in rounded.spans(offset, canvas(framebuffer)) {
+        for x in left..=right {
+            framebuffer.point(x, y);
+        }
+    }
+    Ok(())
+}
+
+// Where a pixel-exact primitive lands under the framebuffer's transform, or None
+// when the transform does more than move by whole pixels
+fn place(framebuffer: &Framebuffer, (x, y): (isize, isize)) -> Option<(isize, isize)> {
+    pixel_translation(&framebuffer.transform()).map(|(dx, dy)| (x + dx, y + dy))
+}
+
+// The framebuffer's size, which pixel-exact primitives are clipped to
+fn canvas(framebuffer: &Framebuffer) -> (isize, isize) {
+    (framebuffer.width as isize, framebuffer.height as isize)
+}
+
+fn check_radii(rx: isize, ry: isize) -> Result<()> {
+    if rx < 0 || ry < 0 {
+        return Err(Error::InvalidGeometry(format!("radii must not be negative, got {}x{}", rx, ry)));
+    }
+    if rx > MAX_RADIUS || ry > MAX_RADIUS {
+        return Err(Error::InvalidGeometry(format!("radii must be at most {}, got {}x{}", MAX_RADIUS, rx, ry)));
+    }
+    Ok(())
+}
+
+// Returns None for an empty rectangle
+fn rounded_rect(x: isize, y: isize, width: isize, height: isize, radius: isize) -> Result<Option<RoundedBox>> {
+    if width < 0 || height < 0 || radius < 0 {
+        return Err(Error::InvalidGeometry(format!(
+            "rectangle size and radius must not be negative, got {}x{} with radius {}",
+            width, height, radius
+        )));
+    }
+    if width == 0 || height == 0 {
+        return Ok(None);
+    }
+    let radius = radius.min((width - 1) / 2).min((height - 1) / 2);
+    check_radii(radius, radius)?;
+    let (right, bottom) = (x + width - 1, y + height - 1);
+    Ok(Some(RoundedBox::new(x + radius, y + radius, right - radius, bottom - radius, radius, radius)))
+}
+
+// Sets every pixel once, so translucent outlines do not darken where the
+// symmetric parts of a shape meet
+fn plot(framebuffer: &mut Framebuffer, mut pixels: Vec<(isize, isize)>, color: Color) {
+    pixels.sort_unstable();
+    pixels.dedup();
+    framebuffer.set_current_color(color);
+    for (x, y) in pixels {
+        framebuffer.point(x, y);
+    }
+}
+
+// A rectangle from (left, top) to (right, bottom) grown by elliptical corners of
+// radii rx, ry; an ellipse is the case where the rectangle is a single pixel
+struct RoundedBox {
+    left: isize,
+    top: isize,
+    right: isize,
+    bottom: isize,
+    rx: isize,
+    ry: isize,
+    quadrant: Vec<(isize, isize)>,
+}
+
+impl RoundedBox {
+    fn new(left: isize, top: isize, right: isize, bottom: isize, rx: isize, ry: isize) -> Self {
+        RoundedBox { left, top, right, bottom, rx, ry, quadrant: ellipse_quadrant(rx, ry) }
+    }
+
+    // The outline pixels inside a canvas of the given size, moved by (dx, dy)
+    fn outline(&self, (dx, dy): (isize, isize), (width, height): (isize, isize)) -> Vec<(isize, isize)> {
+        let (left, top, right, bottom) = (self.left + dx, self.top + dy, self.right + dx, self.bottom + dy);
+        let mut pixels = Vec::new();
+        let mut push = |x: isize, y: isize| {
+            if (0..width).contains(&x) && (0..height).contains(&y) {
+                pixels.push((x, y));
+            }
+        };
+        for &(qx, qy) in &self.quadrant {
+            push(right + qx, bottom + qy);
+            push(left - qx, bottom + qy);
+            push(right + qx, top - qy);
+            push(left - qx, top - qy);
+        }
+        for x in left.max(0)..=right.min(width - 1) {
+            push(x, top - self.ry);
+            push(x, bottom + self.ry);
+        }
+        for y in top.max(0)..=bottom.min(height - 1) {
+            push(left - self.rx, y);
+            push(right + self.rx, y);
+        }
+        pixels
+    }
+
+    // The shape as a path through the outline's pixel centers, for transforms the
+    // pixels cannot follow; growing it by half a pixel covers the filled pixels
+    fn path(&self, grow: f32) -> Path {
+        let (rx, ry) = (self.rx as f32 + grow, self.ry as f32 + grow);
+        let (left, top) = (self.left as f32 - rx, self.top as f32 - ry);
+        let (width, height) = ((self.right - self.left) as f32 + 2.0 * rx, (self.bottom - self.top) as f32 + 2.0 * ry);
+        let mut path = Path::new();
+        if self.left == self.right && self.top == self.bottom {
+            path.ellipse(self.left as f32, self.top as f32, rx, ry);
+        } else {
+            path.rounded_rect(left, top, width, height, rx, ry);
+        }
+        path
+    }
+
+    // (y, left, right) for every covered row inside a canvas of the given size,
+    // top to bottom, moved by (dx, dy)
+    fn spans(&self, (dx, dy): (isize, isize), (width, height): (isize, isize)) -> Vec<(isize, isize, isize)> {
+        // widest quadrant offset on every row of a corner
+        let mut half_width = vec![0; self.ry as usize + 1];
+        for &(qx, qy) in &self.quadrant {
+            half_width[qy as usize] = half_width[qy as usize].max(qx);
+        }
+
+        ((self.top - self.ry + dy).max(0)..=(self.bottom + self.ry + dy).min(height - 1))
+            .filter_map(|row| {
+                let y = row - dy;
+                let offset = if y < self.top {
+                    self.top - y
+                } else if y > self.bottom {
+                    y - self.bottom
+                } else {
+                    0
+                };
+                let half = half_width[offset as usize];
+                let (left, right) = ((self.left - half + dx).max(0), (self.right + half + dx).min(width - 1));
+                (left <= right).then_some((row, left, right))
+            })
+            .collect()
+    }
+}
+
+// Midpoint ellipse algorithm: the outline pixels of one quadrant, as offsets
+// (x, y) >= 0 from the center, running from (0, ry) to (rx, 0)
+fn ellipse_quadrant(rx: isize, ry: isize) -> Vec<(isize, isize)> {
+    if rx == 0 || ry == 0 {
+        // degenerate ellipses are straight lines
+        let mut pixels: Vec<(isize, isize)> = (0..=ry).rev().map(|y| (0, y)).collect();
+        pixels.extend((1..=rx).map(|x| (x, 0)));
+        return pixels;
+    }
+
+    // 4 * a2 * b2 needs more than 64 bits for radii beyond a few ten thousand
+    let (a2, b2) = ((rx as i128).pow(2), (ry as i128).pow(2));
+    let (mut x, mut y) = (0i128, ry as i128);
+    let mut pixels = Vec::new();
+
+    // region 1: the slope is shallower than -1, step along x
+    // (decision values are scaled by 4 to stay in integers)
+    let mut d = 4 * b2 - 4 * a2 * y + a2;
+    while b2 * x < a2 * y {
+        pixels.push((x as isize, y as isize));
+        if d >= 0 {
+            d += 4 * a2 * (2 - 2 * y);
+            y -= 1;
+        }
+        d += 4 * b2 * (2 * x + 3);
+        x += 1;
+    }
+
+    // region 2: step along y
+    d = b2 * (2 * x + 1) * (2 * x + 1) + 4 * a2 * (y - 1) * (y - 1) - 4 * a2 * b2;
+    while y >= 0 {
+        pixels.push((x as isize, y as isize));
+        if d <= 0 {
+            d += 4 * b2 * (2 * x + 2);
+            x += 1;
+        }
+        d += 4 * a2 * (3 - 2 * y);
+        y -= 1;
+    }
+    pixels
+}
+
+// The pixel offset where a ray from the center at `angle` meets the ellipse
+fn ellipse_point(rx: isize, ry: isize, angle: f32) -> (isize, isize) {
+    let (x, y) = ellipse_offset(rx as f32, ry as f32, angle);
+    (x.round() as isize, y.round() as isize)
+}
+
+fn ellipse_offset(rx: f32, ry: f32, angle: f32) -> (f32, f32) {
+    let (sin, cos) = angle.sin_cos();
+    let denominator = ((ry * cos).powi(2) + (rx * sin).powi(2)).sqrt();
+    if denominator == 0.0 {
+        return (0.0, 0.0);
+    }
+    let r = rx * ry / denominator;
+    (r * cos, r * sin)
+}
+
+// Bresenham pixels from `start` to `end`, inclusive
+fn line_pixels(start: (isize, isize), end: (isize, isize), pixels: &mut Vec<(isize, isize)>) {
+    let (mut x, mut y) = start;
+    let dx = (end.0 - x).abs();
+    let dy = -(end.1 - y).abs();
+    let sx = if x < end.0 { 1 } else { -1 };
+    let sy = if y < end.1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    loop {
+        pixels.push((x, y));
+        if (x, y) == end {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+// The angles covered by an arc
+struct Sweep {
+    start: f32,
+    length: f32,
+    full: bool,
+}
+
+impl Sweep {
+    fn new(start_angle: f32, end_angle: f32) -> Result<Self> {
+        if !start_angle.is_finite() || !end_angle.is_finite() {
+            return Err(Error::InvalidGeometry(format!(
+                "arc angles must be finite, got {} and {}",
+                start_angle, end_angle
+            )));
+        }
+        let length = end_angle - start_angle;
+        let full = length >= TAU;
+        Ok(Sweep { start: start_angle, length: length.rem_euclid(TAU), full })
+    }
+
+    // Whether the direction from the center to the pixel offset lies on the arc
+    fn contains(&self, dx: isize, dy: isize) -> bool {
+        if self.full || (dx == 0 && dy == 0) {
+            return true;
+        }
+        let angle = (dy as f32).atan2(dx as f32);
+        (angle - self.start).rem_euclid(TAU) <= self.length
+    }
+
+    // The arc as a path, closed through the center for a pie and grown by `grow`
+    // pixels like `RoundedBox::path`
+    fn path(&self, center: (isize, isize), rx: isize, ry: isize, pie: bool, grow: f32) -> Path {
+        let (cx, cy) = (center.0 as f32, center.1 as f32);
+        let (rx, ry) = (rx as f32 + grow, ry as f32 + grow);
+        let mut path = Path::new();
+        if self.full {
+            path.ellipse(cx, cy, rx, ry);
+            return path;
+        }
+        let (start_x, start_y) = ellipse_offset(rx, ry, self.start);
+        let (end_x, end_y) = ellipse_offset(rx, ry, self.start + self.length);
+        if pie {
+            path.move_to(cx, cy);
+        }
+        path.line_to(cx + start_x, cy + start_y);
+        path.arc_to(rx, ry, 0.0, self.length > PI, true, cx + end_x, cy + end_y);
+        if pie {
+            path.close();
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blend::BlendMode;
+
+    fn square(x: f32, y: f32, size: f32) -> Vec<Vec3> {
+        vec![
+            Vec3::new(x, y, 0.0),
+            Vec3::new(x + size, y, 0.0),
+            Vec3::new(x + size, y + size, 0.0),
+            Vec3::new(x, y + size, 0.0),
+        ]
+    }
+
+    fn filled(rule: FillRule, contours: &[Vec<Vec3>]) -> Framebuffer {
+        let mut fb = Framebuffer::new(30, 30);
+        fb.set_background_color(Color::from_hex(0x0000FF));
+        fb.clear();
+        fill_polygon(&mut fb, contours, rule, Color::from_hex(0xFF0000)).unwrap();
+        fb
+    }
+
+    fn is_filled(fb: &Framebuffer, x: isize, y: isize) -> bool {
+        fb.get_point(x, y) == Some(Color::from_hex(0xFF0000))
+    }
+
+    #[test]
+    fn test_rejects_degenerate_polygons() {
+        let mut fb = Framebuffer::new(10, 10);
+        let line = vec![Vec3::new(1.0, 1.0, 0.0), Vec3::new(5.0, 5.0, 0.0)];
+        let red = Color::from_hex(0xFF0000);
+        assert!(matches!(draw_polygon(&mut fb, &line, red), Err(Error::InvalidGeometry(_))));
+        assert!(matches!(fill_polygon(&mut fb, &[line], FillRule::NonZero, red), Err(Error::InvalidGeometry(_))));
+        let no_contours: [Vec<Vec3>; 0] = [];
+        assert!(matches!(fill_polygon(&mut fb, &no_contours, FillRule::EvenOdd, red), Err(Error::InvalidGeometry(_))));
+        assert_eq!(fb.get_point(3, 3), Some(Color::from_hex(0x000000)));
+    }
+
+    #[test]
+    fn test_hole_keeps_background() {
+        let outer = square(2.0, 2.0, 20.0);
+        let mut hole = square(8.0, 8.0, 6.0);
+        hole.reverse();
+
+        for rule in [FillRule::EvenOdd, FillRule::NonZero] {
+            let fb = filled(rule, &[outer.clone(), hole.clone()]);
+            assert!(is_filled(&fb, 4, 4));
+            assert!(!is_filled(&fb, 11, 11));
+            assert_eq!(fb.get_point(11, 11), Some(Color::from_hex(0x0000FF)));
+        }
+    }
+
+    #[test]
+    fn test_fill_rules_differ_on_same_orientation() {
+        // the inner square winds the same way as the outer one
+        let contours = [square(2.0, 2.0, 20.0), square(8.0, 8.0, 6.0)];
+        assert!(!is_filled(&filled(FillRule::EvenOdd, &contours), 11, 11));
+        assert!(is_filled(&filled(FillRule::NonZero, &contours), 11, 11));
+    }
+
+    #[test]
+    fn test_shared_edges_tile_exactly() {
+        // a square split along a diagonal with sub-pixel vertices, drawn additively
+        let mut fb = Framebuffer::new(30, 30);
+        fb.set_blend_mode(BlendMode::Additive);
+        let a = Vec3::new(3.3, 2.7, 0.0);
+        let b = Vec3::new(24.6, 4.1, 0.0);
+        let c = Vec3::new(22.2, 25.9, 0.0);
+        let d = Vec3::new(4.5, 21.4, 0.0);
+        let step = Color::from_hex(0x010101);
+        fill_polygon(&mut fb, &[vec![a, b, c]], FillRule::NonZero, step).unwrap();
+        fill_polygon(&mut fb, &[vec![a, c, d]], FillRule::NonZero, step).unwrap();
+
+        let mut whole = Framebuffer::new(30, 30);
+        whole.set_blend_mode(BlendMode::Additive);
+        fill_polygon(&mut whole, &[vec![a, b, c, d]], FillRule::NonZero, step).unwrap();
+
+        for y in 0..30 {
+            for x in 0..30 {
+                let count = fb.get_point(x, y).unwrap().r;
+                assert!(count <= 1, "pixel ({}, {}) drawn {} times", x, y, count);
+                assert_eq!(fb.get_point(x, y), whole.get_point(x, y), "pixel ({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_top_left_rule() {
+        // a 4x3 rectangle on integer coordinates covers exactly 4x3 pixel centers
+        let rectangle = vec![Vec3::new(5.0, 5.0, 0.0), Vec3::new(9.0, 5.0, 0.0), Vec3::new(9.0, 8.0, 0.0), Vec3::new(5.0, 8.0, 0.0)];
+        let fb = filled(FillRule::NonZero, &[rectangle]);
+        let count = (0..30).flat_map(|y| (0..30).map(move |x| (x, y))).filter(|&(x, y)| is_filled(&fb, x, y)).count();
+        assert_eq!(count, 12);
+        assert!(is_filled(&fb, 5, 5) && is_filled(&fb, 8, 7));
+        assert!(!is_filled(&fb, 9, 5) && !is_filled(&fb, 5, 8));
+    }
+
+    #[test]
+    fn test_sub_pixel_and_clipped_polygons() {
+        // a sliver narrower than a pixel still covers the centers inside it
+        let sliver = vec![Vec3::new(9.8, 2.0, 0.0), Vec3::new(10.2, 2.0, 0.0), Vec3::new(10.2, 12.0, 0.0), Vec3::new(9.8, 12.0, 0.0)];
+        let fb = filled(FillRule::NonZero, &[sliver]);
+        assert!((2..12).all(|y| is_filled(&fb, 10, y)));
+        assert!(!is_filled(&fb, 9, 5) && !is_filled(&fb, 11, 5));
+
+        // polygons hanging off the framebuffer are clipped instead of panicking
+        let fb = filled(FillRule::NonZero, &[square(-50.0, -50.0, 70.0)]);
+        assert!(is_filled(&fb, 0, 0) && is_filled(&fb, 19, 19) && !is_filled(&fb, 20, 20));
+    }
+
+    #[test]
+    fn test_self_intersecting_star() {
+        // a pentagram: the center is wound twice
+        let star: Vec<Vec3> = (0..5)
+            .map(|i| {
+                let angle = -std::f32::consts::FRAC_PI_2 + i as f32 * 4.0 * std::f32::consts::PI / 5.0;
+                Vec3::new(15.0 + 12.0 * angle.cos(), 15.0 + 12.0 * angle.sin(), 0.0)
+            })
+            .collect();
+        let contours = [star];
+        assert!(!is_filled(&filled(FillRule::EvenOdd, &contours), 15, 15));
+        assert!(is_filled(&filled(FillRule::NonZero, &contours), 15, 15));
+    }
+
+    fn lit(fb: &Framebuffer) -> Vec<(isize, isize)> {
+        let mut pixels = Vec::new();
+        for y in 0..fb.height as isize {
+            for x in 0..fb.width as isize {
+                if fb.get_point(x, y) != Some(Color::from_hex(0x000000)) {
+                    pixels.push((x, y));
+                }
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn test_circle_outline() {
+        let mut fb = Framebuffer::new(40, 40);
+        draw_circle(&mut fb, (20, 20), 12, Color::from_hex(0xFFFFFF)).unwrap();
+        let pixels = lit(&fb);
+        for &(x, y) in &pixels {
+            let distance = (((x - 20).pow(2) + (y - 20).pow(2)) as f32).sqrt();
+            assert!((distance - 12.0).abs() < 0.75, "({}, {}) is {} from the center", x, y, distance);
+            // symmetric across both axes and the diagonal
+            assert!(pixels.contains(&(40 - x, y)) && pixels.contains(&(x, 40 - y)));
+            assert!(pixels.contains(&(y, x)));
+        }
+        assert!(pixels.contains(&(32, 20)) && pixels.contains(&(20, 8)));
+
+        // a zero radius is a single pixel
+        let mut fb = Framebuffer::new(5, 5);
+        draw_circle(&mut fb, (2, 2), 0, Color::from_hex(0xFFFFFF)).unwrap();
+        assert_eq!(lit(&fb), vec![(2, 2)]);
+    }
+
+    #[test]
+    fn test_filled_ellipse_contains_outline() {
+        let mut outline = Framebuffer::new(50, 30);
+        draw_ellipse(&mut outline, (25, 15), 20, 9, Color::from_hex(0xFFFFFF)).unwrap();
+        let mut filled = Framebuffer::new(50, 30);
+        fill_ellipse(&mut filled, (25, 15), 20, 9, Color::from_hex(0xFFFFFF)).unwrap();
+
+        let outline = lit(&outline);
+        let filled = lit(&filled);
+        assert!(outline.iter().all(|pixel| filled.contains(pixel)));
+        assert!(outline.contains(&(5, 15)) && outline.contains(&(45, 15)));
+        assert!(outline.contains(&(25, 6)) && outline.contains(&(25, 24)));
+        assert!(!filled.contains(&(4, 15)) && !filled.contains(&(25, 5)));
+
+        let area = std::f32::consts::PI * 20.0 * 9.0;
+        assert!((filled.len() as f32 - area).abs() < area * 0.1, "{} pixels for area {}", filled.len(), area);
+    }
+
+    #[test]
+    fn test_translucent_shapes_blend_once() {
+        for draw in [
+            |fb: &mut Framebuffer, c| draw_circle(fb, (15, 15), 10, c),
+            |fb: &mut Framebuffer, c| fill_ellipse(fb, (15, 15), 12, 5, c),
+            |fb: &mut Framebuffer, c| draw_pie(fb, (15, 15), 10, 10, 0.3, 2.0, c),
+            |fb: &mut Framebuffer, c| draw_rounded_rect(fb, 2, 3, 25, 20, 6, c),
+        ] {
+            let mut fb = Framebuffer::new(30, 30);
+            fb.set_blend_mode(BlendMode::Additive);
+            draw(&mut fb, Color::from_hex(0x010101)).unwrap();
+            assert!(lit(&fb).iter().all(|&(x, y)| fb.get_point(x, y).unwrap().r == 1));
+        }
+    }
+
+    #[test]
+    fn test_arcs_and_pies() {
+        use std::f32::consts::{FRAC_PI_2, PI};
+
+        // the quarter from +x to +y is the bottom-right on screen
+        let mut fb = Framebuffer::new(30, 30);
+        draw_arc(&mut fb, (15, 15), 10, 10, 0.0, FRAC_PI_2, Color::from_hex(0xFFFFFF)).unwrap();
+        let arc = lit(&fb);
+        assert!(arc.contains(&(25, 15)) && arc.contains(&(15, 25)));
+        assert!(arc.iter().all(|&(x, y)| x >= 15 && y >= 15));
+
+        // swapping the angles draws the rest of the circle
+        let mut fb = Framebuffer::new(30, 30);
+        draw_arc(&mut fb, (15, 15), 10, 10, FRAC_PI_2, 0.0, Color::from_hex(0xFFFFFF)).unwrap();
+        assert!(lit(&fb).contains(&(5, 15)) && !lit(&fb).contains(&(22, 22)));
+
+        // a half-disc pie fills only the upper half
+        let mut fb = Framebuffer::new(30, 30);
+        fill_pie(&mut fb, (15, 15), 10, 6, PI, 2.0 * PI, Color::from_hex(0xFFFFFF)).unwrap();
+        let pie = lit(&fb);
+        assert!(pie.contains(&(15, 15)) && pie.contains(&(15, 9)) && pie.contains(&(6, 15)));
+        assert!(pie.iter().all(|&(_, y)| y <= 15));
+
+        // the pie outline closes the arc with both radii
+        let mut fb = Framebuffer::new(30, 30);
+        draw_pie(&mut fb, (15, 15), 10, 10, 0.0, FRAC_PI_2, Color::from_hex(0xFFFFFF)).unwrap();
+        let outline = lit(&fb);
+        assert!((15..=25).all(|x| outline.contains(&(x, 15))));
+        assert!((15..=25).all(|y| outline.contains(&(15, y))));
+        assert!(!outline.contains(&(18, 18)));
+    }
+
+    #[test]
+    fn test_rectangles() {
+        let mut fb = Framebuffer::new(20, 20);
+        draw_rect(&mut fb, 2, 3, 10, 6, Color::from_hex(0xFFFFFF)).unwrap();
+        let outline = lit(&fb);
+        assert_eq!(outline.len(), 2 * 10 + 2 * 6 - 4);
+        assert!(outline.contains(&(2, 3)) && outline.contains(&(11, 8)) && !outline.contains(&(5, 5)));
+
+        let mut fb = Framebuffer::new(20, 20);
+        fill_rect(&mut fb, 2, 3, 10, 6, Color::from_hex(0xFFFFFF)).unwrap();
+        assert_eq!(lit(&fb).len(), 60);
+
+        // rounded corners leave the bounding box corners empty but keep the edges
+        let mut fb = Framebuffer::new(20, 20);
+        fill_rounded_rect(&mut fb, 1, 1, 16, 12, 4, Color::from_hex(0xFFFFFF)).unwrap();
+        let rounded = lit(&fb);
+        assert!(!rounded.contains(&(1, 1)) && !rounded.contains(&(16, 12)));
+        assert!(rounded.contains(&(5, 1)) && rounded.contains(&(1, 5)) && rounded.contains(&(16, 8)));
+
+        // an oversized radius is clamped to half the shorter side
+        let mut fb = Framebuffer::new(20, 20);
+        draw_rounded_rect(&mut fb, 0, 0, 11, 11, 50, Color::from_hex(0xFFFFFF)).unwrap();
+        let mut circle = Framebuffer::new(20, 20);
+        draw_circle(&mut circle, (5, 5), 5, Color::from_hex(0xFFFFFF)).unwrap();
+        assert_eq!(lit(&fb), lit(&circle));
+    }
+
+    #[test]
+    fn test_rejects_invalid_primitives() {
+        let mut fb = Framebuffer::new(10, 10);
+        let white = Color::from_hex(0xFFFFFF);
+        assert!(matches!(draw_circle(&mut fb, (5, 5), -1, white), Err(Error::InvalidGeometry(_))));
+        assert!(matches!(fill_rect(&mut fb, 0, 0, -3, 2, white), Err(Error::InvalidGeometry(_))));
+        assert!(matches!(draw_arc(&mut fb, (5, 5), 3, 3, 0.0, f32::NAN, white), Err(Error::InvalidGeometry(_))));
+        fill_rect(&mut fb, 0, 0, 0, 5, white).unwrap();
+        assert!(lit(&fb).is_empty());
+    }
+
+    #[test]
+    fn test_huge_primitives_are_clipped() {
+        // only the pixels on the canvas are visited, and the radii fit the arithmetic
+        let white = Color::from_hex(0xFFFFFF);
+        let mut fb = Framebuffer::new(10, 10);
+        fill_circle(&mut fb, (5, 5), 40_000, white).unwrap();
+        assert_eq!(lit(&fb).len(), 100);
+
+        let mut fb = Framebuffer::new(10, 10);
+        fill_rect(&mut fb, 0, 0, 200_000, 200_000, white).unwrap();
+        assert_eq!(lit(&fb).len(), 100);
+
+        // the outline passes through the canvas only along its top and left edges
+        let mut fb = Framebuffer::new(10, 10);
+        draw_rounded_rect(&mut fb, 2, 3, 200_000, 200_000, 1, white).unwrap();
+        assert!(fb.get_point(5, 3) == Some(white) && fb.get_point(2, 6) == Some(white));
+        assert_eq!(fb.get_point(5, 5), Some(Color::from_hex(0x000000)));
+
+        let mut fb = Framebuffer::new(10, 10);
+        fill_pie(&mut fb, (0, 0), 50_000, 50_000, 0.0, 1.0, white).unwrap();
+        draw_pie(&mut fb, (0, 0), 50_000, 50_000, 0.0, 1.0, white).unwrap();
+        assert_eq!(fb.get_point(5, 3), Some(white));
+        assert_eq!(fb.get_point(3, 8), Some(Color::from_hex(0x000000)));
+        assert!(matches!(draw_circle(&mut fb, (0, 0), MAX_RADIUS + 1, white), Err(Error::InvalidGeometry(_))));
+    }
+
+    #[test]
+    fn test_fill_path_matches_primitives() {
+        // a circle of radius 10 built from two arcs covers about as much as the ellipse fill
+        let mut path = Path::new();
+        path.move_to(5.0, 15.0).arc_to(10.0, 10.0, 0.0, false, true, 25.0, 15.0).arc_to(10.0, 10.0, 0.0, false, true, 5.0, 15.0).close();
+        let mut from_path = Framebuffer::new(30, 30);
+        fill_path(&mut from_path, &path, FillRule::NonZero, Color::from_hex(0xFFFFFF)).unwrap();
+        let mut circle = Framebuffer::new(30, 30);
+        fill_circle(&mut circle, (15, 15), 10, Color::from_hex(0xFFFFFF)).unwrap();
+        let (a, b) = (lit(&from_path), lit(&circle));
+        assert!(a.iter().all(|pixel| b.contains(pixel)));
+        assert!(b.len() - a.len() < 45, "{} vs {} pixels", a.len(), b.len());
+
+        // a second subpath cuts a hole under even-odd
+        path.move_to(12.0, 12.0).line_to(18.0, 12.0).line_to(18.0, 18.0).line_to(12.0, 18.0);
+        let mut fb = Framebuffer::new(30, 30);
+        fill_path(&mut fb, &path, FillRule::EvenOdd, Color::from_hex(0xFFFFFF)).unwrap();
+        assert!(!lit(&fb).contains(&(15, 15)) && lit(&fb).contains(&(8, 15)));
+    }
+
+    #[test]
+    fn test_draw_path() {
+        let mut path = Path::new();
+        path.move_to(2.0, 2.0).line_to(20.0, 2.0).move_to(2.0, 10.0).quad_to(11.0, 28.0, 20.0, 10.0);
+        let mut fb = Framebuffer::new(30, 30);
+        draw_path(&mut fb, &path, Color::from_hex(0xFFFFFF)).unwrap();
+        let pixels = lit(&fb);
+        assert!((2..=20).all(|x| pixels.contains(&(x, 2))));
+        assert!(pixels.contains(&(2, 10)) && pixels.contains(&(20, 10)) && pixels.contains(&(11, 19)));
+        // the subpaths are not joined
+        assert!(!pixels.contains(&(2, 6)));
+    }
+
+    #[test]
+    fn test_transformed_polygons() {
+        use crate::transform::{rotation, translation};
+        use std::f32::consts::FRAC_PI_2;
+
+        let red = Color::from_hex(0xFF0000);
+        let pixels = |contours: &[Vec<Vec3>], transform: &dyn Fn(&mut Framebuffer)| {
+            let mut fb = Framebuffer::new(30, 30);
+            transform(&mut fb);
+            fill_polygon(&mut fb, contours, FillRule::NonZero, red).unwrap();
+            lit(&fb)
+        };
+
+        // the same square placed twice with push and pop
+        let mut fb = Framebuffer::new(30, 30);
+        for offset in [0.0, 12.0] {
+            fb.push_transform();
+            fb.translate(offset, 5.0);
+            fill_polygon(&mut fb, &[square(2.5, 2.5, 6.0)], FillRule::NonZero, red).unwrap();
+            assert!(fb.pop_transform());
+        }
+        assert_eq!(lit(&fb), pixels(&[square(2.5, 7.5, 6.0), square(14.5, 7.5, 6.0)], &|_| {}));
+        assert_eq!(fb.transform(), crate::transform::identity());
+
+        // a quarter turn about the origin swings the square to the left of it
+        let rotated = pixels(&[square(0.5, 0.5, 6.0)], &|fb| fb.set_transform(translation(20.0, 5.0) * rotation(FRAC_PI_2)));
+        assert_eq!(rotated, pixels(&[square(13.5, 5.5, 6.0)], &|_| {}));
+
+        // outlines follow the transform too
+        let mut fb = Framebuffer::new(30, 30);
+        fb.scale(2.0, 2.0);
+        draw_polygon(&mut fb, &square(2.0, 2.0, 5.0), Color::from_hex(0xFFFFFF)).unwrap();
+        let outline = lit(&fb);
+        assert!(outline.contains(&(4, 4)) && outline.contains(&(14, 14)) && !outline.contains(&(9, 9)));
+    }
+
+    #[test]
+    fn test_transformed_primitives() {
+        let white = Color::from_hex(0xFFFFFF);
+
+        // whole-pixel translations keep the exact midpoint pixels
+        let mut moved = Framebuffer::new(30, 30);
+        moved.translate(4.0, -3.0);
+        draw_circle(&mut moved, (10, 15), 8, white).unwrap();
+        let mut placed = Framebuffer::new(30, 30);
+        draw_circle(&mut placed, (14, 12), 8, white).unwrap();
+        assert_eq!(lit(&moved), lit(&placed));
+
+        // a scaled circle is filled as an ellipse
+        let mut fb = Framebuffer::new(40, 30);
+        fb.scale(2.0, 1.0);
+        fill_circle(&mut fb, (10, 15), 8, white).unwrap();
+        let ellipse = lit(&fb);
+        assert!(ellipse.contains(&(4, 15)) && ellipse.contains(&(36, 15)) && ellipse.contains(&(20, 23)));
+        assert!(!ellipse.contains(&(20, 24)) && !ellipse.contains(&(38, 15)));
+
+        // a rectangle turned a quarter about its corner covers the same number of pixels
+        let mut fb = Framebuffer::new(30, 30);
+        fb.translate(20.0, 2.0);
+        fb.rotate(std::f32::consts::FRAC_PI_2);
+        fill_rect(&mut fb, 0, 0, 10, 6, white).unwrap();
+        let rotated = lit(&fb);
+        assert_eq!(rotated.len(), 60);
+        assert!(rotated.iter().all(|&(x, y)| (15..=20).contains(&x) && (2..=11).contains(&y)));
+
+        // arcs keep their direction under a rotation
+        let mut fb = Framebuffer::new(30, 30);
+        fb.translate(15.0, 15.0);
+        fb.rotate(std::f32::consts::PI);
+        draw_arc(&mut fb, (0, 0), 10, 10, 0.0, std::f32::consts::FRAC_PI_2, white).unwrap();
+        let arc = lit(&fb);
+        assert!(arc.contains(&(5, 15)) && arc.contains(&(15, 5)));
+        assert!(arc.iter().all(|&(x, y)| x <= 15 && y <= 15));
+    }
+}
@@ -0,0 +1,575 @@
+use nalgebra_glm::{Vec2, Vec3};
+
+use crate::framebuffer::Framebuffer;
+use crate::line_impl::LineMode;
+
+/// Shape drawn at the open ends of a stroke.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum LineCap {
+    /// The stroke stops exactly at the endpoint.
+    #[default]
+    Butt,
+    /// A half circle centered on the endpoint.
+    Round,
+    /// The stroke extends past the endpoint by half its width.
+    Square,
+}
+
+/// Shape drawn where two segments of a stroke meet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum LineJoin {
+    /// Outer edges are extended until they meet, falling back to a bevel past the miter limit.
+    #[default]
+    Miter,
+    /// A circle centered on the vertex.
+    Round,
+    /// The outer corners are connected with a straight edge.
+    Bevel,
+}
+
+/// Width, caps and joins used when stroking lines and polygon outlines.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StrokeStyle {
+    /// Stroke width in pixels; widths of 1 or less are drawn as hairlines.
+    pub width: f32,
+    pub cap: LineCap,
+    pub join: LineJoin,
+    /// Maximum ratio between the miter length and the stroke width.
+    pub miter_limit: f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        StrokeStyle { width: 1.0, cap: LineCap::Butt, join: LineJoin::Miter, miter_limit: 4.0 }
+    }
+}
+
+impl StrokeStyle {
+    pub fn new(width: f32) -> Self {
+        StrokeStyle { width, ..StrokeStyle::default() }
+    }
+
+    pub fn with_cap(self, cap: LineCap) -> Self {
+        StrokeStyle { cap, ..self }
+    }
+
+    pub fn with_join(self, join: LineJoin) -> Self {
+        StrokeStyle { join, ..self }
+    }
+
+    pub fn with_miter_limit(self, miter_limit: f32) -> Self {
+        StrokeStyle { miter_limit, ..self }
+    }
+
+    pub fn is_hairline(&self) -> bool {
+        self.width <= 1.0
+    }
+}
+
+/// On/off pattern applied along a stroke. Patterns are measured along the whole
+/// polyline, so they continue across vertices instead of restarting on each edge.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum StrokePattern {
+    #[default]
+    Solid,
+    /// Alternating dash and gap lengths in pixels, starting `offset` pixels into the
+    /// pattern. As in SVG, an odd number of lengths is repeated to make it even.
+    /// Patterns repeating more often than every tenth of a pixel draw solid.
+    Dashed { dashes: Vec<f32>, offset: f32 },
+    /// Dots as long as the stroke width, separated by gaps of the same length.
+    Dotted,
+    /// One bit per pixel of stroke length, least significant bit first, repeating
+    /// every `length` bits (at most 32), like OpenGL line stipple.
+    Bitmask { bits: u32, length: u32, offset: f32 },
+}
+
+// Shortest pattern period that is still dashed; anything finer looks solid anyway
+const MIN_PATTERN_PERIOD: f32 = 0.1;
+
+// Most pattern repeats along one polyline before it is drawn solid instead
+const MAX_PATTERN_REPEATS: f32 = 1_000_000.0;
+
+impl StrokePattern {
+    pub fn dashed(dashes: &[f32]) -> Self {
+        StrokePattern::Dashed { dashes: dashes.to_vec(), offset: 0.0 }
+    }
+
+    pub fn bitmask(bits: u32, length: u32) -> Self {
+        StrokePattern::Bitmask { bits, length, offset: 0.0 }
+    }
+
+    pub fn is_solid(&self) -> bool {
+        *self == StrokePattern::Solid
+    }
+
+    // Expands the pattern into (length, on) runs plus its starting offset.
+    // Returns None when the pattern draws a solid line.
+    fn runs(&self, width: f32) -> Option<(Vec<(f32, bool)>, f32)> {
+        let (runs, offset) = match self {
+            StrokePattern::Solid => return None,
+            StrokePattern::Dashed { dashes, offset } => {
+                // invalid dash arrays render solid, as SVG does
+                if dashes.iter().any(|&d| d < 0.0 || !d.is_finite()) {
+                    return None;
+                }
+                let repeat = if dashes.len() % 2 == 1 { 2 } else { 1 };
+                let runs = dashes.iter().cycle().take(dashes.len() * repeat).enumerate().map(|(i, &d)| (d, i % 2 == 0)).collect();
+                (runs, *offset)
+            }
+            StrokePattern::Dotted => {
+                let dot = width.max(1.0);
+                (vec![(dot, true), (dot, false)], 0.0)
+            }
+            StrokePattern::Bitmask { bits, length, offset } => {
+                let length = (*length).clamp(1, 32);
+                let mut runs: Vec<(f32, bool)> = Vec::new();
+                for i in 0..length {
+                    let on = bits >> i & 1 == 1;
+                    match runs.last_mut() {
+                        Some(run) if run.1 == on => run.0 += 1.0,
+                        _ => runs.push((1.0, on)),
+                    }
+                }
+                (runs, *offset)
+            }
+        };
+
+        if runs.iter().all(|&(_, on)| on) {
+            return None;
+        }
+        if runs.iter().map(|&(length, _)| length).sum::<f32>() < MIN_PATTERN_PERIOD {
+            return None;
+        }
+        Some((runs, offset))
+    }
+}
+
+// Convex pieces whose union forms the stroke outline
+enum Piece {
+    Polygon(Vec<Vec2>),
+    Circle(Vec2, f32),
+}
+
+impl Piece {
+    fn bounds(&self) -> (Vec2, Vec2) {
+        match self {
+            Piece::Polygon(points) => {
+                let min = points.iter().fold(Vec2::new(f32::MAX, f32::MAX), |m, p| Vec2::new(m.x.min(p.x), m.y.min(p.y)));
+                let max = points.iter().fold(Vec2::new(f32::MIN, f32::MIN), |m, p| Vec2::new(m.x.max(p.x), m.y.max(p.y)));
+                (min, max)
+            }
+            Piece::Circle(center, radius) => (center - Vec2::new(*radius, *radius), center + Vec2::new(*radius, *radius)),
+        }
+    }
+
+    fn contains(&self, p: Vec2) -> bool {
+        match self {
+            Piece::Polygon(points) => {
+                // inside a convex polygon every edge sees the point on the same side
+                let (mut positive, mut negative) = (false, false);
+                for i in 0..points.len() {
+                    let a = points[i];
+                    let b = points[(i + 1) % points.len()];
+                    let cross = (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+                    positive |= cross > 1e-4;
+                    negative |= cross < -1e-4;
+                }
+                !(positive && negative)
+            }
+            Piece::Circle(center, radius) => (p - center).norm_squared() <= radius * radius,
+        }
+    }
+}
+
+// Sub-pixel sample grid used for anti-aliased strokes
+const SAMPLES_PER_AXIS: usize = 4;
+
+/// Strokes the polyline with the framebuffer's stroke style, current color and line mode.
+pub(crate) fn stroke_polyline(framebuffer: &mut Framebuffer, vertices: &[Vec3], closed: bool) {
+    let style = framebuffer.stroke_style();
+    let mut points: Vec<Vec2> = Vec::with_capacity(vertices.len());
+    for v in vertices {
+        // repeated points carry no direction
+        if points.last().is_none_or(|last| (last - v.xy()).norm() > 1e-6) {
+            points.push(v.xy());
+        }
+    }
+    if closed && points.len() > 1 && (points[0] - points[points.len() - 1]).norm() <= 1e-6 {
+        points.pop();
+    }
+    if points.is_empty() {
+        return;
+    }
+
+    let pieces = outline_pieces(&points, closed && points.len() > 2, &style);
+    fill_pieces(framebuffer, &pieces);
+}
+
+fn outline_pieces(points: &[Vec2], closed: bool, style: &StrokeStyle) -> Vec<Piece> {
+    let half = style.width / 2.0;
+    let mut pieces = Vec::new();
+
+    if points.len() == 1 {
+        // a lone point only shows up through its caps
+        let p = points[0];
+        match style.cap {
+            LineCap::Butt => {}
+            LineCap::Round => pieces.push(Piece::Circle(p, half)),
+            LineCap::Square => pieces.push(Piece::Polygon(vec![
+                p + Vec2::new(-half, -half),
+                p + Vec2::new(half, -half),
+                p + Vec2::new(half, half),
+                p + Vec2::new(-half, half),
+            ])),
+        }
+        return pieces;
+    }
+
+    let segment_count = if closed { points.len() } else { points.len() - 1 };
+    for i in 0..segment_count {
+        let mut start = points[i];
+        let mut end = points[(i + 1) % points.len()];
+        let direction = (end - start).normalize();
+
+        if !closed && style.cap == LineCap::Square {
+            if i == 0 {
+                start -= direction * half;
+            }
+            if i == segment_count - 1 {
+                end += direction * half;
+            }
+        }
+
+        let normal = perpendicular(direction) * half;
+        pieces.push(Piece::Polygon(vec![start + normal, end + normal, end - normal, start - normal]));
+    }
+
+    if !closed && style.cap == LineCap::Round {
+        pieces.push(Piece::Circle(points[0], half));
+        pieces.push(Piece::Circle(points[points.len() - 1], half));
+    }
+
+    // joins at interior vertices, or at every vertex of a closed outline
+    let joints: Vec<usize> = if closed { (0..points.len()).collect() } else { (1..points.len() - 1).collect() };
+    for i in joints {
+        let previous = points[(i + points.len() - 1) % points.len()];
+        let vertex = points[i];
+        let next = points[(i + 1) % points.len()];
+        let incoming = (vertex - previous).normalize();
+        let outgoing = (next - vertex).normalize();
+
+        let turn = incoming.x * outgoing.y - incoming.y * outgoing.x;
+        if turn.abs() < 1e-6 && incoming.dot(&outgoing) > 0.0 {
+            continue; // straight continuation, the segments already touch
+        }
+
+        // the join fills the gap on the outer side of the turn
+        let side = if turn > 0.0 { -1.0 } else { 1.0 };
+        let outer_in = vertex + perpendicular(incoming) * half * side;
+        let outer_out = vertex + perpendicular(outgoing) * half * side;
+
+        match style.join {
+            LineJoin::Round => pieces.push(Piece::Circle(vertex, half)),
+            LineJoin::Bevel => pieces.push(Piece::Polygon(vec![vertex, outer_in, outer_out])),
+            LineJoin::Miter => {
+                let bisector = perpendicular(incoming) + perpendicular(outgoing);
+                // |bisector| = 2 cos(theta / 2); the miter ratio is 1 / cos(theta / 2)
+                let cos_half = bisector.norm() / 2.0;
+                if cos_half > 1e-6 && 1.0 / cos_half <= style.miter_limit {
+                    let tip = vertex + bisector.normalize() * (half / cos_half) * side;
+                    pieces.push(Piece::Polygon(vec![vertex, outer_in, tip, outer_out]));
+                } else {
+                    pieces.push(Piece::Polygon(vec![vertex, outer_in, outer_out]));
+                }
+            }
+        }
+    }
+
+    pieces
+}
+
+fn perpendicular(v: Vec2) -> Vec2 {
+    Vec2::new(-v.y, v.x)
+}
+
+// Rasterizes the union of the pieces into a sample mask, then blends each pixel once
+// so overlapping pieces do not darken translucent strokes.
+fn fill_pieces(framebuffer: &mut Framebuffer, pieces: &[Piece]) {
+    if pieces.is_empty() {
+        return;
+    }
+
+    let anti_aliased = framebuffer.line_mode() == LineMode::AntiAliased;
+    let samples: Vec<Vec2> = if anti_aliased {
+        let step = 1.0 / SAMPLES_PER_AXIS as f32;
+        (0..SAMPLES_PER_AXIS * SAMPLES_PER_AXIS)
+            .map(|i| {
+                let (sx, sy) = (i % SAMPLES_PER_AXIS, i / SAMPLES_PER_AXIS);
+                Vec2::new(-0.5 + (sx as f32 + 0.5) * step, -0.5 + (sy as f32 + 0.5) * step)
+            })
+            .collect()
+    } else {
+        vec![Vec2::new(0.0, 0.0)]
+    };
+
+    // pixel centers sit on integer coordinates, clipped to the framebuffer
+    let (mut min, mut max) = pieces[0].bounds();
+    for piece in &pieces[1..] {
+        let (lo, hi) = piece.bounds();
+        min = Vec2::new(min.x.min(lo.x), min.y.min(lo.y));
+        max = Vec2::new(max.x.max(hi.x), max.y.max(hi.y));
+    }
+    let x0 = (min.x - 0.5).floor().max(0.0) as isize;
+    let y0 = (min.y - 0.5).floor().max(0.0) as isize;
+    let x1 = ((max.x + 0.5).ceil() as isize).min(framebuffer.width as isize - 1);
+    let y1 = ((max.y + 0.5).ceil() as isize).min(framebuffer.height as isize - 1);
+    if x0 > x1 || y0 > y1 {
+        return;
+    }
+
+    let mask_width = (x1 - x0 + 1) as usize;
+    let mut mask = vec![0u16; mask_width * (y1 - y0 + 1) as usize];
+    for piece in pieces {
+        let (lo, hi) = piece.bounds();
+        let px0 = ((lo.x - 0.5).floor() as isize).max(x0);
+        let py0 = ((lo.y - 0.5).floor() as isize).max(y0);
+        let px1 = ((hi.x + 0.5).ceil() as isize).min(x1);
+        let py1 = ((hi.y + 0.5).ceil() as isize).min(y1);
+        for y in py0..=py1 {
+            for x in px0..=px1 {
+                let cell = &mut mask[(y - y0) as usize * mask_width + (x - x0) as usize];
+                for (bit, offset) in samples.iter().enumerate() {
+                    if *cell & (1 << bit) == 0 && piece.contains(Vec2::new(x as f32, y as f32) + offset) {
+                        *cell |= 1 << bit;
+                    }
+                }
+            }
+        }
+    }
+
+    let color = framebuffer.current_color();
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            let hits = mask[(y - y0) as usize * mask_width + (x - x0) as usize].count_ones();
+            if hits == 0 {
+                continue;
+            }
+            let alpha = (color.a as u32 * hits + samples.len() as u32 / 2) / samples.len() as u32;
+            framebuffer.blend_point(x, y, color.with_alpha(alpha as u8));
+        }
+    }
+}
+
+/// Splits a polyline into the pieces covered by the pattern's "on" runs. Each piece
+/// keeps the vertices it passes through so joins are still drawn inside a dash.
+/// Returns None when the pattern is solid, or repeats too many times along the
+/// polyline to be worth splitting.
+pub(crate) fn dash_polyline(vertices: &[Vec3], closed: bool, pattern: &StrokePattern, width: f32) -> Option<Vec<Vec<Vec3>>> {
+    let (runs, offset) = pattern.runs(width)?;
+    let mut dashes = Vec::new();
+    if vertices.is_empty() {
+        return Some(dashes);
+    }
+    let segment_count = if closed { vertices.len() } else { vertices.len() - 1 };
+    let segment_length = |i: usize| (vertices[(i + 1) % vertices.len()] - vertices[i]).xy().norm();
+    let period: f32 = runs.iter().map(|&(length, _)| length).sum();
+    let total_length: f32 = (0..segment_count).map(segment_length).sum();
+    let repeats = total_length / period;
+    if repeats.is_nan() || repeats > MAX_PATTERN_REPEATS {
+        return None;
+    }
+
+    // find where in the pattern the path starts
+    let mut phase = offset.rem_euclid(period);
+    let mut run = 0;
+    while phase >= runs[run].0 && !(phase == 0.0 && runs[run].0 == 0.0) {
+        phase -= runs[run].0;
+        run = (run + 1) % runs.len();
+    }
+    let mut remaining = runs[run].0 - phase;
+    let mut on = runs[run].1;
+
+    let mut current: Vec<Vec3> = Vec::new();
+    if on {
+        current.push(vertices[0]);
+    }
+
+    for i in 0..segment_count {
+        let start = vertices[i];
+        let end = vertices[(i + 1) % vertices.len()];
+        let length = segment_length(i);
+        let mut travelled = 0.0;
+
+        loop {
+            // far from the origin, runs too short to move along the segment end it too
+            if remaining > length - travelled || (remaining > 0.0 && travelled + remaining == travelled) {
+                // the current run continues into the next segment
+                remaining -= length - travelled;
+                if on {
+                    current.push(end);
+                }
+                break;
+            }
+
+            travelled += remaining;
+            let point = start.lerp(&end, if length > 0.0 { travelled / length } else { 0.0 });
+            if on {
+                current.push(point);
+                dashes.push(std::mem::take(&mut current));
+            }
+            run = (run + 1) % runs.len();
+            remaining = runs[run].0;
+            on = runs[run].1;
+            if on {
+                current.push(point);
+            }
+        }
+    }
+
+    // a dash that only just started at the very end has no length
+    if current.len() > 1 {
+        dashes.push(current);
+    }
+    Some(dashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::line_impl::Line;
+
+    const WHITE: u32 = 0xFFFFFF;
+    const BLACK: u32 = 0x000000;
+
+    fn canvas(style: StrokeStyle) -> Framebuffer {
+        let mut fb = Framebuffer::new(40, 40);
+        fb.set_background_color(Color::from_hex(WHITE));
+        fb.clear();
+        fb.set_current_color(Color::from_hex(BLACK));
+        fb.set_stroke_style(style);
+        fb
+    }
+
+    fn is_ink(fb: &Framebuffer, x: isize, y: isize) -> bool {
+        fb.get_point(x, y) == Some(Color::from_hex(BLACK))
+    }
+
+    fn square(size: f32, offset: f32) -> Vec<Vec3> {
+        vec![
+            Vec3::new(offset, offset, 0.0),
+            Vec3::new(offset + size, offset, 0.0),
+            Vec3::new(offset + size, offset + size, 0.0),
+            Vec3::new(offset, offset + size, 0.0),
+        ]
+    }
+
+    #[test]
+    fn test_width_and_caps() {
+        let mut fb = canvas(StrokeStyle::new(5.0));
+        fb.draw_line(10.0, 20.0, 30.0, 20.0);
+        // five rows thick, ends cut at the endpoints
+        assert!((18..=22).all(|y| is_ink(&fb, 20, y)));
+        assert!(!is_ink(&fb, 20, 17) && !is_ink(&fb, 20, 23));
+        assert!(is_ink(&fb, 10, 20) && !is_ink(&fb, 9, 20));
+
+        let mut fb = canvas(StrokeStyle::new(5.0).with_cap(LineCap::Square));
+        fb.draw_line(10.0, 20.0, 30.0, 20.0);
+        assert!(is_ink(&fb, 8, 22) && !is_ink(&fb, 7, 20));
+        assert!(is_ink(&fb, 32, 18) && !is_ink(&fb, 33, 20));
+
+        let mut fb = canvas(StrokeStyle::new(5.0).with_cap(LineCap::Round));
+        fb.draw_line(10.0, 20.0, 30.0, 20.0);
+        assert!(is_ink(&fb, 8, 20) && !is_ink(&fb, 8, 18));
+    }
+
+    #[test]
+    fn test_joins() {
+        let corner = |join| {
+            let mut fb = canvas(StrokeStyle::new(6.0).with_join(join));
+            stroke_polyline(&mut fb, &square(20.0, 10.0), true);
+            fb
+        };
+
+        // the outer corner of the top-left vertex is (7, 7)
+        let miter = corner(LineJoin::Miter);
+        assert!(is_ink(&miter, 7, 7));
+        let bevel = corner(LineJoin::Bevel);
+        assert!(!is_ink(&bevel, 7, 7) && is_ink(&bevel, 9, 9));
+        let round = corner(LineJoin::Round);
+        assert!(!is_ink(&round, 7, 7) && is_ink(&round, 8, 9));
+    }
+
+    #[test]
+    fn test_miter_limit_falls_back_to_bevel() {
+        // a sharp spike whose miter would reach far beyond the vertex
+        let spike = vec![Vec3::new(5.0, 30.0, 0.0), Vec3::new(20.0, 10.0, 0.0), Vec3::new(35.0, 30.0, 0.0)];
+        let tip = |limit| {
+            let mut fb = canvas(StrokeStyle::new(6.0).with_miter_limit(limit));
+            stroke_polyline(&mut fb, &spike, false);
+            is_ink(&fb, 20, 6)
+        };
+        assert!(tip(10.0));
+        assert!(!tip(1.5));
+    }
+
+    #[test]
+    fn test_dash_splitting() {
+        let path = vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0), Vec3::new(10.0, 10.0, 0.0)];
+        let dashes = dash_polyline(&path, false, &StrokePattern::dashed(&[4.0, 2.0]), 1.0).unwrap();
+        let ends: Vec<(Vec3, Vec3)> = dashes.iter().map(|d| (d[0], d[d.len() - 1])).collect();
+        assert_eq!(ends[0], (Vec3::new(0.0, 0.0, 0.0), Vec3::new(4.0, 0.0, 0.0)));
+        assert_eq!(ends[1], (Vec3::new(6.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0)));
+        // the third dash turns the corner and keeps the vertex for its join
+        assert_eq!(dashes[2], vec![Vec3::new(10.0, 2.0, 0.0), Vec3::new(10.0, 6.0, 0.0)]);
+        assert_eq!(dashes.len(), 4);
+
+        // an odd dash array repeats, and the offset shifts the start
+        let odd = StrokePattern::Dashed { dashes: vec![3.0], offset: 1.0 };
+        let dashes = dash_polyline(&path[..2], false, &odd, 1.0).unwrap();
+        assert_eq!(dashes[0], vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0)]);
+        assert_eq!(dashes[1], vec![Vec3::new(5.0, 0.0, 0.0), Vec3::new(8.0, 0.0, 0.0)]);
+
+        let corner = StrokePattern::dashed(&[12.0, 2.0]);
+        let dashes = dash_polyline(&path, false, &corner, 1.0).unwrap();
+        assert_eq!(dashes[0], vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0), Vec3::new(10.0, 2.0, 0.0)]);
+    }
+
+    #[test]
+    fn test_solid_patterns() {
+        let path = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0)];
+        assert!(dash_polyline(&path, false, &StrokePattern::Solid, 1.0).is_none());
+        assert!(dash_polyline(&path, false, &StrokePattern::dashed(&[2.0, -1.0]), 1.0).is_none());
+        assert!(dash_polyline(&path, false, &StrokePattern::dashed(&[0.0, 0.0]), 1.0).is_none());
+        assert!(dash_polyline(&path, false, &StrokePattern::bitmask(0xFFFF, 16), 1.0).is_none());
+
+        // patterns too fine to see, or repeating too often, finish quickly as solid lines
+        assert!(dash_polyline(&path, false, &StrokePattern::dashed(&[1e-30, 1e-30]), 1.0).is_none());
+        let long = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(1e9, 0.0, 0.0)];
+        assert!(dash_polyline(&long, false, &StrokePattern::dashed(&[1.0, 1.0]), 1.0).is_none());
+        let mut fb = canvas(StrokeStyle::new(1.0));
+        fb.set_stroke_pattern(StrokePattern::dashed(&[1e-30, 1e-30]));
+        fb.draw_line(0.0, 5.0, 10.0, 5.0);
+        assert!((0..=10).all(|x| is_ink(&fb, x, 5)));
+    }
+
+    #[test]
+    fn test_translucent_overlap_blends_once() {
+        let mut fb = canvas(StrokeStyle::new(6.0).with_join(LineJoin::Round));
+        fb.set_current_color(Color::from_hex(BLACK).with_alpha(128));
+        stroke_polyline(&mut fb, &square(20.0, 10.0), true);
+        // the corner is covered by two segments and the join
+        assert_eq!(fb.get_point(10, 10), fb.get_point(20, 10));
+    }
+
+    #[test]
+    fn test_anti_aliased_edges() {
+        let mut fb = canvas(StrokeStyle::new(4.0));
+        fb.set_line_mode(LineMode::AntiAliased);
+        fb.draw_line(10.0, 20.0, 30.0, 20.0);
+        // edges at 18 and 22 split the boundary pixels in half
+        let edge = fb.get_point(20, 18).unwrap();
+        assert!(edge.r > 100 && edge.r < 155);
+        assert!(is_ink(&fb, 20, 20));
+    }
+}
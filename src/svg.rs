@@ -0,0 +1,901 @@
+use std::fs;
+
+use nalgebra_glm::{Mat3, Vec2};
+
+use crate::color::Color;
+use crate::error::{Error, Result};
+use crate::fill::FillRule;
+use crate::framebuffer::{Framebuffer, MAX_CANVAS_SIDE};
+use crate::path::Path;
+use crate::shapes::{draw_path, fill_path};
+use crate::stroke::{LineCap, LineJoin, StrokeStyle};
+use crate::transform::{self, rotation, scaling, skewing, translation};
+
+/// A shape read from an SVG document, with its resolved paint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgShape {
+    pub path: Path,
+    pub fill: Option<Color>,
+    pub fill_rule: FillRule,
+    pub stroke: Option<Color>,
+    pub stroke_style: StrokeStyle,
+    /// The element's transform composed with those of its groups.
+    pub transform: Mat3,
+}
+
+/// The drawable content of an SVG document, in painting order.
+///
+/// Supports `<path>`, `<polygon>`, `<polyline>`, `<line>`, `<rect>`, `<circle>`
+/// and `<ellipse>` inside nested `<g>` groups, painted with the `fill`, `stroke`,
+/// `fill-rule`, `stroke-width`, `stroke-linecap`, `stroke-linejoin`,
+/// `stroke-miterlimit`, `color` and opacity properties, given as attributes or in
+/// `style`, and placed by `transform` attributes. `viewBox` scaling is ignored, and
+/// gradient and pattern paints fall back to their fallback color or to no paint.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Svg {
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+    pub shapes: Vec<SvgShape>,
+}
+
+impl Svg {
+    /// The document size in whole pixels, when it declares one. Sizes that are
+    /// empty, or larger than [`MAX_CANVAS_SIDE`] on a side, are an error.
+    pub fn size(&self) -> Result<Option<(usize, usize)>> {
+        let (Some(width), Some(height)) = (self.width, self.height) else {
+            return Ok(None);
+        };
+        let (width, height) = (width.ceil(), height.ceil());
+        let valid = |side: f32| side >= 1.0 && side <= MAX_CANVAS_SIDE as f32;
+        if !valid(width) || !valid(height) {
+            return Err(Error::InvalidDimensions { width: width as usize, height: height as usize });
+        }
+        Ok(Some((width as usize, height as usize)))
+    }
+
+    /// Paints every shape onto the framebuffer, fill first and then stroke, under
+    /// the framebuffer's current transform.
+    pub fn render(&self, framebuffer: &mut Framebuffer) -> Result<()> {
+        let previous_style = framebuffer.stroke_style();
+        for shape in &self.shapes {
+            framebuffer.push_transform();
+            framebuffer.apply_transform(&shape.transform);
+            if let Some(fill) = shape.fill {
+                fill_path(framebuffer, &shape.path, shape.fill_rule, fill)?;
+            }
+            if let Some(stroke) = shape.stroke {
+                framebuffer.set_stroke_style(shape.stroke_style);
+                draw_path(framebuffer, &shape.path, stroke)?;
+            }
+            framebuffer.pop_transform();
+        }
+        framebuffer.set_stroke_style(previous_style);
+        Ok(())
+    }
+}
+
+pub fn read_svg_file(file_path: &str) -> Result<Svg> {
+    let text = fs::read_to_string(file_path)?;
+    parse_svg(&text)
+}
+
+pub fn parse_svg(text: &str) -> Result<Svg> {
+    let tags = parse_tags(text)?;
+    match tags.first() {
+        Some(tag) if tag.name == "svg" && !tag.closing => {}
+        _ => return Err(Error::UnsupportedFormat("not an SVG document".to_string())),
+    }
+
+    let mut svg = Svg::default();
+    let root = &tags[0];
+    let view_box: Vec<f32> = match root.attribute("viewBox") {
+        Some(value) => numbers(value)?,
+        None => Vec::new(),
+    };
+    svg.width = root.attribute("width").and_then(|value| length(value).ok()).or(view_box.get(2).copied());
+    svg.height = root.attribute("height").and_then(|value| length(value).ok()).or(view_box.get(3).copied());
+
+    // presentation inherited from the enclosing elements
+    let mut stack: Vec<Presentation> = Vec::new();
+    // depth inside elements whose children are never painted directly
+    let mut hidden = 0;
+    for tag in &tags {
+        if tag.closing {
+            stack.pop();
+            if hidden > 0 {
+                hidden -= 1;
+            }
+            continue;
+        }
+
+        let mut presentation = stack.last().cloned().unwrap_or_default();
+        presentation.apply(tag)?;
+        if hidden == 0 && !NON_RENDERED.contains(&tag.name) {
+            if let Some(path) = shape_path(tag)? {
+                svg.shapes.push(presentation.shape(path));
+            }
+        }
+        if !tag.self_closing {
+            if hidden > 0 || NON_RENDERED.contains(&tag.name) {
+                hidden += 1;
+            }
+            stack.push(presentation);
+        }
+    }
+    Ok(svg)
+}
+
+/// Parses SVG path data (the `d` attribute) into a path. Supports every command,
+/// absolute and relative: M, L, H, V, C, S, Q, T, A and Z.
+pub fn parse_path_data(data: &str) -> Result<Path> {
+    let mut scanner = Scanner::new(data);
+    let mut path = Path::new();
+    let mut command: Option<u8> = None;
+    // last control point of the previous curve, for the reflected S and T commands
+    let mut cubic_control: Option<Vec2> = None;
+    let mut quad_control: Option<Vec2> = None;
+
+    loop {
+        scanner.skip_separators();
+        let Some(next) = scanner.peek() else {
+            break;
+        };
+        if next.is_ascii_alphabetic() {
+            scanner.pos += 1;
+            if command.is_none() && !matches!(next, b'M' | b'm') {
+                return Err(Error::InvalidData("SVG path data must start with a moveto".to_string()));
+            }
+            command = Some(next);
+            if matches!(next, b'Z' | b'z') {
+                path.close();
+                (cubic_control, quad_control) = (None, None);
+                continue;
+            }
+        }
+        let Some(current_command) = command else {
+            return Err(Error::InvalidData("SVG path data must start with a moveto".to_string()));
+        };
+
+        let current = path.current_point().unwrap_or_else(Vec2::zeros);
+        let origin = if current_command.is_ascii_lowercase() { current } else { Vec2::zeros() };
+        let (mut next_cubic, mut next_quad) = (None, None);
+        match current_command.to_ascii_uppercase() {
+            b'M' => {
+                let to = origin + scanner.point()?;
+                path.move_to(to.x, to.y);
+                // further coordinate pairs are implicit lineto commands
+                command = Some(if current_command == b'm' { b'l' } else { b'L' });
+            }
+            b'L' => {
+                let to = origin + scanner.point()?;
+                path.line_to(to.x, to.y);
+            }
+            b'H' => {
+                let x = origin.x + scanner.number()?;
+                path.line_to(x, current.y);
+            }
+            b'V' => {
+                let y = origin.y + scanner.number()?;
+                path.line_to(current.x, y);
+            }
+            b'C' | b'S' => {
+                let c1 = if current_command.eq_ignore_ascii_case(&b'C') {
+                    origin + scanner.point()?
+                } else {
+                    cubic_control.map_or(current, |control| current * 2.0 - control)
+                };
+                let c2 = origin + scanner.point()?;
+                let to = origin + scanner.point()?;
+                path.cubic_to(c1.x, c1.y, c2.x, c2.y, to.x, to.y);
+                next_cubic = Some(c2);
+            }
+            b'Q' | b'T' => {
+                let control = if current_command.eq_ignore_ascii_case(&b'Q') {
+                    origin + scanner.point()?
+                } else {
+                    quad_control.map_or(current, |control| current * 2.0 - control)
+                };
+                let to = origin + scanner.point()?;
+                path.quad_to(control.x, control.y, to.x, to.y);
+                next_quad = Some(control);
+            }
+            b'A' => {
+                let rx = scanner.number()?;
+                let ry = scanner.number()?;
+                let rotation = scanner.number()?.to_radians();
+                let large_arc = scanner.flag()?;
+                let sweep = scanner.flag()?;
+                let to = origin + scanner.point()?;
+                path.arc_to(rx, ry, rotation, large_arc, sweep, to.x, to.y);
+            }
+            b'Z' => {
+                return Err(Error::InvalidData("unexpected number after closepath in SVG path data".to_string()));
+            }
+            other => {
+                return Err(Error::InvalidData(format!("unknown SVG path command '{}'", other as char)));
+            }
+        }
+        (cubic_control, quad_control) = (next_cubic, next_quad);
+    }
+    Ok(path)
+}
+
+// Elements whose content is only referenced from elsewhere
+const NON_RENDERED: [&str; 8] = ["defs", "symbol", "clipPath", "mask", "marker", "pattern", "style", "title"];
+
+// The geometry of a shape element, or None for elements that draw nothing
+fn shape_path(tag: &Tag) -> Result<Option<Path>> {
+    let number = |name: &str| tag.attribute(name).map_or(Ok(0.0), length);
+    let mut path = Path::new();
+    match tag.name {
+        "path" => {
+            return tag.attribute("d").map(parse_path_data).transpose();
+        }
+        "polygon" | "polyline" => {
+            let coordinates = numbers(tag.attribute("points").unwrap_or(""))?;
+            for (i, point) in coordinates.chunks_exact(2).enumerate() {
+                if i == 0 {
+                    path.move_to(point[0], point[1]);
+                } else {
+                    path.line_to(point[0], point[1]);
+                }
+            }
+            if tag.name == "polygon" {
+                path.close();
+            }
+        }
+        "line" => {
+            path.move_to(number("x1")?, number("y1")?).line_to(number("x2")?, number("y2")?);
+        }
+        "rect" => {
+            let (x, y, width, height) = (number("x")?, number("y")?, number("width")?, number("height")?);
+            if width < 0.0 || height < 0.0 {
+                return Err(Error::InvalidData(format!("negative SVG rect size {}x{}", width, height)));
+            }
+            if width == 0.0 || height == 0.0 {
+                return Ok(None);
+            }
+            // a missing corner radius takes the other one
+            let rx = tag.attribute("rx").map(length).transpose()?;
+            let ry = tag.attribute("ry").map(length).transpose()?;
+            let rx = rx.or(ry).unwrap_or(0.0);
+            let ry = ry.unwrap_or(rx);
+            path.rounded_rect(x, y, width, height, rx, ry);
+        }
+        "circle" | "ellipse" => {
+            let (cx, cy) = (number("cx")?, number("cy")?);
+            let (rx, ry) = if tag.name == "circle" {
+                let r = number("r")?;
+                (r, r)
+            } else {
+                (number("rx")?, number("ry")?)
+            };
+            if rx < 0.0 || ry < 0.0 {
+                return Err(Error::InvalidData(format!("negative SVG {} radius", tag.name)));
+            }
+            if rx == 0.0 || ry == 0.0 {
+                return Ok(None);
+            }
+            path.ellipse(cx, cy, rx, ry);
+        }
+        _ => return Ok(None),
+    }
+    Ok(Some(path))
+}
+
+// Paint properties as they cascade from parent to child elements
+#[derive(Debug, Clone)]
+struct Presentation {
+    fill: Option<Color>,
+    fill_rule: FillRule,
+    stroke: Option<Color>,
+    stroke_style: StrokeStyle,
+    // what currentColor paints with
+    color: Color,
+    fill_opacity: f32,
+    stroke_opacity: f32,
+    opacity: f32,
+    transform: Mat3,
+}
+
+impl Default for Presentation {
+    fn default() -> Self {
+        // SVG paints shapes black and leaves them unstroked unless told otherwise
+        Presentation {
+            fill: Some(Color::from_hex(0x000000)),
+            fill_rule: FillRule::NonZero,
+            stroke: None,
+            stroke_style: StrokeStyle::default(),
+            color: Color::from_hex(0x000000),
+            fill_opacity: 1.0,
+            stroke_opacity: 1.0,
+            opacity: 1.0,
+            transform: transform::identity(),
+        }
+    }
+}
+
+impl Presentation {
+    // Applies the element's attributes, then its style declarations which take precedence
+    fn apply(&mut self, tag: &Tag) -> Result<()> {
+        // a group's opacity fades everything inside it
+        let inherited_opacity = self.opacity;
+        self.opacity = 1.0;
+        let mut declarations: Vec<(&str, &str)> = tag.attributes.iter().map(|(name, value)| (*name, value.as_str())).collect();
+        if let Some(style) = tag.attribute("style") {
+            for declaration in style.split(';') {
+                if let Some((name, value)) = declaration.split_once(':') {
+                    declarations.push((name.trim(), value.trim()));
+                }
+            }
+        }
+        // the color goes first so currentColor sees it wherever it is declared
+        for &(name, value) in declarations.iter().filter(|(name, _)| *name == "color") {
+            self.set(name, value)?;
+        }
+        for &(name, value) in declarations.iter().filter(|(name, _)| *name != "color") {
+            self.set(name, value)?;
+        }
+        self.opacity *= inherited_opacity;
+        // an element's transform applies inside its parent's
+        if let Some(value) = tag.attribute("transform") {
+            self.transform *= parse_transform(value)?;
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        let value = value.trim();
+        match name {
+            "fill" => self.fill = self.paint(value)?,
+            "stroke" => self.stroke = self.paint(value)?,
+            "color" => self.color = self.paint(value)?.unwrap_or(self.color),
+            "fill-rule" => {
+                self.fill_rule = match value {
+                    "evenodd" => FillRule::EvenOdd,
+                    _ => FillRule::NonZero,
+                }
+            }
+            "stroke-width" => self.stroke_style.width = length(value)?.max(0.0),
+            "stroke-linecap" => {
+                self.stroke_style.cap = match value {
+                    "round" => LineCap::Round,
+                    "square" => LineCap::Square,
+                    _ => LineCap::Butt,
+                }
+            }
+            "stroke-linejoin" => {
+                self.stroke_style.join = match value {
+                    "round" => LineJoin::Round,
+                    "bevel" => LineJoin::Bevel,
+                    _ => LineJoin::Miter,
+                }
+            }
+            "stroke-miterlimit" => self.stroke_style.miter_limit = number(value)?.max(1.0),
+            "fill-opacity" => self.fill_opacity = number(value)?.clamp(0.0, 1.0),
+            "stroke-opacity" => self.stroke_opacity = number(value)?.clamp(0.0, 1.0),
+            "opacity" => self.opacity = number(value)?.clamp(0.0, 1.0),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn paint(&self, value: &str) -> Result<Option<Color>> {
+        if value.eq_ignore_ascii_case("currentColor") {
+            return Ok(Some(self.color));
+        }
+        paint(value)
+    }
+
+    fn shape(&self, path: Path) -> SvgShape {
+        let fade = |color: Color, opacity: f32| color.with_alpha((color.a as f32 * opacity * self.opacity).round() as u8);
+        SvgShape {
+            path,
+            fill: self.fill.map(|color| fade(color, self.fill_opacity)),
+            fill_rule: self.fill_rule,
+            stroke: self.stroke.map(|color| fade(color, self.stroke_opacity)),
+            stroke_style: self.stroke_style,
+            transform: self.transform,
+        }
+    }
+}
+
+// Parses a transform list such as "translate(10 20) rotate(45)"; angles are in
+// degrees and the functions apply right to left
+pub(crate) fn parse_transform(value: &str) -> Result<Mat3> {
+    let invalid = || Error::InvalidData(format!("invalid SVG transform \"{}\"", value));
+    let separators = |c: char| c.is_whitespace() || c == ',';
+    let mut matrix = transform::identity();
+    let mut rest = value.trim_start_matches(separators);
+    while !rest.is_empty() {
+        let (name, tail) = rest.split_once('(').ok_or_else(invalid)?;
+        let (arguments, tail) = tail.split_once(')').ok_or_else(invalid)?;
+        matrix *= match (name.trim(), numbers(arguments)?.as_slice()) {
+            ("matrix", &[a, b, c, d, e, f]) => Mat3::new(a, c, e, b, d, f, 0.0, 0.0, 1.0),
+            ("translate", &[tx]) => translation(tx, 0.0),
+            ("translate", &[tx, ty]) => translation(tx, ty),
+            ("scale", &[factor]) => scaling(factor, factor),
+            ("scale", &[sx, sy]) => scaling(sx, sy),
+            ("rotate", &[angle]) => rotation(angle.to_radians()),
+            ("rotate", &[angle, cx, cy]) => translation(cx, cy) * rotation(angle.to_radians()) * translation(-cx, -cy),
+            ("skewX", &[angle]) => skewing(angle.to_radians(), 0.0),
+            ("skewY", &[angle]) => skewing(0.0, angle.to_radians()),
+            _ => return Err(invalid()),
+        };
+        rest = tail.trim_start_matches(separators);
+    }
+    Ok(matrix)
+}
+
+// Parses a paint value: "none", #rgb, #rrggbb, rgb(r, g, b) or a basic color keyword.
+// Gradients and patterns are not drawn: a url(...) paint uses the fallback after it, or none.
+pub(crate) fn paint(value: &str) -> Result<Option<Color>> {
+    let unsupported = || Error::UnsupportedFormat(format!("SVG paint \"{}\" is not supported", value));
+    if value == "none" {
+        return Ok(None);
+    }
+    if let Some(reference) = value.strip_prefix("url(") {
+        let (_, fallback) = reference.split_once(')').ok_or_else(unsupported)?;
+        let fallback = fallback.trim();
+        return if fallback.is_empty() { Ok(None) } else { paint(fallback) };
+    }
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits = u32::from_str_radix(hex, 16).map_err(|_| unsupported())?;
+        return match hex.len() {
+            3 => {
+                let (r, g, b) = ((digits >> 8) & 0xF, (digits >> 4) & 0xF, digits & 0xF);
+                Ok(Some(Color::new((r * 0x11) as i32, (g * 0x11) as i32, (b * 0x11) as i32)))
+            }
+            6 => Ok(Some(Color::from_hex(digits))),
+            _ => Err(unsupported()),
+        };
+    }
+    if let Some(arguments) = value.strip_prefix("rgb(").and_then(|rest| rest.strip_suffix(')')) {
+        let channels = arguments
+            .split(',')
+            .map(|channel| channel.trim().parse::<i32>())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| unsupported())?;
+        return match channels[..] {
+            [r, g, b] => Ok(Some(Color::new(r, g, b))),
+            _ => Err(unsupported()),
+        };
+    }
+
+    let hex = match value.to_ascii_lowercase().as_str() {
+        "black" => 0x000000,
+        "white" => 0xFFFFFF,
+        "red" => 0xFF0000,
+        "lime" => 0x00FF00,
+        "green" => 0x008000,
+        "blue" => 0x0000FF,
+        "yellow" => 0xFFFF00,
+        "cyan" | "aqua" => 0x00FFFF,
+        "magenta" | "fuchsia" => 0xFF00FF,
+        "gray" | "grey" => 0x808080,
+        "silver" => 0xC0C0C0,
+        "maroon" => 0x800000,
+        "olive" => 0x808000,
+        "navy" => 0x000080,
+        "purple" => 0x800080,
+        "teal" => 0x008080,
+        "orange" => 0xFFA500,
+        "transparent" => return Ok(Some(Color::new_rgba(0, 0, 0, 0))),
+        _ => return Err(unsupported()),
+    };
+    Ok(Some(Color::from_hex(hex)))
+}
+
+fn number(value: &str) -> Result<f32> {
+    let mut scanner = Scanner::new(value);
+    let number = scanner.number()?;
+    scanner.skip_separators();
+    if scanner.peek().is_some() {
+        return Err(Error::InvalidData(format!("invalid SVG number \"{}\"", value)));
+    }
+    Ok(number)
+}
+
+// A length in user units; only unitless and pixel lengths are supported
+fn length(value: &str) -> Result<f32> {
+    let value = value.trim();
+    let digits = value.strip_suffix("px").unwrap_or(value);
+    if digits.ends_with(|c: char| c.is_ascii_alphabetic() || c == '%') {
+        return Err(Error::UnsupportedFormat(format!("SVG length \"{}\" is not in pixels", value)));
+    }
+    number(digits)
+}
+
+// A list of numbers separated by whitespace and commas
+fn numbers(value: &str) -> Result<Vec<f32>> {
+    let mut scanner = Scanner::new(value);
+    let mut numbers = Vec::new();
+    loop {
+        scanner.skip_separators();
+        if scanner.peek().is_none() {
+            return Ok(numbers);
+        }
+        numbers.push(scanner.number()?);
+    }
+}
+
+// Reads the compact number syntax of SVG attributes, where "1-2.5.5" is 1, -2.5 and .5
+struct Scanner<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(text: &'a str) -> Self {
+        Scanner { data: text.as_bytes(), pos: 0 }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn skip_separators(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r' | b',')) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn number(&mut self) -> Result<f32> {
+        self.skip_separators();
+        let start = self.pos;
+        if matches!(self.peek(), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        let mut digits = self.skip_digits();
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            digits += self.skip_digits();
+        }
+        if digits == 0 {
+            self.pos = start;
+            return Err(Error::InvalidData(format!("expected a number in SVG data at offset {}", start)));
+        }
+        // only take the exponent if digits follow, so "2e" leaves the "e" alone
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            let mantissa_end = self.pos;
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if self.skip_digits() == 0 {
+                self.pos = mantissa_end;
+            }
+        }
+        let text = std::str::from_utf8(&self.data[start..self.pos]).expect("numbers are ASCII");
+        text.parse().map_err(|_| Error::InvalidData(format!("invalid number {} in SVG data", text)))
+    }
+
+    fn point(&mut self) -> Result<Vec2> {
+        Ok(Vec2::new(self.number()?, self.number()?))
+    }
+
+    // Arc flags are a single digit and may be written without separators
+    fn flag(&mut self) -> Result<bool> {
+        self.skip_separators();
+        let flag = match self.peek() {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err(Error::InvalidData("expected an arc flag in SVG path data".to_string())),
+        };
+        self.pos += 1;
+        Ok(flag)
+    }
+}
+
+// A start or end tag of the XML document
+#[derive(Debug)]
+struct Tag<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, String)>,
+    closing: bool,
+    self_closing: bool,
+}
+
+impl Tag<'_> {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| *key == name).map(|(_, value)| value.as_str())
+    }
+}
+
+// Splits the document into tags, skipping text, comments, declarations and CDATA.
+// Namespace prefixes are dropped from element names.
+fn parse_tags(text: &str) -> Result<Vec<Tag<'_>>> {
+    let unterminated = || Error::InvalidData("unterminated markup in SVG document".to_string());
+    let mut tags = Vec::new();
+    let mut rest = text;
+
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open..];
+        let skip_to = |end: &str| rest.find(end).map(|i| i + end.len()).ok_or_else(unterminated);
+        if rest.starts_with("<!--") {
+            rest = &rest[skip_to("-->")?..];
+        } else if rest.starts_with("<![CDATA[") {
+            rest = &rest[skip_to("]]>")?..];
+        } else if rest.starts_with("<?") {
+            rest = &rest[skip_to("?>")?..];
+        } else if rest.starts_with("<!") {
+            rest = &rest[skip_to(">")?..];
+        } else {
+            let (tag, consumed) = parse_tag(rest)?;
+            tags.push(tag);
+            rest = &rest[consumed..];
+        }
+    }
+    Ok(tags)
+}
+
+// Parses one tag at the start of `text`, returning it and its length in bytes
+fn parse_tag(text: &str) -> Result<(Tag<'_>, usize)> {
+    let invalid = |message: &str| Error::InvalidData(format!("{} in SVG document", message));
+    let bytes = text.as_bytes();
+    let mut pos = 1;
+    let closing = bytes.get(pos) == Some(&b'/');
+    if closing {
+        pos += 1;
+    }
+
+    let is_name = |c: u8| c.is_ascii_alphanumeric() || matches!(c, b'_' | b'-' | b':' | b'.');
+    let read_name = |pos: &mut usize| {
+        let start = *pos;
+        while bytes.get(*pos).is_some_and(|&c| is_name(c)) {
+            *pos += 1;
+        }
+        &text[start..*pos]
+    };
+    let skip_whitespace = |pos: &mut usize| {
+        while bytes.get(*pos).is_some_and(|c| c.is_ascii_whitespace()) {
+            *pos += 1;
+        }
+    };
+
+    let qualified = read_name(&mut pos);
+    if qualified.is_empty() {
+        return Err(invalid("missing element name"));
+    }
+    let name = qualified.rsplit(':').next().unwrap_or(qualified);
+
+    let mut attributes = Vec::new();
+    loop {
+        skip_whitespace(&mut pos);
+        match bytes.get(pos) {
+            None => return Err(invalid("unterminated tag")),
+            Some(b'>') => {
+                let tag = Tag { name, attributes, closing, self_closing: false };
+                return Ok((tag, pos + 1));
+            }
+            Some(b'/') if bytes.get(pos + 1) == Some(&b'>') => {
+                let tag = Tag { name, attributes, closing, self_closing: true };
+                return Ok((tag, pos + 2));
+            }
+            Some(_) => {}
+        }
+
+        let key = read_name(&mut pos);
+        if key.is_empty() {
+            return Err(invalid("malformed attribute"));
+        }
+        skip_whitespace(&mut pos);
+        if bytes.get(pos) != Some(&b'=') {
+            return Err(invalid(&format!("attribute {} has no value", key)));
+        }
+        pos += 1;
+        skip_whitespace(&mut pos);
+        let quote = match bytes.get(pos) {
+            Some(&quote @ (b'"' | b'\'')) => quote,
+            _ => return Err(invalid(&format!("attribute {} is not quoted", key))),
+        };
+        let start = pos + 1;
+        let end = bytes[start..].iter().position(|&c| c == quote).ok_or_else(|| invalid("unterminated attribute"))? + start;
+        attributes.push((key, unescape(&text[start..end])));
+        pos = end + 1;
+    }
+}
+
+// Decodes the predefined XML entities
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::PathSegment;
+
+    fn points(path: &Path) -> Vec<(f32, f32)> {
+        path.segments()
+            .iter()
+            .filter_map(|segment| match *segment {
+                PathSegment::MoveTo(p) | PathSegment::LineTo(p) => Some((p.x, p.y)),
+                PathSegment::QuadTo(_, p) | PathSegment::CubicTo(_, _, p) => Some((p.x, p.y)),
+                PathSegment::Close => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_path_data_lines() {
+        let path = parse_path_data("M10,20 L30 20 h5 v-10 H0 V1e1 z m1-2 3.5.5 l-1-1").unwrap();
+        assert_eq!(
+            points(&path),
+            vec![(10.0, 20.0), (30.0, 20.0), (35.0, 20.0), (35.0, 10.0), (0.0, 10.0), (0.0, 10.0), (11.0, 18.0), (14.5, 18.5), (13.5, 17.5)]
+        );
+        assert!(path.segments().contains(&PathSegment::Close));
+    }
+
+    #[test]
+    fn test_path_data_curves() {
+        let path = parse_path_data("M0 0 C10 0 20 10 20 20 S30 40 40 40 Q50 40 50 50 t10 10").unwrap();
+        let segments = path.segments();
+        assert_eq!(segments[2], PathSegment::CubicTo(Vec2::new(20.0, 30.0), Vec2::new(30.0, 40.0), Vec2::new(40.0, 40.0)));
+        assert_eq!(segments[3], PathSegment::QuadTo(Vec2::new(50.0, 40.0), Vec2::new(50.0, 50.0)));
+        assert_eq!(segments[4], PathSegment::QuadTo(Vec2::new(50.0, 60.0), Vec2::new(60.0, 60.0)));
+
+        // S without a preceding cubic uses the current point as its first control
+        let path = parse_path_data("M5 5 s10 0 10 10").unwrap();
+        assert_eq!(path.segments()[1], PathSegment::CubicTo(Vec2::new(5.0, 5.0), Vec2::new(15.0, 5.0), Vec2::new(15.0, 15.0)));
+    }
+
+    #[test]
+    fn test_path_data_arcs() {
+        // packed flags: large arc 0, sweep 1, then the relative end point
+        let path = parse_path_data("M0 0a10 10 0 0120 0").unwrap();
+        let end = path.current_point().unwrap();
+        assert!((end - Vec2::new(20.0, 0.0)).norm() < 1e-4);
+        let lowest = path.flatten(0.1)[0].vertices.iter().map(|v| v.y).fold(f32::MAX, f32::min);
+        assert!((lowest + 10.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_path_data_errors() {
+        assert!(matches!(parse_path_data("L10 10"), Err(Error::InvalidData(_))));
+        assert!(matches!(parse_path_data("M10"), Err(Error::InvalidData(_))));
+        assert!(matches!(parse_path_data("M0 0 X5"), Err(Error::InvalidData(_))));
+        assert!(matches!(parse_path_data("M0 0 A5 5 0 2 0 10 10"), Err(Error::InvalidData(_))));
+        assert!(parse_path_data("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_document() {
+        let svg = parse_svg(
+            r##"<?xml version="1.0"?>
+            <!-- exported -->
+            <svg xmlns="http://www.w3.org/2000/svg" width="40px" height="30" viewBox="0 0 40 30">
+              <defs><rect width="5" height="5"/></defs>
+              <g fill="#f00" stroke="blue" stroke-width="3">
+                <rect x="1" y="2" width="10" height="5" rx="2"/>
+                <circle cx="20" cy="15" r="4" style="fill: none; stroke-linecap: round"/>
+                <polygon points="0,0 10,0 10,10" fill-rule="evenodd" fill-opacity=".5"/>
+              </g>
+              <path d="M0 0 L5 5" fill="rgb(0, 128, 255)"/>
+            </svg>"##,
+        )
+        .unwrap();
+        assert_eq!(svg.size().unwrap(), Some((40, 30)));
+        assert_eq!(svg.shapes.len(), 4);
+
+        let rect = &svg.shapes[0];
+        assert_eq!(rect.fill, Some(Color::from_hex(0xFF0000)));
+        assert_eq!(rect.stroke, Some(Color::from_hex(0x0000FF)));
+        assert_eq!(rect.stroke_style.width, 3.0);
+        assert_eq!(svg.shapes[1].fill, None);
+        assert_eq!(svg.shapes[1].stroke_style.cap, LineCap::Round);
+        assert_eq!(svg.shapes[2].fill_rule, FillRule::EvenOdd);
+        assert_eq!(svg.shapes[2].fill, Some(Color::new_rgba(255, 0, 0, 128)));
+        // outside the group the defaults apply again
+        assert_eq!(svg.shapes[3].fill, Some(Color::from_hex(0x0080FF)));
+        assert_eq!(svg.shapes[3].stroke, None);
+    }
+
+    #[test]
+    fn test_document_errors() {
+        assert!(matches!(parse_svg("<html></html>"), Err(Error::UnsupportedFormat(_))));
+        assert!(matches!(parse_svg("<svg><path d='M0 0'"), Err(Error::InvalidData(_))));
+        assert!(matches!(parse_svg("<svg><rect width=5/></svg>"), Err(Error::InvalidData(_))));
+        assert!(matches!(parse_svg("<svg><rect width='5em'/></svg>"), Err(Error::UnsupportedFormat(_))));
+        assert!(matches!(parse_svg("<svg><circle r='3' fill='chartreuse'/></svg>"), Err(Error::UnsupportedFormat(_))));
+
+        // sizes are only checked when asked for, as the shapes may still be of use
+        assert_eq!(parse_svg("<svg></svg>").unwrap().size().unwrap(), None);
+        for size in ["width='1e30' height='10'", "width='0.5' height='0'", "width='20000' height='10'"] {
+            let svg = parse_svg(&format!("<svg {}></svg>", size)).unwrap();
+            assert!(matches!(svg.size(), Err(Error::InvalidDimensions { .. })), "{}", size);
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let svg = parse_svg(
+            r##"<svg width="20" height="20">
+              <rect x="2" y="2" width="16" height="16" fill="#00f"/>
+              <path d="M6 6 h8 v8 h-8 z" fill="white" stroke="red"/>
+            </svg>"##,
+        )
+        .unwrap();
+        let (width, height) = svg.size().unwrap().unwrap();
+        let mut fb = Framebuffer::new(width, height);
+        svg.render(&mut fb).unwrap();
+        assert_eq!(fb.get_point(3, 3), Some(Color::from_hex(0x0000FF)));
+        assert_eq!(fb.get_point(10, 10), Some(Color::from_hex(0xFFFFFF)));
+        assert_eq!(fb.get_point(6, 10), Some(Color::from_hex(0xFF0000)));
+        assert_eq!(fb.get_point(0, 0), Some(Color::from_hex(0x000000)));
+        assert_eq!(fb.stroke_style(), StrokeStyle::default());
+    }
+
+    #[test]
+    fn test_paint_fallbacks() {
+        let svg = parse_svg(
+            r##"<svg width="10" height="10">
+              <defs><linearGradient id="g"><stop offset="0" stop-color="red"/></linearGradient></defs>
+              <rect width="10" height="10" fill="url(#g)" stroke="url('#g') #0f0"/>
+              <g color="blue"><circle r="3" style="fill: currentColor; color: red" stroke="currentcolor"/></g>
+              <circle r="3" fill="currentColor" stroke="url(#g) none"/>
+            </svg>"##,
+        )
+        .unwrap();
+        assert_eq!((svg.shapes[0].fill, svg.shapes[0].stroke), (None, Some(Color::from_hex(0x00FF00))));
+        assert_eq!((svg.shapes[1].fill, svg.shapes[1].stroke), (Some(Color::from_hex(0xFF0000)), Some(Color::from_hex(0xFF0000))));
+        assert_eq!((svg.shapes[2].fill, svg.shapes[2].stroke), (Some(Color::from_hex(0x000000)), None));
+
+        // the gradient does not keep the rest of the document from rendering
+        let mut fb = Framebuffer::new(10, 10);
+        svg.render(&mut fb).unwrap();
+        assert_eq!(fb.get_point(0, 9), Some(Color::from_hex(0x00FF00)));
+        assert!(matches!(parse_svg("<svg><rect fill='url(#g'/></svg>"), Err(Error::UnsupportedFormat(_))));
+    }
+
+    #[test]
+    fn test_render_far_off_lines() {
+        // only the part of the line on the canvas is walked
+        let svg = parse_svg(r#"<svg width="20" height="20"><line x1="0" y1="0" x2="1e15" y2="3" stroke="red"/></svg>"#).unwrap();
+        let mut fb = Framebuffer::new(20, 20);
+        svg.render(&mut fb).unwrap();
+        assert_eq!(fb.get_point(19, 0), Some(Color::from_hex(0xFF0000)));
+    }
+
+    #[test]
+    fn test_transforms() {
+        let point = |matrix: Mat3| transform::transform_vec2(&matrix, Vec2::new(1.0, 0.0));
+        assert_eq!(point(parse_transform("translate(10, 5) scale(2)").unwrap()), Vec2::new(12.0, 5.0));
+        assert_eq!(point(parse_transform("matrix(1 0 0 1 3 4)").unwrap()), Vec2::new(4.0, 4.0));
+        let turned = point(parse_transform("rotate(90 1 1)").unwrap());
+        assert!((turned - Vec2::new(2.0, 1.0)).norm() < 1e-5, "{:?}", turned);
+        for value in ["translate(1", "spin(3)", "scale(1 2 3)", "translate(a)"] {
+            assert!(parse_transform(value).is_err(), "{:?} should be rejected", value);
+        }
+
+        // a group transform wraps the element's own
+        let svg = parse_svg(
+            r##"<svg width="30" height="20">
+              <g transform="translate(10)">
+                <rect width="4" height="4" transform="scale(2)" fill="red"/>
+              </g>
+            </svg>"##,
+        )
+        .unwrap();
+        let mut fb = Framebuffer::new(30, 20);
+        svg.render(&mut fb).unwrap();
+        assert_eq!(fb.get_point(10, 0), Some(Color::from_hex(0xFF0000)));
+        assert_eq!(fb.get_point(17, 7), Some(Color::from_hex(0xFF0000)));
+        assert_eq!(fb.get_point(9, 0), Some(Color::from_hex(0x000000)));
+        assert_eq!(fb.get_point(18, 8), Some(Color::from_hex(0x000000)));
+        assert_eq!(fb.transform(), transform::identity());
+    }
+}
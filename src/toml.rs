@@ -0,0 +1,489 @@
+//! A reader for the subset of TOML used by scene files: tables, arrays of
+//! tables, dotted keys, basic and literal strings, integers, floats, booleans,
+//! arrays and inline tables. Multi-line strings and dates are not supported.
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+/// Key-value pairs in document order.
+pub type Table = Vec<(String, Value)>;
+
+// Deepest nesting of arrays and inline tables, well within the stack
+const MAX_DEPTH: usize = 128;
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Integers are accepted wherever a float is expected.
+    pub fn as_float(&self) -> Option<f64> {
+        match *self {
+            Value::Integer(value) => Some(value as f64),
+            Value::Float(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match *self {
+            Value::Integer(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Boolean(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_table(&self) -> Option<&Table> {
+        match self {
+            Value::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Integer(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Boolean(_) => "boolean",
+            Value::Array(_) => "array",
+            Value::Table(_) => "table",
+        }
+    }
+}
+
+/// Looks up a key in a table.
+pub fn get<'a>(table: &'a Table, key: &str) -> Option<&'a Value> {
+    table.iter().find(|(name, _)| name == key).map(|(_, value)| value)
+}
+
+pub fn parse(text: &str) -> Result<Table> {
+    let mut parser = Parser { chars: text.chars().collect(), pos: 0, line: 1, depth: 0 };
+    let mut root = Table::new();
+    // header of the table that key-value pairs currently go into
+    let mut current: Vec<String> = Vec::new();
+
+    loop {
+        parser.skip_blank_lines();
+        match parser.peek() {
+            None => return Ok(root),
+            Some('[') => {
+                parser.pos += 1;
+                let array = parser.eat('[');
+                let path = parser.key()?;
+                parser.expect(']')?;
+                if array {
+                    parser.expect(']')?;
+                }
+                parser.end_of_line()?;
+
+                let (last, parents) = path.split_last().expect("keys are never empty");
+                let parent = parser.descend(&mut root, parents)?;
+                let existing = parent.iter().position(|(name, _)| name == last);
+                match (array, existing) {
+                    (true, None) => parent.push((last.clone(), Value::Array(vec![Value::Table(Table::new())]))),
+                    (false, None) => parent.push((last.clone(), Value::Table(Table::new()))),
+                    (true, Some(index)) => match &mut parent[index].1 {
+                        Value::Array(tables) if tables.iter().all(|t| matches!(t, Value::Table(_))) => {
+                            tables.push(Value::Table(Table::new()));
+                        }
+                        _ => return Err(parser.error(&format!("{} is not an array of tables", path.join(".")))),
+                    },
+                    (false, Some(_)) => return Err(parser.error(&format!("table {} is defined twice", path.join(".")))),
+                }
+                current = path;
+            }
+            Some(_) => {
+                let path = parser.key()?;
+                parser.skip_whitespace();
+                parser.expect('=')?;
+                let value = parser.value()?;
+                parser.end_of_line()?;
+
+                let (last, parents) = path.split_last().expect("keys are never empty");
+                let mut full_path = current.clone();
+                full_path.extend_from_slice(parents);
+                let table = parser.descend(&mut root, &full_path)?;
+                if get(table, last).is_some() {
+                    return Err(parser.error(&format!("key {} is defined twice", last)));
+                }
+                table.push((last.clone(), value));
+            }
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    // arrays and inline tables the current value is inside of
+    depth: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> Error {
+        Error::InvalidData(format!("TOML line {}: {}", self.line, message))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_whitespace();
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", expected)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while self.peek().is_some_and(|c| c != '\n') {
+                self.pos += 1;
+            }
+        }
+    }
+
+    // Skips whitespace, comments and newlines, as allowed between lines and inside arrays
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.skip_whitespace();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n') => self.line += 1,
+                Some('\r') => {}
+                _ => return,
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<()> {
+        self.skip_whitespace();
+        self.skip_comment();
+        match self.peek() {
+            None | Some('\n') | Some('\r') => Ok(()),
+            Some(c) => Err(self.error(&format!("unexpected '{}' after value", c))),
+        }
+    }
+
+    // A possibly dotted key of bare or quoted parts
+    fn key(&mut self) -> Result<Vec<String>> {
+        let mut parts = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let part = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let start = self.pos;
+                    while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                        self.pos += 1;
+                    }
+                    if start == self.pos {
+                        return Err(self.error("expected a key"));
+                    }
+                    self.chars[start..self.pos].iter().collect()
+                }
+            };
+            parts.push(part);
+            self.skip_whitespace();
+            if !self.eat('.') {
+                return Ok(parts);
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"') => Ok(Value::String(self.basic_string()?)),
+            Some('\'') => Ok(Value::String(self.literal_string()?)),
+            Some('[') => self.nested(Self::array),
+            Some('{') => self.nested(Self::inline_table),
+            Some(_) => self.scalar(),
+            None => Err(self.error("expected a value")),
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Value>) -> Result<Value> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("values are nested too deeply"));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn array(&mut self) -> Result<Value> {
+        self.pos += 1;
+        let mut values = Vec::new();
+        loop {
+            self.skip_blank_lines();
+            if self.eat(']') {
+                return Ok(Value::Array(values));
+            }
+            values.push(self.value()?);
+            self.skip_blank_lines();
+            if !self.eat(',') {
+                self.skip_blank_lines();
+                self.expect(']')?;
+                return Ok(Value::Array(values));
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value> {
+        self.pos += 1;
+        let mut table = Table::new();
+        self.skip_whitespace();
+        if self.eat('}') {
+            return Ok(Value::Table(table));
+        }
+        loop {
+            let path = self.key()?;
+            self.expect('=')?;
+            let value = self.value()?;
+            let (last, parents) = path.split_last().expect("keys are never empty");
+            let target = self.descend(&mut table, parents)?;
+            if get(target, last).is_some() {
+                return Err(self.error(&format!("key {} is defined twice", last)));
+            }
+            target.push((last.clone(), value));
+
+            self.skip_whitespace();
+            if !self.eat(',') {
+                self.expect('}')?;
+                return Ok(Value::Table(table));
+            }
+        }
+    }
+
+    // Integers, floats and booleans
+    fn scalar(&mut self) -> Result<Value> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.' | '_')) {
+            self.pos += 1;
+        }
+        let word: String = self.chars[start..self.pos].iter().collect();
+        match word.as_str() {
+            "true" => return Ok(Value::Boolean(true)),
+            "false" => return Ok(Value::Boolean(false)),
+            _ => {}
+        }
+
+        let invalid = || self.error(&format!("invalid value '{}'", word));
+        let digits = word.replace('_', "");
+        if !digits.trim_start_matches(['+', '-']).starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        if digits.contains(['.', 'e', 'E']) {
+            digits.parse().map(Value::Float).map_err(|_| invalid())
+        } else {
+            digits.parse().map(Value::Integer).map_err(|_| invalid())
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String> {
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            let c = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += 1;
+            match c {
+                '"' => return Ok(text),
+                '\n' => return Err(self.error("unterminated string")),
+                '\\' => {
+                    let escape = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    text.push(match escape {
+                        '"' => '"',
+                        '\\' => '\\',
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        'u' => {
+                            let hex: String = self.chars.get(self.pos..self.pos + 4).unwrap_or(&[]).iter().collect();
+                            self.pos += 4;
+                            u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("invalid unicode escape"))?
+                        }
+                        other => return Err(self.error(&format!("unknown escape '\\{}'", other))),
+                    });
+                }
+                _ => text.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String> {
+        self.pos += 1;
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c != '\'' && c != '\n') {
+            self.pos += 1;
+        }
+        if !self.eat('\'') {
+            return Err(self.error("unterminated string"));
+        }
+        Ok(self.chars[start..self.pos - 1].iter().collect())
+    }
+
+    // Walks down the tables named by `path`, creating missing ones. An array of
+    // tables stands for its last element.
+    fn descend<'a>(&self, mut table: &'a mut Table, path: &[String]) -> Result<&'a mut Table> {
+        for key in path {
+            let index = match table.iter().position(|(name, _)| name == key) {
+                Some(index) => index,
+                None => {
+                    table.push((key.clone(), Value::Table(Table::new())));
+                    table.len() - 1
+                }
+            };
+            table = match &mut table[index].1 {
+                Value::Table(inner) => inner,
+                Value::Array(values) => match values.last_mut() {
+                    Some(Value::Table(inner)) => inner,
+                    _ => return Err(self.error(&format!("{} is not a table", key))),
+                },
+                other => return Err(self.error(&format!("{} is a {}, not a table", key, other.type_name()))),
+            };
+        }
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values() {
+        let table = parse(
+            r#"
+            # a comment
+            name = "Scene \"one\"\t\u00e9"  # trailing comment
+            path = 'C:\images'
+            count = 1_000
+            negative = -3
+            ratio = 0.5
+            large = 1e3
+            enabled = true
+            points = [
+                [1, 2],   # first
+                [3.5, 4],
+            ]
+            style = { color = "red", width = 2 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(get(&table, "name").unwrap().as_str(), Some("Scene \"one\"\té"));
+        assert_eq!(get(&table, "path").unwrap().as_str(), Some("C:\\images"));
+        assert_eq!(get(&table, "count").unwrap().as_integer(), Some(1000));
+        assert_eq!(get(&table, "negative").unwrap().as_float(), Some(-3.0));
+        assert_eq!(get(&table, "ratio").unwrap().as_float(), Some(0.5));
+        assert_eq!(get(&table, "large").unwrap().as_float(), Some(1000.0));
+        assert_eq!(get(&table, "enabled").unwrap().as_bool(), Some(true));
+        let points = get(&table, "points").unwrap().as_array().unwrap();
+        assert_eq!(points[1], Value::Array(vec![Value::Float(3.5), Value::Integer(4)]));
+        let style = get(&table, "style").unwrap().as_table().unwrap();
+        assert_eq!(get(style, "width"), Some(&Value::Integer(2)));
+    }
+
+    #[test]
+    fn test_tables() {
+        let table = parse(
+            r#"
+            [canvas]
+            width = 800
+            size.height = 600
+
+            [[shapes]]
+            type = "rect"
+            [shapes.stroke]
+            color = "blue"
+
+            [[shapes]]
+            type = "circle"
+            "#,
+        )
+        .unwrap();
+        let canvas = get(&table, "canvas").unwrap().as_table().unwrap();
+        assert_eq!(get(canvas, "width"), Some(&Value::Integer(800)));
+        assert_eq!(get(get(canvas, "size").unwrap().as_table().unwrap(), "height"), Some(&Value::Integer(600)));
+
+        let shapes = get(&table, "shapes").unwrap().as_array().unwrap();
+        assert_eq!(shapes.len(), 2);
+        let first = shapes[0].as_table().unwrap();
+        assert_eq!(get(first, "type").unwrap().as_str(), Some("rect"));
+        assert!(get(first, "stroke").unwrap().as_table().is_some());
+        assert_eq!(get(shapes[1].as_table().unwrap(), "stroke"), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        for text in [
+            "a = ",
+            "a = 1\na = 2",
+            "[t]\n[t]",
+            "a = \"unterminated",
+            "a = [1, 2",
+            "a = 1 b = 2",
+            "a = nope",
+            "a = 1979-05-27",
+            "a = 1\n[a]",
+        ] {
+            assert!(matches!(parse(text), Err(Error::InvalidData(_))), "{:?} should not parse", text);
+        }
+
+        let nested = |depth: usize| format!("a = {}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        let error = parse(&nested(200_000)).unwrap_err();
+        assert!(error.to_string().contains("nested too deeply"), "{}", error);
+    }
+}
@@ -0,0 +1,88 @@
+//! 2D affine transforms as 3x3 matrices acting on homogeneous points (x, y, 1).
+//!
+//! Composing with `a * b` applies `b` first, so a transform built up from
+//! `translation(..) * rotation(..)` rotates a shape about its own origin and then
+//! moves it into place.
+
+use nalgebra_glm::{self as glm, Mat3, Vec2, Vec3};
+
+pub fn identity() -> Mat3 {
+    Mat3::identity()
+}
+
+pub fn translation(tx: f32, ty: f32) -> Mat3 {
+    glm::translation2d(&Vec2::new(tx, ty))
+}
+
+/// A rotation about the origin by `angle` radians, turning the positive x axis
+/// towards positive y (clockwise on screen, like the arc angles in `shapes`).
+pub fn rotation(angle: f32) -> Mat3 {
+    glm::rotation2d(angle)
+}
+
+pub fn scaling(sx: f32, sy: f32) -> Mat3 {
+    glm::scaling2d(&Vec2::new(sx, sy))
+}
+
+/// A skew that tilts vertical lines by `x_angle` and horizontal lines by
+/// `y_angle`, both in radians, like SVG's `skewX` and `skewY`.
+pub fn skewing(x_angle: f32, y_angle: f32) -> Mat3 {
+    Mat3::new(1.0, x_angle.tan(), 0.0, y_angle.tan(), 1.0, 0.0, 0.0, 0.0, 1.0)
+}
+
+/// Maps a point through the transform; the z coordinate is left untouched.
+pub fn transform_point(matrix: &Mat3, point: Vec3) -> Vec3 {
+    let mapped = matrix * Vec3::new(point.x, point.y, 1.0);
+    Vec3::new(mapped.x, mapped.y, point.z)
+}
+
+pub fn transform_vec2(matrix: &Mat3, point: Vec2) -> Vec2 {
+    let mapped = matrix * Vec3::new(point.x, point.y, 1.0);
+    Vec2::new(mapped.x, mapped.y)
+}
+
+/// The offset of a transform that only moves by whole pixels, if it is one.
+pub fn pixel_translation(matrix: &Mat3) -> Option<(isize, isize)> {
+    let (tx, ty) = (matrix[(0, 2)], matrix[(1, 2)]);
+    if translation(-tx, -ty) * matrix != Mat3::identity() || tx.fract() != 0.0 || ty.fract() != 0.0 {
+        return None;
+    }
+    Some((tx as isize, ty as isize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!((a - b).norm() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_basic_transforms() {
+        let point = Vec2::new(2.0, 1.0);
+        assert_near(transform_vec2(&translation(3.0, -1.0), point), Vec2::new(5.0, 0.0));
+        assert_near(transform_vec2(&scaling(2.0, -3.0), point), Vec2::new(4.0, -3.0));
+        // a quarter turn takes +x to +y
+        assert_near(transform_vec2(&rotation(FRAC_PI_2), point), Vec2::new(-1.0, 2.0));
+        assert_near(transform_vec2(&skewing(FRAC_PI_2 / 2.0, 0.0), point), Vec2::new(3.0, 1.0));
+        assert_near(transform_vec2(&skewing(0.0, FRAC_PI_2 / 2.0), point), Vec2::new(2.0, 3.0));
+        assert_eq!(transform_point(&translation(1.0, 1.0), Vec3::new(0.0, 0.0, 7.0)), Vec3::new(1.0, 1.0, 7.0));
+    }
+
+    #[test]
+    fn test_composition_applies_right_first() {
+        // rotate about the origin, then move
+        let matrix = translation(10.0, 0.0) * rotation(FRAC_PI_2);
+        assert_near(transform_vec2(&matrix, Vec2::new(1.0, 0.0)), Vec2::new(10.0, 1.0));
+    }
+
+    #[test]
+    fn test_pixel_translation() {
+        assert_eq!(pixel_translation(&identity()), Some((0, 0)));
+        assert_eq!(pixel_translation(&translation(3.0, -2.0)), Some((3, -2)));
+        assert_eq!(pixel_translation(&translation(0.5, 0.0)), None);
+        assert_eq!(pixel_translation(&scaling(2.0, 2.0)), None);
+    }
+}
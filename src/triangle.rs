@@ -0,0 +1,190 @@
+//! Depth-tested triangles, rasterized with barycentric edge functions.
+//!
+//! Vertices are in screen pixels with the depth in z, and pixel centers sit at
+//! integer coordinates as for every other shape. A pixel is covered when its
+//! center is inside the triangle; centers exactly on an edge follow the top-left
+//! rule, so the triangles of a mesh never overlap or leave gaps along shared edges.
+
+use nalgebra_glm::{Vec2, Vec3};
+
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+
+/// Calls `fragment(x, y, weights)` for every pixel of the `width` x `height` area
+/// covered by the triangle, where `weights` are the barycentric coordinates of
+/// the pixel center: the share of each vertex, summing to 1. Both windings are
+/// covered; degenerate triangles cover nothing.
+pub fn rasterize_triangle<F: FnMut(isize, isize, Vec3)>(width: usize, height: usize, vertices: &[Vec3; 3], mut fragment: F) {
+    let [a, b, c] = vertices.map(|vertex| vertex.xy());
+    let area = edge(a, b, c);
+    if area == 0.0 || !area.is_finite() {
+        return;
+    }
+    // with the winding flipped every edge function changes sign
+    let sign = area.signum();
+    let edges = [(b, c), (c, a), (a, b)];
+    let top_left = edges.map(|(from, to)| is_top_left((to - from) * sign));
+
+    let min_x = a.x.min(b.x).min(c.x).ceil().max(0.0);
+    let max_x = a.x.max(b.x).max(c.x).floor().min(width as f32 - 1.0);
+    let min_y = a.y.min(b.y).min(c.y).ceil().max(0.0);
+    let max_y = a.y.max(b.y).max(c.y).floor().min(height as f32 - 1.0);
+    if min_x > max_x || min_y > max_y {
+        return;
+    }
+
+    for y in min_y as isize..=max_y as isize {
+        for x in min_x as isize..=max_x as isize {
+            let center = Vec2::new(x as f32, y as f32);
+            // edge function i is twice the area of the sub-triangle opposite vertex i
+            let mut weights = Vec3::zeros();
+            let mut inside = true;
+            for (i, &(from, to)) in edges.iter().enumerate() {
+                let value = edge(from, to, center) * sign;
+                inside &= value > 0.0 || (value == 0.0 && top_left[i]);
+                weights[i] = value;
+            }
+            if inside {
+                fragment(x, y, weights / (area * sign));
+            }
+        }
+    }
+}
+
+/// Fills a triangle in one color, interpolating the vertex depths and drawing
+/// only the pixels that pass the framebuffer's depth test.
+pub fn fill_triangle(framebuffer: &mut Framebuffer, vertices: &[Vec3; 3], color: Color) {
+    let depths = Vec3::new(vertices[0].z, vertices[1].z, vertices[2].z);
+    rasterize_triangle(framebuffer.width, framebuffer.height, vertices, |x, y, weights| {
+        framebuffer.depth_point(x, y, weights.dot(&depths), color);
+    });
+}
+
+/// Fills a depth-tested triangle, blending the vertex colors across it.
+pub fn fill_triangle_colors(framebuffer: &mut Framebuffer, vertices: &[Vec3; 3], colors: &[Color; 3]) {
+    let depths = Vec3::new(vertices[0].z, vertices[1].z, vertices[2].z);
+    rasterize_triangle(framebuffer.width, framebuffer.height, vertices, |x, y, weights| {
+        framebuffer.depth_point(x, y, weights.dot(&depths), mix(colors, weights));
+    });
+}
+
+// The colors weighted by barycentric coordinates, alpha included
+fn mix(colors: &[Color; 3], weights: Vec3) -> Color {
+    let channel = |value: fn(&Color) -> u8| -> i32 {
+        colors.iter().zip(weights.iter()).map(|(color, weight)| value(color) as f32 * weight).sum::<f32>().round() as i32
+    };
+    Color::new_rgba(channel(|c| c.r), channel(|c| c.g), channel(|c| c.b), channel(|c| c.a))
+}
+
+// Twice the signed area of the triangle (from, to, point). The endpoints are
+// taken in a fixed order, so an edge shared by two triangles evaluates to
+// exactly opposite values and the top-left rule can split it without gaps
+fn edge(from: Vec2, to: Vec2, point: Vec2) -> f32 {
+    if (from.x, from.y) > (to.x, to.y) {
+        return -edge(to, from, point);
+    }
+    (to.x - from.x) * (point.y - from.y) - (to.y - from.y) * (point.x - from.x)
+}
+
+// For an edge direction on a triangle of positive area, with y pointing down:
+// top edges run horizontally to the right and left edges run upwards
+fn is_top_left(direction: Vec2) -> bool {
+    (direction.y == 0.0 && direction.x > 0.0) || direction.y < 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blend::BlendMode;
+    use crate::depth::DepthCompare;
+
+    fn covered(vertices: &[Vec3; 3]) -> Vec<(isize, isize)> {
+        let mut pixels = Vec::new();
+        rasterize_triangle(20, 20, vertices, |x, y, _| pixels.push((x, y)));
+        pixels
+    }
+
+    #[test]
+    fn test_coverage_and_winding() {
+        let a = Vec3::new(2.0, 2.0, 0.0);
+        let b = Vec3::new(12.0, 2.0, 0.0);
+        let c = Vec3::new(2.0, 12.0, 0.0);
+        let pixels = covered(&[a, b, c]);
+        // the top and left edges are in, the diagonal only where it is not on a center
+        assert!(pixels.contains(&(2, 2)) && pixels.contains(&(11, 2)) && pixels.contains(&(2, 11)));
+        assert!(!pixels.contains(&(12, 2)) && !pixels.contains(&(7, 7)) && pixels.contains(&(6, 7)));
+        assert_eq!(pixels.len(), 55);
+        assert_eq!(covered(&[a, c, b]), pixels);
+
+        assert!(covered(&[a, b, Vec3::new(22.0, 2.0, 0.0)]).is_empty());
+        assert!(covered(&[a, b, Vec3::new(f32::NAN, 5.0, 0.0)]).is_empty());
+        // clipped to the area
+        assert!(covered(&[Vec3::new(-30.0, -30.0, 0.0), Vec3::new(50.0, -30.0, 0.0), Vec3::new(-30.0, 50.0, 0.0)])
+            .iter()
+            .all(|&(x, y)| (0..20).contains(&x) && (0..20).contains(&y)));
+    }
+
+    #[test]
+    fn test_barycentric_weights() {
+        let vertices = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0), Vec3::new(0.0, 10.0, 0.0)];
+        rasterize_triangle(20, 20, &vertices, |x, y, weights| {
+            assert!((weights.sum() - 1.0).abs() < 1e-5);
+            let point = vertices[0] * weights.x + vertices[1] * weights.y + vertices[2] * weights.z;
+            assert!((point - Vec3::new(x as f32, y as f32, 0.0)).norm() < 1e-4);
+        });
+    }
+
+    #[test]
+    fn test_mesh_edges_tile_exactly() {
+        // a fan of sub-pixel triangles around a shared center, drawn additively
+        let mut fb = Framebuffer::new(30, 30);
+        fb.set_blend_mode(BlendMode::Additive);
+        fb.set_depth_compare(DepthCompare::Always);
+        let center = Vec3::new(14.3, 15.6, 0.0);
+        let rim = [(3.7, 2.2), (25.1, 4.4), (27.9, 20.3), (12.6, 27.5), (2.4, 17.8)].map(|(x, y)| Vec3::new(x, y, 0.0));
+        for i in 0..rim.len() {
+            fill_triangle(&mut fb, &[center, rim[i], rim[(i + 1) % rim.len()]], Color::from_hex(0x010101));
+        }
+        let mut whole = Framebuffer::new(30, 30);
+        crate::shapes::fill_polygon(&mut whole, &[rim.to_vec()], crate::shapes::FillRule::NonZero, Color::from_hex(0x010101)).unwrap();
+        for y in 0..30 {
+            for x in 0..30 {
+                assert_eq!(fb.get_point(x, y), whole.get_point(x, y), "pixel ({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_depth_tested_triangles() {
+        // two triangles crossing in depth: each shows where it is nearer
+        let red = Color::from_hex(0xFF0000);
+        let blue = Color::from_hex(0x0000FF);
+        let tilted = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(19.0, 0.0, 1.0), Vec3::new(0.0, 19.0, 0.0)];
+        let flat = [Vec3::new(0.0, 0.0, 0.5), Vec3::new(19.0, 0.0, 0.5), Vec3::new(0.0, 19.0, 0.5)];
+        for order in [[0, 1], [1, 0]] {
+            let mut fb = Framebuffer::new(20, 20);
+            for index in order {
+                if index == 0 {
+                    fill_triangle(&mut fb, &tilted, red);
+                } else {
+                    fill_triangle(&mut fb, &flat, blue);
+                }
+            }
+            assert_eq!(fb.get_point(3, 3), Some(red));
+            assert_eq!(fb.get_point(15, 1), Some(blue));
+            assert!((fb.get_depth(4, 2).unwrap() - 4.0 / 19.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_vertex_colors() {
+        let mut fb = Framebuffer::new(20, 20);
+        let vertices = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(19.0, 0.0, 0.0), Vec3::new(0.0, 19.0, 0.0)];
+        let colors = [Color::from_hex(0xFF0000), Color::from_hex(0x00FF00), Color::from_hex(0x0000FF)];
+        fill_triangle_colors(&mut fb, &vertices, &colors);
+        assert_eq!(fb.get_point(0, 0), Some(colors[0]));
+        assert_eq!(fb.get_point(0, 18), Some(Color::new(13, 0, 242)));
+        let middle = fb.get_point(6, 6).unwrap();
+        assert!(middle.r > 80 && middle.g > 70 && middle.b > 70, "{}", middle);
+    }
+}
@@ -0,0 +1,662 @@
+// Minimal zlib (RFC 1950) / DEFLATE (RFC 1951) compressor used by the PNG encoder.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+const WINDOW_SIZE: usize = 1 << 15;
+const WINDOW_MASK: usize = WINDOW_SIZE - 1;
+const HASH_BITS: usize = 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_STORED_BLOCK: usize = 65535;
+const TOKENS_PER_BLOCK: usize = 1 << 16;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+    131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// Order in which the code length code lengths are stored in a dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// How hard the compressor works; higher levels are slower but produce smaller output.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// No compression, data is copied into stored blocks.
+    Stored,
+    /// Short match searches, good for quick previews.
+    #[default]
+    Fast,
+    /// Long match searches with lazy matching.
+    Best,
+}
+
+impl Compression {
+    // (max hash chain length, length at which a match is good enough, lazy matching)
+    fn search_params(self) -> (usize, usize, bool) {
+        match self {
+            Compression::Stored => (0, 0, false),
+            Compression::Fast => (16, 32, false),
+            Compression::Best => (1024, MAX_MATCH, true),
+        }
+    }
+
+    // Value of the FLEVEL field in the zlib header
+    fn level_flag(self) -> u8 {
+        match self {
+            Compression::Stored => 0,
+            Compression::Fast => 1,
+            Compression::Best => 3,
+        }
+    }
+}
+
+/// Compresses `data` into a zlib stream.
+pub fn compress(data: &[u8], compression: Compression) -> Vec<u8> {
+    let mut out = BitWriter::new();
+
+    // CMF: deflate with a 32K window; FLG: level plus check bits
+    let cmf: u8 = 0x78;
+    let mut flg = compression.level_flag() << 6;
+    flg += 31 - (((cmf as u16) << 8 | flg as u16) % 31) as u8;
+    out.bytes.extend_from_slice(&[cmf, flg]);
+
+    match compression {
+        Compression::Stored => write_stored_blocks(&mut out, data),
+        _ => {
+            let tokens = find_matches(data, compression);
+            if tokens.is_empty() {
+                write_block(&mut out, &[], true);
+            }
+            let blocks = tokens.chunks(TOKENS_PER_BLOCK).count();
+            for (i, block) in tokens.chunks(TOKENS_PER_BLOCK).enumerate() {
+                write_block(&mut out, block, i + 1 == blocks);
+            }
+        }
+    }
+
+    out.flush();
+    out.bytes.extend_from_slice(&adler32(data).to_be_bytes());
+    out.bytes
+}
+
+/// Adler-32 checksum as used by the zlib trailer.
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the largest block that cannot overflow before the modulo
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        BitWriter { bytes: Vec::new(), bits: 0, count: 0 }
+    }
+
+    // Writes `count` bits of `value`, least significant bit first
+    fn write(&mut self, value: u32, count: u32) {
+        self.bits |= (value as u64) << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // Huffman codes are packed starting from their most significant bit
+    fn write_code(&mut self, code: u16, length: u8) {
+        let reversed = (code.reverse_bits() >> (16 - length as u32)) as u32;
+        self.write(reversed, length as u32);
+    }
+
+    // Pads to the next byte boundary
+    fn flush(&mut self) {
+        if self.count > 0 {
+            self.bytes.push(self.bits as u8);
+            self.bits = 0;
+            self.count = 0;
+        }
+    }
+}
+
+fn write_stored_blocks(out: &mut BitWriter, data: &[u8]) {
+    if data.is_empty() {
+        out.write(1, 3);
+        out.flush();
+        out.bytes.extend_from_slice(&[0x00, 0x00, 0xFF, 0xFF]);
+        return;
+    }
+
+    let blocks = data.len().div_ceil(MAX_STORED_BLOCK);
+    for (i, block) in data.chunks(MAX_STORED_BLOCK).enumerate() {
+        let last = i + 1 == blocks;
+        out.write(last as u32, 3);
+        out.flush();
+        let len = block.len() as u16;
+        out.bytes.extend_from_slice(&len.to_le_bytes());
+        out.bytes.extend_from_slice(&(!len).to_le_bytes());
+        out.bytes.extend_from_slice(block);
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Token {
+    Literal(u8),
+    Match { length: u16, distance: u16 },
+}
+
+fn hash(data: &[u8], pos: usize) -> usize {
+    let value = (data[pos] as u32) << 16 | (data[pos + 1] as u32) << 8 | data[pos + 2] as u32;
+    (value.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+}
+
+// LZ77 match finder using hash chains over a sliding 32K window
+fn find_matches(data: &[u8], compression: Compression) -> Vec<Token> {
+    let (max_chain, nice_length, lazy) = compression.search_params();
+    let mut head = vec![u32::MAX; 1 << HASH_BITS];
+    let mut prev = vec![u32::MAX; WINDOW_SIZE];
+    let mut tokens = Vec::with_capacity(data.len() / 2);
+
+    let insert = |head: &mut [u32], prev: &mut [u32], pos: usize| {
+        if pos + MIN_MATCH <= data.len() {
+            let h = hash(data, pos);
+            prev[pos & WINDOW_MASK] = head[h];
+            head[h] = pos as u32;
+        }
+    };
+
+    let longest_match = |head: &[u32], prev: &[u32], pos: usize| -> (usize, usize) {
+        if pos + MIN_MATCH > data.len() {
+            return (0, 0);
+        }
+        let max_length = MAX_MATCH.min(data.len() - pos);
+        let (mut best_length, mut best_distance) = (0, 0);
+        let mut candidate = head[hash(data, pos)];
+        let mut chain = max_chain;
+        while candidate != u32::MAX && chain > 0 {
+            let start = candidate as usize;
+            let distance = pos - start;
+            if distance > WINDOW_SIZE {
+                break;
+            }
+            let length = data[start..start + max_length]
+                .iter()
+                .zip(&data[pos..pos + max_length])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length > best_length {
+                best_length = length;
+                best_distance = distance;
+                if length >= nice_length.min(max_length) {
+                    break;
+                }
+            }
+            let next = prev[start & WINDOW_MASK];
+            // chains only ever point backwards; anything else is a stale slot
+            if next == u32::MAX || next as usize >= start {
+                break;
+            }
+            candidate = next;
+            chain -= 1;
+        }
+        (best_length, best_distance)
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let (length, distance) = longest_match(&head, &prev, pos);
+        insert(&mut head, &mut prev, pos);
+
+        if length < MIN_MATCH {
+            tokens.push(Token::Literal(data[pos]));
+            pos += 1;
+            continue;
+        }
+
+        // lazy matching: emit a literal if the next position has a longer match
+        if lazy && length < nice_length {
+            let (next_length, _) = longest_match(&head, &prev, pos + 1);
+            if next_length > length {
+                tokens.push(Token::Literal(data[pos]));
+                pos += 1;
+                continue;
+            }
+        }
+
+        tokens.push(Token::Match { length: length as u16, distance: distance as u16 });
+        for p in pos + 1..pos + length {
+            insert(&mut head, &mut prev, p);
+        }
+        pos += length;
+    }
+
+    tokens
+}
+
+fn length_symbol(length: u16) -> usize {
+    LENGTH_BASE.iter().rposition(|&base| base <= length).unwrap()
+}
+
+fn distance_symbol(distance: u16) -> usize {
+    DIST_BASE.iter().rposition(|&base| base <= distance).unwrap()
+}
+
+// Emits one block with dynamic Huffman codes built from the token frequencies
+fn write_block(out: &mut BitWriter, tokens: &[Token], last: bool) {
+    let mut lit_freq = [0u32; 286];
+    let mut dist_freq = [0u32; 30];
+    for token in tokens {
+        match *token {
+            Token::Literal(byte) => lit_freq[byte as usize] += 1,
+            Token::Match { length, distance } => {
+                lit_freq[257 + length_symbol(length)] += 1;
+                dist_freq[distance_symbol(distance)] += 1;
+            }
+        }
+    }
+    lit_freq[256] = 1;
+
+    let lit_lengths = code_lengths(&lit_freq, 15);
+    let dist_lengths = code_lengths(&dist_freq, 15);
+    let lit_codes = canonical_codes(&lit_lengths);
+    let dist_codes = canonical_codes(&dist_lengths);
+
+    let hlit = 257.max(lit_lengths.iter().rposition(|&l| l > 0).unwrap() + 1);
+    let hdist = 1.max(dist_lengths.iter().rposition(|&l| l > 0).map_or(0, |i| i + 1));
+
+    // run-length encode the concatenated code lengths with symbols 16, 17 and 18
+    let mut all_lengths = lit_lengths[..hlit].to_vec();
+    all_lengths.extend_from_slice(&dist_lengths[..hdist]);
+    let runs = encode_code_lengths(&all_lengths);
+
+    let mut cl_freq = [0u32; 19];
+    for &(symbol, _) in &runs {
+        cl_freq[symbol as usize] += 1;
+    }
+    let cl_lengths = code_lengths(&cl_freq, 7);
+    let cl_codes = canonical_codes(&cl_lengths);
+    let hclen = 4.max(CODE_LENGTH_ORDER.iter().rposition(|&s| cl_lengths[s] > 0).unwrap() + 1);
+
+    out.write(last as u32, 1);
+    out.write(2, 2);
+    out.write((hlit - 257) as u32, 5);
+    out.write((hdist - 1) as u32, 5);
+    out.write((hclen - 4) as u32, 4);
+    for &symbol in &CODE_LENGTH_ORDER[..hclen] {
+        out.write(cl_lengths[symbol] as u32, 3);
+    }
+    for &(symbol, extra) in &runs {
+        out.write_code(cl_codes[symbol as usize], cl_lengths[symbol as usize]);
+        match symbol {
+            16 => out.write(extra as u32, 2),
+            17 => out.write(extra as u32, 3),
+            18 => out.write(extra as u32, 7),
+            _ => {}
+        }
+    }
+
+    for token in tokens {
+        match *token {
+            Token::Literal(byte) => out.write_code(lit_codes[byte as usize], lit_lengths[byte as usize]),
+            Token::Match { length, distance } => {
+                let ls = length_symbol(length);
+                out.write_code(lit_codes[257 + ls], lit_lengths[257 + ls]);
+                out.write((length - LENGTH_BASE[ls]) as u32, LENGTH_EXTRA[ls] as u32);
+                let ds = distance_symbol(distance);
+                out.write_code(dist_codes[ds], dist_lengths[ds]);
+                out.write((distance - DIST_BASE[ds]) as u32, DIST_EXTRA[ds] as u32);
+            }
+        }
+    }
+    out.write_code(lit_codes[256], lit_lengths[256]);
+}
+
+// Returns (symbol, extra bits value) pairs for the code length alphabet
+fn encode_code_lengths(lengths: &[u8]) -> Vec<(u8, u8)> {
+    let mut runs = Vec::new();
+    let mut i = 0;
+    while i < lengths.len() {
+        let value = lengths[i];
+        let run = lengths[i..].iter().take_while(|&&l| l == value).count();
+        let mut remaining = run;
+
+        if value == 0 {
+            while remaining >= 11 {
+                let n = remaining.min(138);
+                runs.push((18, (n - 11) as u8));
+                remaining -= n;
+            }
+            if remaining >= 3 {
+                runs.push((17, (remaining - 3) as u8));
+                remaining = 0;
+            }
+        } else {
+            runs.push((value, 0));
+            remaining -= 1;
+            while remaining >= 3 {
+                let n = remaining.min(6);
+                runs.push((16, (n - 3) as u8));
+                remaining -= n;
+            }
+        }
+        for _ in 0..remaining {
+            runs.push((value, 0));
+        }
+        i += run;
+    }
+    runs
+}
+
+// Builds Huffman code lengths no longer than `limit` bits. The resulting code is
+// always complete, since decoders reject incomplete codes.
+fn code_lengths(freqs: &[u32], limit: u8) -> Vec<u8> {
+    let mut lengths = vec![0u8; freqs.len()];
+    let used: Vec<usize> = (0..freqs.len()).filter(|&s| freqs[s] > 0).collect();
+
+    match used.len() {
+        0 => {
+            // an unused alphabet still needs a valid code
+            lengths[0] = 1;
+            lengths[1] = 1;
+            return lengths;
+        }
+        1 => {
+            lengths[used[0]] = 1;
+            lengths[if used[0] == 0 { 1 } else { 0 }] = 1;
+            return lengths;
+        }
+        _ => {}
+    }
+
+    // classic Huffman tree: leaves are 0..n, internal nodes are appended
+    let mut parent = vec![usize::MAX; used.len() * 2 - 1];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = used
+        .iter()
+        .enumerate()
+        .map(|(node, &s)| Reverse((freqs[s] as u64, node)))
+        .collect();
+    let mut next = used.len();
+    while heap.len() > 1 {
+        let Reverse((fa, a)) = heap.pop().unwrap();
+        let Reverse((fb, b)) = heap.pop().unwrap();
+        parent[a] = next;
+        parent[b] = next;
+        heap.push(Reverse((fa + fb, next)));
+        next += 1;
+    }
+    let mut depth = vec![0u32; parent.len()];
+    for node in (0..parent.len() - 1).rev() {
+        depth[node] = depth[parent[node]] + 1;
+    }
+
+    // limit the depth, then rebalance until the Kraft sum is exactly one
+    let limit = limit as u32;
+    let mut leaf_lengths: Vec<u32> = (0..used.len()).map(|node| depth[node].min(limit)).collect();
+    let target = 1u64 << limit;
+    let mut kraft: u64 = leaf_lengths.iter().map(|&l| 1u64 << (limit - l)).sum();
+
+    // leaves ordered from least to most frequent
+    let mut order: Vec<usize> = (0..used.len()).collect();
+    order.sort_by_key(|&node| (freqs[used[node]], Reverse(node)));
+
+    while kraft > target {
+        // lengthen the least frequent leaf that is not yet at the limit
+        let node = *order.iter().find(|&&node| leaf_lengths[node] < limit).unwrap();
+        leaf_lengths[node] += 1;
+        kraft -= 1u64 << (limit - leaf_lengths[node]);
+    }
+    while kraft < target {
+        // shorten the most frequent leaf whose gain still fits
+        let node = *order
+            .iter()
+            .rev()
+            .find(|&&node| leaf_lengths[node] > 1 && kraft + (1u64 << (limit - leaf_lengths[node])) <= target)
+            .unwrap();
+        kraft += 1u64 << (limit - leaf_lengths[node]);
+        leaf_lengths[node] -= 1;
+    }
+
+    for (node, &s) in used.iter().enumerate() {
+        lengths[s] = leaf_lengths[node] as u8;
+    }
+    lengths
+}
+
+// Assigns canonical Huffman codes from code lengths (RFC 1951, section 3.2.2)
+fn canonical_codes(lengths: &[u8]) -> Vec<u16> {
+    let max = *lengths.iter().max().unwrap_or(&0) as usize;
+    let mut bl_count = vec![0u16; max + 1];
+    for &l in lengths {
+        if l > 0 {
+            bl_count[l as usize] += 1;
+        }
+    }
+    let mut next_code = vec![0u16; max + 2];
+    let mut code = 0u16;
+    for bits in 1..=max {
+        code = (code + bl_count[bits - 1]) << 1;
+        next_code[bits] = code;
+    }
+    lengths
+        .iter()
+        .map(|&l| {
+            if l == 0 {
+                0
+            } else {
+                let c = next_code[l as usize];
+                next_code[l as usize] += 1;
+                c
+            }
+        })
+        .collect()
+}
+
+// Reference decoder used to verify the compressor and the PNG encoder
+#[cfg(test)]
+pub(crate) fn decompress(stream: &[u8]) -> Vec<u8> {
+    struct BitReader<'a> {
+        data: &'a [u8],
+        pos: usize,
+        bit: u32,
+    }
+
+    impl BitReader<'_> {
+        fn bits(&mut self, count: u32) -> u32 {
+            let mut value = 0;
+            for i in 0..count {
+                let byte = self.data[self.pos];
+                value |= (((byte >> self.bit) & 1) as u32) << i;
+                self.bit += 1;
+                if self.bit == 8 {
+                    self.bit = 0;
+                    self.pos += 1;
+                }
+            }
+            value
+        }
+
+        fn align(&mut self) {
+            if self.bit > 0 {
+                self.bit = 0;
+                self.pos += 1;
+            }
+        }
+
+        // Decodes one symbol by walking canonical codes bit by bit
+        fn symbol(&mut self, lengths: &[u8]) -> usize {
+            let codes = canonical_codes(lengths);
+            let (mut code, mut length) = (0u16, 0u8);
+            loop {
+                code = (code << 1) | self.bits(1) as u16;
+                length += 1;
+                if let Some(s) = (0..lengths.len()).find(|&s| lengths[s] == length && codes[s] == code) {
+                    return s;
+                }
+                assert!(length <= 15, "invalid Huffman code");
+            }
+        }
+    }
+
+    assert_eq!(((stream[0] as u16) << 8 | stream[1] as u16) % 31, 0, "bad zlib header check");
+    let mut reader = BitReader { data: &stream[2..], pos: 0, bit: 0 };
+    let mut out: Vec<u8> = Vec::new();
+
+    loop {
+        let last = reader.bits(1) == 1;
+        let kind = reader.bits(2);
+        if kind == 0 {
+            reader.align();
+            let len = reader.bits(16) as usize;
+            let nlen = reader.bits(16) as usize;
+            assert_eq!(len, !nlen & 0xFFFF);
+            for _ in 0..len {
+                out.push(reader.bits(8) as u8);
+            }
+        } else {
+            let (lit_lengths, dist_lengths) = if kind == 1 {
+                let mut lit = vec![8u8; 288];
+                lit[144..256].fill(9);
+                lit[256..280].fill(7);
+                (lit, vec![5u8; 30])
+            } else {
+                assert_eq!(kind, 2, "reserved block type");
+                let hlit = reader.bits(5) as usize + 257;
+                let hdist = reader.bits(5) as usize + 1;
+                let hclen = reader.bits(4) as usize + 4;
+                let mut cl_lengths = [0u8; 19];
+                for &s in &CODE_LENGTH_ORDER[..hclen] {
+                    cl_lengths[s] = reader.bits(3) as u8;
+                }
+                let mut lengths = Vec::new();
+                while lengths.len() < hlit + hdist {
+                    match reader.symbol(&cl_lengths) {
+                        16 => {
+                            let prev = *lengths.last().unwrap();
+                            let n = 3 + reader.bits(2);
+                            lengths.extend((0..n).map(|_| prev));
+                        }
+                        17 => lengths.extend((0..3 + reader.bits(3)).map(|_| 0)),
+                        18 => lengths.extend((0..11 + reader.bits(7)).map(|_| 0)),
+                        s => lengths.push(s as u8),
+                    }
+                }
+                (lengths[..hlit].to_vec(), lengths[hlit..].to_vec())
+            };
+
+            loop {
+                let symbol = reader.symbol(&lit_lengths);
+                if symbol < 256 {
+                    out.push(symbol as u8);
+                } else if symbol == 256 {
+                    break;
+                } else {
+                    let ls = symbol - 257;
+                    let length = LENGTH_BASE[ls] as usize + reader.bits(LENGTH_EXTRA[ls] as u32) as usize;
+                    let ds = reader.symbol(&dist_lengths);
+                    let distance = DIST_BASE[ds] as usize + reader.bits(DIST_EXTRA[ds] as u32) as usize;
+                    let start = out.len() - distance;
+                    for i in 0..length {
+                        out.push(out[start + i]);
+                    }
+                }
+            }
+        }
+        if last {
+            break;
+        }
+    }
+
+    reader.align();
+    let trailer = &reader.data[reader.pos..reader.pos + 4];
+    assert_eq!(u32::from_be_bytes(trailer.try_into().unwrap()), adler32(&out), "adler32 mismatch");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_data() -> Vec<u8> {
+        let mut data = Vec::new();
+        for i in 0..20_000u32 {
+            data.push((i % 251) as u8);
+            if i % 7 == 0 {
+                data.extend_from_slice(b"framebuffer");
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn test_round_trip_all_levels() {
+        let inputs: Vec<Vec<u8>> = vec![
+            Vec::new(),
+            vec![42],
+            vec![0; 100_000],
+            b"abcabcabcabcabcabcabcabc".to_vec(),
+            sample_data(),
+        ];
+        for data in &inputs {
+            for compression in [Compression::Stored, Compression::Fast, Compression::Best] {
+                let compressed = compress(data, compression);
+                assert_eq!(&decompress(&compressed), data, "{:?} len {}", compression, data.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_levels_shrink_output() {
+        let data = sample_data();
+        let stored = compress(&data, Compression::Stored).len();
+        let fast = compress(&data, Compression::Fast).len();
+        let best = compress(&data, Compression::Best).len();
+        assert!(stored > data.len());
+        assert!(fast < stored);
+        assert!(best <= fast);
+    }
+
+    #[test]
+    fn test_code_lengths_respect_limit() {
+        // Fibonacci frequencies produce a maximally deep unrestricted tree
+        let mut freqs = vec![1u32, 1];
+        while freqs.len() < 30 {
+            let n = freqs.len();
+            freqs.push(freqs[n - 1] + freqs[n - 2]);
+        }
+        let lengths = code_lengths(&freqs, 15);
+        assert!(lengths.iter().all(|&l| (1..=15).contains(&l)));
+        let kraft: f64 = lengths.iter().map(|&l| 0.5f64.powi(l as i32)).sum();
+        assert!((kraft - 1.0).abs() < 1e-12);
+    }
+}